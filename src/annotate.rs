@@ -0,0 +1,110 @@
+// Copyright 2023 Martin Pool
+
+//! Annotations on source code for CI systems, such as GitHub Actions workflow commands.
+
+use clap::ValueEnum;
+
+use crate::outcome::{LabOutcome, SummaryOutcome, TimeoutPolicy};
+use crate::*;
+
+/// A format for annotating missed mutants in CI output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AnnotationFormat {
+    /// Don't print annotations.
+    #[default]
+    None,
+    /// Print GitHub Actions `::warning` workflow commands.
+    Github,
+}
+
+impl AnnotationFormat {
+    /// Choose the format from the command line, or if it's not given, from the environment.
+    ///
+    /// Annotations are turned on automatically when running under GitHub Actions, which
+    /// sets `GITHUB_ACTIONS=true`.
+    pub fn from_arg_or_env(arg: Option<AnnotationFormat>) -> AnnotationFormat {
+        arg.unwrap_or_else(|| {
+            if std::env::var("GITHUB_ACTIONS").map_or(false, |v| v == "true") {
+                AnnotationFormat::Github
+            } else {
+                AnnotationFormat::None
+            }
+        })
+    }
+}
+
+/// Format annotations for all the missed mutants in a lab outcome.
+///
+/// Mutants that timed out are included if timeouts are counted as missed.
+pub fn format_annotations(lab_outcome: &LabOutcome, options: &Options) -> String {
+    if options.annotate == AnnotationFormat::None {
+        return String::new();
+    }
+    let mut s = String::new();
+    for outcome in &lab_outcome.outcomes {
+        let Scenario::Mutant(mutant) = &outcome.scenario else {
+            continue;
+        };
+        let title = match outcome.summary() {
+            SummaryOutcome::MissedMutant => "Missed mutant",
+            SummaryOutcome::Timeout if options.timeout_policy == TimeoutPolicy::Survived => {
+                "Mutant timed out"
+            }
+            _ => continue,
+        };
+        s.push_str(&github_warning(mutant, title));
+        s.push('\n');
+    }
+    s
+}
+
+/// Format a GitHub Actions workflow command to show a warning on the mutated line.
+fn github_warning(mutant: &Mutant, title: &str) -> String {
+    format!(
+        "::warning file={file},line={line},endLine={end_line},title={title}::{message}",
+        file = escape_property(&mutant.source_file.tree_relative_slashes()),
+        line = mutant.span.start.line,
+        end_line = mutant.span.end.line,
+        title = escape_property(title),
+        message = escape_data(&mutant.describe_change()),
+    )
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a workflow command, which also can't contain `:` or `,`.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_workflow_command_values() {
+        assert_eq!(escape_data("100% done\nok"), "100%25 done%0Aok");
+        assert_eq!(escape_property("a::b, c"), "a%3A%3Ab%2C c");
+    }
+
+    #[test]
+    fn github_warning_for_mutant() {
+        let tool = CargoTool::new();
+        let source_tree = tool
+            .find_root(Utf8Path::new("testdata/tree/factorial"))
+            .unwrap();
+        let mutants = walk_tree(&tool, &source_tree, &Options::default())
+            .unwrap()
+            .mutants;
+        assert_eq!(
+            github_warning(&mutants[1], "Missed mutant"),
+            "::warning file=src/bin/factorial.rs,line=7,endLine=13,title=Missed mutant::\
+             replace factorial -> u32 with 0"
+        );
+    }
+}
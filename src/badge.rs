@@ -0,0 +1,92 @@
+// Copyright 2023 Martin Pool
+
+//! Write the mutation score as a badge description for shields.io.
+//!
+//! The format is the shields.io [endpoint
+//! schema](https://shields.io/endpoint), which can be published with the project
+//! and shown in its README.
+
+use std::fs;
+
+use anyhow::Context;
+use serde_json::{json, Value};
+
+use crate::outcome::LabOutcome;
+use crate::*;
+
+/// The label shown on the left of the badge.
+const LABEL: &str = "mutation score";
+
+/// Scores below this are shown in red.
+const YELLOW_THRESHOLD: f64 = 60.0;
+
+/// Scores below this, and not below [YELLOW_THRESHOLD], are shown in yellow;
+/// scores at or above it are green.
+const GREEN_THRESHOLD: f64 = 80.0;
+
+/// Write a badge for the mutation score of this outcome to `path`.
+pub fn write_badge_json(
+    lab_outcome: &LabOutcome,
+    options: &Options,
+    path: &Utf8Path,
+) -> Result<()> {
+    let badge = badge_json(lab_outcome.mutation_score(options.timeout_policy));
+    fs::write(path, serde_json::to_string_pretty(&badge)? + "\n")
+        .with_context(|| format!("write badge json {path:?}"))
+}
+
+/// Describe a badge for a mutation score, which is None if there were no viable mutants.
+fn badge_json(score: Option<f64>) -> Value {
+    let (message, color) = match score {
+        None => ("unknown".to_owned(), "lightgrey"),
+        // Round down, so that the badge only shows 100% if every mutant was caught.
+        Some(score) => (format!("{}%", score.floor()), score_color(score)),
+    };
+    json!({
+        "schemaVersion": 1,
+        "label": LABEL,
+        "message": message,
+        "color": color,
+    })
+}
+
+fn score_color(score: f64) -> &'static str {
+    if score < YELLOW_THRESHOLD {
+        "red"
+    } else if score < GREEN_THRESHOLD {
+        "yellow"
+    } else {
+        "brightgreen"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn badge_for_score() {
+        assert_eq!(
+            badge_json(Some(99.6)),
+            json!({
+                "schemaVersion": 1,
+                "label": "mutation score",
+                "message": "99%",
+                "color": "brightgreen",
+            })
+        );
+        assert_eq!(badge_json(Some(100.0))["message"], "100%");
+        assert_eq!(badge_json(Some(80.0))["color"], "brightgreen");
+        assert_eq!(badge_json(Some(79.9))["color"], "yellow");
+        assert_eq!(badge_json(Some(60.0))["color"], "yellow");
+        assert_eq!(badge_json(Some(59.9))["color"], "red");
+        assert_eq!(badge_json(Some(0.0))["message"], "0%");
+    }
+
+    #[test]
+    fn badge_without_viable_mutants() {
+        let badge = badge_json(None);
+        assert_eq!(badge["message"], "unknown");
+        assert_eq!(badge["color"], "lightgrey");
+    }
+}
@@ -4,9 +4,11 @@
 
 use std::convert::TryInto;
 use std::fmt;
+use std::fs::{self, File};
 
 use anyhow::Context;
 use camino::{Utf8Path, Utf8PathBuf};
+use fs2::FileExt;
 use tempfile::TempDir;
 use tracing::{debug, error, info, trace};
 
@@ -23,9 +25,22 @@ const SOURCE_EXCLUDE: &[&str] = &[
     ".pijul",
     "mutants.out",
     "mutants.out.old",
+    REUSED_BUILD_DIRS,
     "target",
 ];
 
+/// A file in the root of a build directory leaked by `--leak-dirs`, holding the
+/// canonical path of the source tree it was copied from.
+///
+/// An fs2 lock is held on it while the run that made the directory is still going, so
+/// that `--clean` and `--max-log-age` only remove directories from this tree that are no
+/// longer in use.
+pub const LEAKED_DIR_MARKER: &str = ".cargo-mutants-source";
+
+/// The directory, next to `mutants.out`, holding the build directories kept between
+/// runs by `--reuse-build-dir`, in subdirectories numbered by job.
+pub const REUSED_BUILD_DIRS: &str = "mutants.build";
+
 /// A temporary directory initialized with a copy of the source, where mutations can be tested.
 pub struct BuildDir {
     /// The path of the root of the temporary directory.
@@ -40,26 +55,48 @@ pub struct BuildDir {
 
 enum TempDirStrategy {
     Collect(TempDir),
-    Leak,
+    /// Left in the temp directory, with `--leak-dirs`; the lock on the
+    /// [LEAKED_DIR_MARKER] file is held until the run finishes.
+    Leak {
+        _marker: File,
+    },
+    /// Kept for later runs, with `--reuse-build-dir`; the source tree is remembered so
+    /// that directories for more jobs can be refreshed from it.
+    Reuse {
+        source: Utf8PathBuf,
+    },
 }
 
 impl BuildDir {
     /// Make a new build dir, copying from a source directory.
     ///
     /// [SOURCE_EXCLUDE] is excluded.
+    ///
+    /// With `--reuse-build-dir`, the directory for the first job in `mutants.build` is
+    /// used instead of a temporary directory, as described in [BuildDir::reuse].
     pub fn new(source: &Utf8Path, options: &Options, console: &Console) -> Result<BuildDir> {
-        let name_base = format!("cargo-mutants-{}-", source.file_name().unwrap_or(""));
+        let name_base = name_base(source);
         let source_abs = source
             .canonicalize_utf8()
             .expect("canonicalize source path");
+        if options.reuse_build_dir {
+            let path = options
+                .output_in_dir
+                .as_deref()
+                .unwrap_or(source)
+                .join(REUSED_BUILD_DIRS)
+                .join("0");
+            return BuildDir::reuse(&source_abs, path, None, name_base, console);
+        }
         let temp_dir = copy_tree(source, &name_base, SOURCE_EXCLUDE, console)?;
         let path: Utf8PathBuf = temp_dir.path().to_owned().try_into().unwrap();
         fix_manifest(&path.join("Cargo.toml"), &source_abs)?;
         fix_cargo_config(&path, &source_abs)?;
         let strategy = if options.leak_dirs {
+            let marker = write_leaked_dir_marker(&path, &source_abs)?;
             let _ = temp_dir.into_path();
             info!(?path, "Build directory will be leaked for inspection");
-            TempDirStrategy::Leak
+            TempDirStrategy::Leak { _marker: marker }
         } else {
             TempDirStrategy::Collect(temp_dir)
         };
@@ -75,9 +112,26 @@ impl BuildDir {
         self.path.as_path()
     }
 
-    /// Make a copy of this build dir, including its target directory.
-    pub fn copy(&self, console: &Console) -> Result<BuildDir> {
-        let temp_dir = copy_tree(&self.path, &self.name_base, &[], console)?;
+    /// Make a copy of this build dir for another job, including its target directory.
+    ///
+    /// With `--reuse-build-dir`, the job's directory in `mutants.build` is used, and
+    /// only copied from this one if it doesn't exist yet.
+    pub fn copy(&self, job: usize, console: &Console) -> Result<BuildDir> {
+        if let TempDirStrategy::Reuse { source } = &self.strategy {
+            let path = self
+                .path
+                .parent()
+                .expect("reused build dir has a parent")
+                .join(job.to_string());
+            return BuildDir::reuse(
+                source,
+                path,
+                Some(&self.path),
+                self.name_base.clone(),
+                console,
+            );
+        }
+        let temp_dir = copy_tree(&self.path, &self.name_base, &[LEAKED_DIR_MARKER], console)?;
 
         Ok(BuildDir {
             path: temp_dir.path().to_owned().try_into().unwrap(),
@@ -87,6 +141,80 @@ impl BuildDir {
     }
 }
 
+impl BuildDir {
+    /// Make or refresh a build directory that's kept between runs, for
+    /// `--reuse-build-dir`.
+    ///
+    /// If the directory already exists, everything in it except the `target` directory is
+    /// replaced by a fresh copy of the source, so that the source matches the tree and
+    /// any mutation left by an interrupted run is gone, while the dependencies stay
+    /// compiled. Otherwise it's copied from `seed`, another build directory that may
+    /// already have been built, or if there's none, from the source.
+    fn reuse(
+        source: &Utf8Path,
+        path: Utf8PathBuf,
+        seed: Option<&Utf8Path>,
+        name_base: String,
+        console: &Console,
+    ) -> Result<BuildDir> {
+        if path.is_dir() {
+            info!("Reuse build directory {path}");
+            for entry in path
+                .read_dir_utf8()
+                .with_context(|| format!("read {path}"))?
+            {
+                let entry = entry.with_context(|| format!("read {path}"))?;
+                if entry.file_name() == "target" {
+                    continue;
+                }
+                if entry.file_type()?.is_dir() {
+                    fs::remove_dir_all(entry.path())
+                } else {
+                    fs::remove_file(entry.path())
+                }
+                .with_context(|| format!("remove {}", entry.path()))?;
+            }
+            copy_tree_into(source, &path, SOURCE_EXCLUDE, console)?;
+            fix_manifest(&path.join("Cargo.toml"), source)?;
+            fix_cargo_config(&path, source)?;
+            touch_source_files(&path)?;
+        } else {
+            fs::create_dir_all(&path).with_context(|| format!("create {path}"))?;
+            if let Some(seed) = seed {
+                copy_tree_into(seed, &path, &[], console)?;
+            } else {
+                copy_tree_into(source, &path, SOURCE_EXCLUDE, console)?;
+                fix_manifest(&path.join("Cargo.toml"), source)?;
+                fix_cargo_config(&path, source)?;
+            }
+        }
+        Ok(BuildDir {
+            path,
+            name_base,
+            strategy: TempDirStrategy::Reuse {
+                source: source.to_owned(),
+            },
+        })
+    }
+}
+
+/// Record in a leaked build directory which source tree it came from, and lock the
+/// marker for as long as the returned file is open.
+fn write_leaked_dir_marker(path: &Utf8Path, source_abs: &Utf8Path) -> Result<File> {
+    let marker_path = path.join(LEAKED_DIR_MARKER);
+    fs::write(&marker_path, source_abs.as_str()).with_context(|| format!("write {marker_path}"))?;
+    let marker = File::open(&marker_path).with_context(|| format!("open {marker_path}"))?;
+    marker
+        .try_lock_exclusive()
+        .with_context(|| format!("lock {marker_path}"))?;
+    Ok(marker)
+}
+
+/// The prefix for the names of build directories copied from a source directory.
+pub fn name_base(source: &Utf8Path) -> String {
+    format!("cargo-mutants-{}-", source.file_name().unwrap_or(""))
+}
+
 impl fmt::Debug for BuildDir {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BuildDir")
@@ -99,13 +227,57 @@ fn copy_tree(
     from_path: &Utf8Path,
     name_base: &str,
     exclude: &[&str],
-    _console: &Console,
+    console: &Console,
 ) -> Result<TempDir> {
     let temp_dir = tempfile::Builder::new()
         .prefix(&name_base)
         .suffix(".tmp")
         .tempdir()
         .context("create temp dir")?;
+    let path: &Utf8Path = temp_dir
+        .path()
+        .try_into()
+        .context("temp dir is not UTF-8")?;
+    copy_tree_into(from_path, path, exclude, console)?;
+    Ok(temp_dir)
+}
+
+/// Rewrite every file in a reused build directory, outside of `target`, so that it's
+/// newer than anything cargo built there before.
+///
+/// The copy keeps the source files' original mtimes, and the last mutant built in this
+/// directory may have left its output in `target`: without this, cargo could consider that
+/// mutated build still fresh.
+fn touch_source_files(build_path: &Utf8Path) -> Result<()> {
+    touch_tree(build_path, &["target"])
+}
+
+fn touch_tree(dir: &Utf8Path, exclude: &[&str]) -> Result<()> {
+    for entry in dir.read_dir_utf8().with_context(|| format!("read {dir}"))? {
+        let entry = entry.with_context(|| format!("read {dir}"))?;
+        if exclude.contains(&entry.file_name()) {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type().with_context(|| format!("stat {path}"))?;
+        if file_type.is_dir() {
+            touch_tree(path, &[])?;
+        } else if file_type.is_file() {
+            let content = fs::read(path).with_context(|| format!("read {path}"))?;
+            fs::write(path, content).with_context(|| format!("write {path}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a tree into an existing directory, except for files and directories whose names
+/// end with `exclude`.
+fn copy_tree_into(
+    from_path: &Utf8Path,
+    to_path: &Utf8Path,
+    exclude: &[&str],
+    _console: &Console,
+) -> Result<()> {
     // console.start_copy();
     let copy_options = cp_r::CopyOptions::new()
         .after_entry_copied(|path, _ft, _stats| {
@@ -122,7 +294,7 @@ fn copy_tree(
             Ok(!excluded)
         });
     match copy_options
-        .copy_tree(from_path, temp_dir.path())
+        .copy_tree(from_path, to_path)
         .context("copy tree")
     {
         Ok(stats) => {
@@ -132,14 +304,14 @@ fn copy_tree(
             error!(
                 "error copying {} to {}: {:?}",
                 &from_path.to_slash_path(),
-                &temp_dir.path().to_slash_lossy(),
+                &to_path.to_slash_path(),
                 err
             );
             return Err(err);
         }
     }
     // console.finish_copy();
-    Ok(temp_dir)
+    Ok(())
 }
 
 #[cfg(test)]
@@ -19,6 +19,14 @@ use crate::source::Package;
 use crate::tool::Tool;
 use crate::*;
 
+/// Messages from Cargo showing that it failed because another Cargo process held a
+/// lock, such as the lock on the package cache in `CARGO_HOME`.
+const CARGO_LOCK_ERRORS: &[&str] = &[
+    "failed to acquire package cache lock",
+    "could not acquire package cache lock",
+    "failed to lock file",
+];
+
 #[derive(Debug)]
 pub struct CargoTool {
     // environment is currently constant across all invocations.
@@ -74,8 +82,9 @@ impl Tool for CargoTool {
             .exec()
             .context("run cargo metadata")?;
 
+        let workspace_packages = metadata.workspace_packages();
         let mut r = Vec::new();
-        for package_metadata in &metadata.workspace_packages() {
+        for package_metadata in &workspace_packages {
             check_interrupted()?;
             let _span = debug_span!("package", name = %package_metadata.name).entered();
             debug!(manifest_path = %package_metadata.manifest_path, "walk package");
@@ -87,6 +96,12 @@ impl Tool for CargoTool {
             let package = Arc::new(Package {
                 name: package_metadata.name.clone(),
                 relative_manifest_path,
+                workspace_dependencies: package_metadata
+                    .dependencies
+                    .iter()
+                    .filter(|dep| workspace_packages.iter().any(|p| p.name == dep.name))
+                    .map(|dep| dep.name.clone())
+                    .collect(),
             });
             for source_path in direct_package_sources(source_root_path, package_metadata)? {
                 check_interrupted()?;
@@ -119,9 +134,17 @@ impl Tool for CargoTool {
         &self,
         _scenario: &Scenario,
         _phase: Phase,
-        _options: &Options,
+        options: &Options,
     ) -> Result<Vec<(String, String)>> {
-        Ok(self.env.clone())
+        let mut env = self.env.clone();
+        if let Some(test_threads) = options.test_threads {
+            env.push(("RUST_TEST_THREADS".to_owned(), test_threads.to_string()));
+        }
+        Ok(env)
+    }
+
+    fn is_transient_failure(&self, output: &str) -> bool {
+        CARGO_LOCK_ERRORS.iter().any(|error| output.contains(error))
     }
 }
 
@@ -151,9 +174,20 @@ fn cargo_argv(
     } else {
         cargo_args.push("--workspace".to_string());
     }
+    if let Some(profile) = &options.profile {
+        cargo_args.push(format!("--profile={profile}"));
+    }
     cargo_args.extend(options.additional_cargo_args.iter().cloned());
     if phase == Phase::Test {
         cargo_args.extend(options.additional_cargo_test_args.iter().cloned());
+        if let Some(test_threads) = options.test_threads {
+            // Arguments after `--` go to the test binaries; there might already be one
+            // from the user's arguments.
+            if !options.additional_cargo_test_args.iter().any(|a| a == "--") {
+                cargo_args.push("--".to_owned());
+            }
+            cargo_args.push(format!("--test-threads={test_threads}"));
+        }
     }
     cargo_args
 }
@@ -258,6 +292,20 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn lock_errors_are_transient_failures() {
+        let tool = CargoTool::new();
+        assert!(tool.is_transient_failure(
+            "error: failed to download from `https://crates.io/api/v1/crates/x/1.0.0/download`\n\n\
+             Caused by:\n  failed to acquire package cache lock\n"
+        ));
+        assert!(!tool.is_transient_failure("error[E0308]: mismatched types\n --> src/lib.rs:3:5\n"));
+        assert!(!tool.is_transient_failure(
+            "    Blocking waiting for file lock on package cache\n\
+             test result: FAILED. 0 passed; 1 failed\n"
+        ));
+    }
+
     #[test]
     fn generate_cargo_args_for_baseline_with_default_options() {
         let options = Options::default();
@@ -288,6 +336,7 @@ mod test {
         let package = Arc::new(Package {
             name: package_name.to_owned(),
             relative_manifest_path: relative_manifest_path.clone(),
+            workspace_dependencies: Vec::new(),
         });
         let build_manifest_path = build_dir.join(relative_manifest_path);
         assert_eq!(
@@ -350,6 +399,52 @@ mod test {
         );
     }
 
+    #[test]
+    fn profile_is_passed_to_every_phase() {
+        let mut options = Options::default();
+        let build_dir = Utf8Path::new("/tmp/buildXYZ");
+        options.profile = Some("release".to_owned());
+        options
+            .additional_cargo_args
+            .extend(["--all-features".to_owned()]);
+        for phase in [Phase::Check, Phase::Build, Phase::Test] {
+            let argv = cargo_argv(build_dir, None, phase, &options);
+            assert_eq!(
+                argv[argv.len() - 3..],
+                ["--workspace", "--profile=release", "--all-features"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_threads_are_passed_to_test_binaries() {
+        let mut options = Options::default();
+        let build_dir = Utf8Path::new("/tmp/buildXYZ");
+        options.test_threads = Some(1);
+        assert_eq!(
+            cargo_argv(build_dir, None, Phase::Build, &options)[1..],
+            ["build", "--tests", "--workspace"]
+        );
+        assert_eq!(
+            cargo_argv(build_dir, None, Phase::Test, &options)[1..],
+            ["test", "--workspace", "--", "--test-threads=1"]
+        );
+        options
+            .additional_cargo_test_args
+            .extend(["--lib", "--", "--nocapture"].iter().map(|s| s.to_string()));
+        assert_eq!(
+            cargo_argv(build_dir, None, Phase::Test, &options)[1..],
+            [
+                "test",
+                "--workspace",
+                "--lib",
+                "--",
+                "--nocapture",
+                "--test-threads=1"
+            ]
+        );
+    }
+
     #[test]
     fn error_opening_outside_of_crate() {
         CargoTool::new().find_root(Utf8Path::new("/")).unwrap_err();
@@ -0,0 +1,216 @@
+// Copyright 2023 Martin Pool
+
+//! Remove output and scratch directories left by earlier runs.
+//!
+//! Each run keeps the output of the run before it as `mutants.out.old`, and with
+//! `--leak-dirs` the build directories are left in the temp directory, so they can
+//! accumulate over many runs. With `--reuse-build-dir` they're kept in `mutants.build`.
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use fs2::FileExt;
+use tracing::{debug, info};
+
+use crate::build_dir;
+use crate::output::{LockFile, OUTDIR_NAME, ROTATED_NAME};
+use crate::*;
+
+/// Remove the output directories and reused build directories within `output_in_dir`,
+/// and any build directories leaked by earlier runs on the source tree.
+///
+/// If another run is writing to the output directory, this waits for it to finish.
+///
+/// Returns the paths that were removed.
+pub fn clean(source: &Utf8Path, output_in_dir: &Utf8Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    let output_dir = output_in_dir.join(OUTDIR_NAME);
+    let _lock = if output_dir.is_dir() {
+        Some(LockFile::acquire_lock(output_dir.as_std_path())?)
+    } else {
+        None
+    };
+    for path in [
+        output_dir,
+        output_in_dir.join(ROTATED_NAME),
+        output_in_dir.join(build_dir::REUSED_BUILD_DIRS),
+    ] {
+        if path.is_dir() {
+            remove_dir(path.as_std_path())?;
+            removed.push(path.into_std_path_buf());
+        }
+    }
+    for (path, _mtime) in leaked_build_dirs(source)? {
+        remove_dir(&path)?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Remove leaked build directories for this source tree, and the output of the
+/// previous run in `mutants.out.old`, if they were last modified more than `max_age`
+/// ago.
+pub fn prune_older_than(
+    source: &Utf8Path,
+    output_in_dir: &Utf8Path,
+    max_age: Duration,
+) -> Result<()> {
+    let now = SystemTime::now();
+    let too_old = |mtime: SystemTime| now.duration_since(mtime).map_or(false, |age| age > max_age);
+    let rotated = output_in_dir.join(ROTATED_NAME);
+    if rotated.is_dir() && too_old(modified(rotated.as_std_path())?) {
+        info!("Remove old output {rotated}");
+        remove_dir(rotated.as_std_path())?;
+    }
+    for (path, mtime) in leaked_build_dirs(source)? {
+        if too_old(mtime) {
+            info!("Remove old build directory {}", path.display());
+            remove_dir(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Find build directories for this source tree left in the temp directory by earlier
+/// runs with `--leak-dirs`, with the time they were last modified.
+///
+/// Only directories whose [build_dir::LEAKED_DIR_MARKER] names this tree are returned,
+/// and not those still locked by a run that's in progress, so that the scratch
+/// directories of concurrent runs, or of other trees with the same name, are left alone.
+fn leaked_build_dirs(source: &Utf8Path) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let source = source
+        .canonicalize_utf8()
+        .with_context(|| format!("canonicalize {source:?}"))?;
+    let name_base = build_dir::name_base(&source);
+    let temp_dir = env::temp_dir();
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(&temp_dir).with_context(|| format!("list {temp_dir:?}"))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with(&name_base)
+            && name.ends_with(".tmp")
+            && entry.file_type()?.is_dir()
+            && is_unused_leaked_dir(&entry.path(), &source)
+        {
+            let path = entry.path();
+            debug!(?path, "found leaked build directory");
+            dirs.push((path.clone(), modified(&path)?));
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// True if this build directory was leaked by a run on `source` that has finished.
+///
+/// Directories without a readable marker aren't known to be ours, so they're kept.
+fn is_unused_leaked_dir(path: &Path, source: &Utf8Path) -> bool {
+    let marker_path = path.join(build_dir::LEAKED_DIR_MARKER);
+    match fs::read_to_string(&marker_path) {
+        Ok(marker_source) if marker_source == source.as_str() => {}
+        Ok(_) => return false,
+        Err(err) => {
+            debug!(
+                ?marker_path,
+                ?err,
+                "can't read leaked build directory marker"
+            );
+            return false;
+        }
+    }
+    match File::open(&marker_path).and_then(|marker| marker.try_lock_exclusive()) {
+        Ok(()) => true,
+        Err(err) => {
+            debug!(?path, ?err, "leaked build directory is still in use");
+            false
+        }
+    }
+}
+
+fn modified(path: &Path) -> Result<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("read modification time of {path:?}"))
+}
+
+fn remove_dir(path: &Path) -> Result<()> {
+    fs::remove_dir_all(path).with_context(|| format!("remove {path:?}"))
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn clean_removes_output_directories() {
+        let tmp = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir_all(root.join("mutants.out/log")).unwrap();
+        fs::create_dir_all(root.join("mutants.out.old/log")).unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+        let removed = clean(root, root).unwrap();
+        assert_eq!(
+            removed,
+            [
+                root.join("mutants.out").into_std_path_buf(),
+                root.join("mutants.out.old").into_std_path_buf()
+            ]
+        );
+        assert!(!root.join("mutants.out").exists());
+        assert!(!root.join("mutants.out.old").exists());
+        assert!(root.join("Cargo.toml").exists());
+        assert_eq!(clean(root, root).unwrap(), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn clean_removes_only_finished_leaked_dirs_from_this_tree() {
+        let tmp = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        let source = root.canonicalize_utf8().unwrap();
+        let name_base = build_dir::name_base(root);
+        let leaked_dir = |suffix: &str, marker: Option<&str>| {
+            let path = env::temp_dir().join(format!("{name_base}{suffix}.tmp"));
+            fs::create_dir_all(&path).unwrap();
+            if let Some(marker) = marker {
+                fs::write(path.join(build_dir::LEAKED_DIR_MARKER), marker).unwrap();
+            }
+            path
+        };
+        let finished = leaked_dir("finished", Some(source.as_str()));
+        let unmarked = leaked_dir("unmarked", None);
+        let other_tree = leaked_dir("other", Some("/some/other/tree"));
+        let running = leaked_dir("running", Some(source.as_str()));
+        let lock = File::open(running.join(build_dir::LEAKED_DIR_MARKER)).unwrap();
+        lock.try_lock_exclusive().unwrap();
+
+        let removed = clean(root, root).unwrap();
+
+        drop(lock);
+        for path in [&unmarked, &other_tree, &running] {
+            assert!(path.is_dir(), "{path:?} should be kept");
+            fs::remove_dir_all(path).unwrap();
+        }
+        assert!(!finished.exists());
+        assert_eq!(removed, [finished]);
+    }
+
+    #[test]
+    fn prune_keeps_recent_output() {
+        let tmp = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir_all(root.join("mutants.out.old/log")).unwrap();
+        prune_older_than(root, root, Duration::from_secs(3600)).unwrap();
+        assert!(root.join("mutants.out.old").is_dir());
+        std::thread::sleep(Duration::from_millis(50));
+        prune_older_than(root, root, Duration::ZERO).unwrap();
+        assert!(!root.join("mutants.out.old").exists());
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2023 Martin Pool
+
+//! Compare the outcomes of this run with those of an earlier run, to show which
+//! mutants are newly missed, newly caught, or no longer exist.
+//!
+//! Also, list the mutants that survived an earlier run, for review without running
+//! the tests again.
+//!
+//! Mutants are matched by their [id](Mutant::id), which doesn't depend on line numbers.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs::read_to_string;
+
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::console::plural;
+use crate::outcome::{LabOutcome, SummaryOutcome};
+use crate::*;
+
+/// A mutant from an earlier run, as recorded in its `outcomes.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviousMutant {
+    /// The mutant as serialized in the report.
+    mutant: Value,
+    /// The summary outcome, like `"CaughtMutant"`.
+    summary: String,
+}
+
+impl PreviousMutant {
+    fn missed(&self) -> bool {
+        self.summary == "MissedMutant"
+    }
+
+    /// Describe the mutant from its recorded fields, like `src/lib.rs:3: factorial`.
+    fn describe(&self) -> String {
+        let field = |name: &str| {
+            self.mutant
+                .get(name)
+                .map(|value| match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_default()
+        };
+        format!(
+            "{}:{}: {} (id {})",
+            field("file"),
+            field("line"),
+            field("function"),
+            field("id")
+        )
+    }
+}
+
+/// Read the mutants and their outcomes, by id, from the `outcomes.json` of an earlier run.
+pub fn read_previous_outcomes(path: &Utf8Path) -> Result<HashMap<String, PreviousMutant>> {
+    let json = read_to_string(path).with_context(|| format!("read outcomes {path:?}"))?;
+    let report: Value =
+        serde_json::from_str(&json).with_context(|| format!("parse outcomes {path:?}"))?;
+    previous_outcomes_in_report(&report).with_context(|| format!("read outcomes from {path:?}"))
+}
+
+fn previous_outcomes_in_report(report: &Value) -> Result<HashMap<String, PreviousMutant>> {
+    let outcomes = report
+        .get("outcomes")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("report has no outcomes: compare with an outcomes.json"))?;
+    outcomes
+        .iter()
+        // The baseline scenario is just a string; mutants are an object.
+        .filter_map(|outcome| Some((outcome.get("scenario")?.get("Mutant")?, outcome)))
+        .map(|(mutant, outcome)| {
+            let id = mutant
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("mutant has no id: {mutant}"))?;
+            let summary = outcome
+                .get("summary")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("outcome has no summary: {outcome}"))?;
+            Ok((
+                id.to_owned(),
+                PreviousMutant {
+                    mutant: mutant.clone(),
+                    summary: summary.to_owned(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// The differences between the outcomes of an earlier run and this one.
+#[derive(Debug, Default, Serialize)]
+pub struct Comparison {
+    /// Mutants missed in this run that were not missed before, including new mutants.
+    pub newly_missed: Vec<Mutant>,
+    /// Mutants caught in this run that were missed before.
+    pub newly_caught: Vec<Mutant>,
+    /// Mutants in the earlier run that were not tested in this run.
+    pub removed: Vec<Value>,
+    #[serde(skip)]
+    removed_descriptions: Vec<String>,
+}
+
+impl Comparison {
+    /// Compare the outcomes of this run with those of an earlier run.
+    pub fn new(previous: &HashMap<String, PreviousMutant>, lab_outcome: &LabOutcome) -> Comparison {
+        let mut comparison = Comparison::default();
+        let mut current_ids = HashSet::new();
+        for outcome in &lab_outcome.outcomes {
+            let Scenario::Mutant(mutant) = &outcome.scenario else {
+                continue;
+            };
+            current_ids.insert(mutant.id.as_str());
+            let previous = previous.get(&mutant.id);
+            match outcome.summary() {
+                SummaryOutcome::MissedMutant if !previous.map_or(false, PreviousMutant::missed) => {
+                    comparison.newly_missed.push(mutant.clone())
+                }
+                SummaryOutcome::CaughtMutant if previous.map_or(false, PreviousMutant::missed) => {
+                    comparison.newly_caught.push(mutant.clone())
+                }
+                _ => (),
+            }
+        }
+        let sort_key = |mutant: &Mutant| {
+            (
+                mutant.source_file.tree_relative_slashes(),
+                mutant.span.start.line,
+                mutant.to_string(),
+            )
+        };
+        comparison.newly_missed.sort_by_cached_key(sort_key);
+        comparison.newly_caught.sort_by_cached_key(sort_key);
+        for (_id, removed) in previous
+            .iter()
+            .filter(|(id, _)| !current_ids.contains(id.as_str()))
+            .sorted_by_cached_key(|(_, removed)| removed.describe())
+        {
+            comparison.removed.push(removed.mutant.clone());
+            comparison.removed_descriptions.push(removed.describe());
+        }
+        comparison
+    }
+
+    /// Describe the differences for people, with one mutant per line.
+    pub fn format(&self, previous_path: &Utf8Path) -> String {
+        let mut s = format!("Compared to {previous_path}:\n");
+        let mut section = |title: &str, lines: Vec<String>| {
+            writeln!(s, "{} {title}", lines.len()).unwrap();
+            for line in lines {
+                writeln!(s, "    {line}").unwrap();
+            }
+        };
+        section(
+            "newly missed",
+            self.newly_missed.iter().map(Mutant::to_string).collect(),
+        );
+        section(
+            "newly caught",
+            self.newly_caught.iter().map(Mutant::to_string).collect(),
+        );
+        section("no longer exist", self.removed_descriptions.clone());
+        s
+    }
+}
+
+/// The mutants missed in an earlier run, matched to the mutants in the tree now so that
+/// their diffs can be shown.
+#[derive(Debug, Default)]
+pub struct Survivors {
+    /// Missed mutants that are still in the tree, in the order they were discovered.
+    pub found: Vec<Mutant>,
+    /// Descriptions of missed mutants that are no longer in the tree, for example
+    /// because the code changed.
+    pub gone: Vec<String>,
+}
+
+impl Survivors {
+    pub fn new(previous: &HashMap<String, PreviousMutant>, mutants: Vec<Mutant>) -> Survivors {
+        let current_ids: HashSet<String> = mutants.iter().map(|m| m.id.clone()).collect();
+        let found = mutants
+            .into_iter()
+            .filter(|mutant| {
+                previous
+                    .get(&mutant.id)
+                    .map_or(false, PreviousMutant::missed)
+            })
+            .collect();
+        let gone = previous
+            .iter()
+            .filter(|(id, previous)| previous.missed() && !current_ids.contains(id.as_str()))
+            .map(|(_, previous)| previous.describe())
+            .sorted()
+            .collect();
+        Survivors { found, gone }
+    }
+
+    /// Describe the survivors for people, grouped by file, with the diff of each.
+    pub fn format(&self, report_path: &Utf8Path) -> String {
+        let mut s = format!(
+            "{} in {report_path}\n",
+            plural(self.found.len() + self.gone.len(), "survivor")
+        );
+        for (file, mutants) in &self
+            .found
+            .iter()
+            .group_by(|mutant| mutant.source_file.tree_relative_slashes())
+        {
+            let mutants = mutants.collect_vec();
+            writeln!(s, "\n{file}: {}", plural(mutants.len(), "survivor")).unwrap();
+            for mutant in mutants {
+                write!(s, "\n{mutant}\n{}", mutant.diff()).unwrap();
+            }
+        }
+        if !self.gone.is_empty() {
+            writeln!(s, "\nNot found in the tree now: {}", self.gone.len()).unwrap();
+            for description in &self.gone {
+                writeln!(s, "    {description}").unwrap();
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn read_outcomes_by_id() {
+        let report = json!({
+            "outcomes": [
+                { "scenario": "Baseline", "summary": "Success" },
+                {
+                    "scenario": { "Mutant": {
+                        "id": "6f793c20f53f", "file": "src/lib.rs", "line": 3,
+                        "function": "factorial"
+                    } },
+                    "summary": "MissedMutant"
+                },
+                {
+                    "scenario": { "Mutant": { "id": "0a6e48aa3db6" } },
+                    "summary": "CaughtMutant"
+                },
+            ],
+        });
+        let previous = previous_outcomes_in_report(&report).unwrap();
+        assert_eq!(
+            previous.keys().sorted().collect_vec(),
+            ["0a6e48aa3db6", "6f793c20f53f"]
+        );
+        assert!(previous["6f793c20f53f"].missed());
+        assert!(!previous["0a6e48aa3db6"].missed());
+        assert_eq!(
+            previous["6f793c20f53f"].describe(),
+            "src/lib.rs:3: factorial (id 6f793c20f53f)"
+        );
+    }
+
+    #[test]
+    fn mutant_lists_have_no_outcomes() {
+        let err = previous_outcomes_in_report(&json!([{ "id": "6f793c20f53f" }])).unwrap_err();
+        assert!(err.to_string().contains("outcomes.json"));
+        assert!(previous_outcomes_in_report(&json!({
+            "outcomes": [{ "scenario": { "Mutant": { "id": "6f793c20f53f" } } }]
+        }))
+        .is_err());
+    }
+}
@@ -6,14 +6,25 @@
 //! and after finding the source tree, because these together
 //! determine its location.
 //!
+//! The config file is found by the first of these that applies:
+//!
+//! 1. The `--config` command line option names a file.
+//! 2. The `CARGO_MUTANTS_CONFIG_DIR` environment variable names a directory
+//!    containing `mutants.toml`.
+//! 3. `.cargo/mutants.toml` in the directory given by `-d` (or the current
+//!    directory), or in any of its parents up to the root of the source tree.
+//!
 //! The config file is then merged in to the [Options].
 
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::fs::read_to_string;
 
-use anyhow::Context;
-use camino::Utf8Path;
+use anyhow::{bail, Context};
+use camino::{Utf8Path, Utf8PathBuf};
+use itertools::Itertools;
 use serde::Deserialize;
+use tracing::debug;
 
 use crate::Result;
 
@@ -34,28 +45,225 @@ pub struct Config {
     pub exclude_re: Vec<String>,
     /// Examine only mutants matching these regexps.
     pub examine_re: Vec<String>,
+    /// Don't mutate items marked `#[doc(hidden)]`.
+    pub exclude_doc_hidden: bool,
+    /// Replace the bodies of functions that always panic or loop forever.
+    pub mutate_diverging_fns: bool,
+    /// Don't mutate functions with type parameters.
+    pub exclude_generics: bool,
     /// Pass extra args to every cargo invocation.
     pub additional_cargo_args: Vec<String>,
     /// Pass extra args to cargo test.
     pub additional_cargo_test_args: Vec<String>,
+    /// Build and test with this cargo profile.
+    pub profile: Option<String>,
+    /// Run this shell command in the build directory before the baseline test.
+    pub baseline_command: Option<String>,
     /// Minimum test timeout, in seconds, as a floor on the autoset value.
     pub minimum_test_timeout: Option<f64>,
+    /// Generate mutants of these genres, as well as those enabled by default.
+    pub genres: Vec<String>,
+    /// Skip impls of these traits, instead of the default set.
+    pub skip_traits: Option<Vec<String>>,
+    /// Generate at most this many mutants from each function.
+    pub max_mutants_per_function: Option<usize>,
+    /// Don't generate `Default::default()` for types with these names.
+    pub no_default_types: Vec<String>,
+    /// Test timeouts in seconds for mutants of particular genres, keyed by genre name.
+    pub timeout_per_genre: BTreeMap<String, f64>,
 }
 
 impl Config {
-    pub fn read_file(path: &Utf8Path) -> Result<Config> {
+    /// Read a config file, merging in the named profile if one is given.
+    pub fn read_file(path: &Utf8Path, profile: Option<&str>) -> Result<Config> {
         let toml = read_to_string(path).with_context(|| format!("read config {path:?}"))?;
-        toml::de::from_str(&toml).with_context(|| format!("parse toml from {path:?}"))
+        Config::parse(&toml, profile).with_context(|| format!("parse toml from {path:?}"))
+    }
+
+    /// Read the config from a file named by the user, failing if it does not exist.
+    pub fn read_explicit_file(path: &Utf8Path, profile: Option<&str>) -> Result<Config> {
+        if !path.is_file() {
+            bail!("config file {path:?} does not exist");
+        }
+        Config::read_file(path, profile)
     }
 
-    /// Read the config from a tree's `.cargo/mutants.toml`, and return a default (empty)
-    /// Config is the file does not exist.
-    pub fn read_tree_config(source_tree_root: &Utf8Path) -> Result<Config> {
-        let path = source_tree_root.join(".cargo").join("mutants.toml");
-        if path.exists() {
-            Config::read_file(&path)
-        } else {
-            Ok(Config::default())
+    /// Parse the text of a config file.
+    ///
+    /// Named profiles are tables like `[profiles.quick]`. If a profile is selected, each
+    /// key it sets replaces the same key in the base config. Every profile is checked,
+    /// even if it's not selected, so that mistakes are found early.
+    fn parse(toml: &str, profile: Option<&str>) -> Result<Config> {
+        let mut table: toml::Table = toml::de::from_str(toml)?;
+        let profiles = match table.remove("profiles") {
+            None => toml::Table::new(),
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => bail!("profiles should be a table of named profiles"),
+        };
+        for (name, value) in &profiles {
+            let toml::Value::Table(overlay) = value else {
+                bail!("config profile {name:?} should be a table");
+            };
+            if overlay.contains_key("profiles") {
+                bail!("config profile {name:?} can't contain more profiles");
+            }
+            toml::Value::Table(overlay.clone())
+                .try_into::<Config>()
+                .with_context(|| format!("parse config profile {name:?}"))?;
         }
+        if let Some(name) = profile {
+            let Some(toml::Value::Table(overlay)) = profiles.get(name) else {
+                bail!(
+                    "config profile {name:?} is not defined; the config has {}",
+                    if profiles.is_empty() {
+                        "no profiles".to_owned()
+                    } else {
+                        format!(
+                            "profiles {}",
+                            profiles.keys().map(|k| format!("{k:?}")).join(", ")
+                        )
+                    }
+                );
+            };
+            debug!(?name, "merge config profile");
+            table.extend(overlay.clone());
+        }
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+}
+
+/// Find the config file to use when none is given on the command line, or return None
+/// if there is no config file.
+///
+/// `config_dir` is the value of `CARGO_MUTANTS_CONFIG_DIR`, if set: it must contain
+/// `mutants.toml`. Otherwise, look for `.cargo/mutants.toml` from `start_dir` up to
+/// `source_tree_root`.
+pub fn find_config_file(
+    config_dir: Option<&Utf8Path>,
+    start_dir: &Utf8Path,
+    source_tree_root: &Utf8Path,
+) -> Result<Option<Utf8PathBuf>> {
+    if let Some(config_dir) = config_dir {
+        let path = config_dir.join("mutants.toml");
+        if !path.is_file() {
+            bail!("config file {path:?} from CARGO_MUTANTS_CONFIG_DIR does not exist");
+        }
+        return Ok(Some(path));
+    }
+    for path in tree_config_search_path(start_dir, source_tree_root) {
+        debug!("look for config in {path}");
+        if path.is_file() {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Return the paths where a tree config file might be, in order of preference:
+/// `.cargo/mutants.toml` in `start_dir` and then each parent, stopping at
+/// `source_tree_root`.
+///
+/// If `start_dir` is not inside `source_tree_root`, only the root is searched.
+fn tree_config_search_path(start_dir: &Utf8Path, source_tree_root: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let config_in = |dir: &Utf8Path| dir.join(".cargo").join("mutants.toml");
+    // Compare canonical paths, so that relative paths and symlinks don't matter, but
+    // return paths within the root as given.
+    let (Ok(start_dir), Ok(canonical_root)) = (
+        start_dir.canonicalize_utf8(),
+        source_tree_root.canonicalize_utf8(),
+    ) else {
+        return vec![config_in(source_tree_root)];
+    };
+    let Ok(relative) = start_dir.strip_prefix(&canonical_root) else {
+        return vec![config_in(source_tree_root)];
+    };
+    relative
+        .ancestors()
+        .map(|rel| config_in(&source_tree_root.join(rel)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::create_dir_all;
+
+    use super::*;
+
+    static PROFILES_TOML: &str = r#"
+        exclude_globs = ["src/generated.rs"]
+        minimum_test_timeout = 20
+
+        [profiles.quick]
+        examine_globs = ["src/lib.rs"]
+        minimum_test_timeout = 5
+
+        [profiles.thorough]
+        genres = ["statement-delete"]
+    "#;
+
+    #[test]
+    fn profile_keys_replace_base_keys() {
+        let config = Config::parse(PROFILES_TOML, Some("quick")).unwrap();
+        assert_eq!(config.examine_globs, ["src/lib.rs"]);
+        assert_eq!(config.exclude_globs, ["src/generated.rs"]);
+        assert_eq!(config.minimum_test_timeout, Some(5.0));
+        assert!(config.genres.is_empty());
+
+        let config = Config::parse(PROFILES_TOML, None).unwrap();
+        assert!(config.examine_globs.is_empty());
+        assert_eq!(config.minimum_test_timeout, Some(20.0));
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let err = Config::parse(PROFILES_TOML, Some("slow")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"config profile "slow" is not defined; the config has profiles "quick", "thorough""#
+        );
+        let err = Config::parse("", Some("quick")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"config profile "quick" is not defined; the config has no profiles"#
+        );
+    }
+
+    #[test]
+    fn unknown_keys_in_unselected_profiles_are_errors() {
+        let toml = "[profiles.quick]\nexamine = true\n";
+        let err = Config::parse(toml, None).unwrap_err();
+        assert_eq!(err.to_string(), r#"parse config profile "quick""#);
+    }
+
+    #[test]
+    fn search_path_walks_up_to_tree_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        create_dir_all(root.join("a/b")).unwrap();
+        assert_eq!(
+            tree_config_search_path(&root.join("a/b"), root),
+            [
+                root.join("a/b/.cargo/mutants.toml"),
+                root.join("a/.cargo/mutants.toml"),
+                root.join(".cargo/mutants.toml"),
+            ]
+        );
+        assert_eq!(
+            tree_config_search_path(root, root),
+            [root.join(".cargo/mutants.toml")]
+        );
+    }
+
+    #[test]
+    fn search_path_outside_tree_only_has_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap().join("root");
+        create_dir_all(&root).unwrap();
+        let other = Utf8Path::from_path(tmp.path()).unwrap().join("other");
+        create_dir_all(&other).unwrap();
+        assert_eq!(
+            tree_config_search_path(&other, &root),
+            [root.join(".cargo/mutants.toml")]
+        );
     }
 }
@@ -3,6 +3,7 @@
 //! Print messages and progress bars on the terminal.
 
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::fmt::Write;
 use std::fs::File;
 use std::sync::{Arc, Mutex};
@@ -10,16 +11,27 @@ use std::time::{Duration, Instant};
 
 use ::console::{style, StyledObject};
 use camino::Utf8Path;
+use clap::ValueEnum;
+use itertools::Itertools;
 
 use tracing::Level;
 use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::prelude::*;
 
-use crate::outcome::{LabOutcome, SummaryOutcome};
+use crate::log_file::first_compiler_error;
+use crate::outcome::{LabOutcome, SummaryOutcome, TimeoutPolicy};
 use crate::*;
 
 static COPY_MESSAGE: &str = "Copy source to scratch directory";
 
+/// The answer to whether to test a mutant, with `--confirm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    Test,
+    Skip,
+    Quit,
+}
+
 /// An interface to the console for the rest of cargo-mutants.
 ///
 /// This wraps the Nutmeg view and model.
@@ -29,6 +41,9 @@ pub struct Console {
 
     /// The `mutants.out/debug.log` file, if it's open yet.
     debug_log: Arc<Mutex<Option<File>>>,
+
+    /// Don't show messages meant for people, because stdout is used for other output.
+    quiet: bool,
 }
 
 impl Console {
@@ -36,6 +51,22 @@ impl Console {
         Console {
             view: Arc::new(nutmeg::View::new(LabModel::default(), nutmeg_options())),
             debug_log: Arc::new(Mutex::new(None)),
+            quiet: false,
+        }
+    }
+
+    /// Make a console for `--output-format jsonl`, which leaves stdout for the results.
+    ///
+    /// Progress bars and messages about each mutant are not shown, and reports asked for
+    /// by other options, as well as log messages, go to stderr.
+    pub fn new_for_json_lines() -> Console {
+        let options = nutmeg_options()
+            .progress_enabled(false)
+            .destination(nutmeg::Destination::Stderr);
+        Console {
+            view: Arc::new(nutmeg::View::new(LabModel::default(), options)),
+            debug_log: Arc::new(Mutex::new(None)),
+            quiet: true,
         }
     }
 
@@ -108,7 +139,20 @@ impl Console {
             );
         }
         s.push('\n');
-        self.view.message(&s);
+        self.message(&s);
+    }
+
+    /// Update that a mutant wasn't tested, because earlier mutants in its function were all
+    /// caught.
+    pub fn mutant_assumed_caught(&self, mutant: &Mutant, options: &Options) {
+        self.view.update(|model| model.mutants_done += 1);
+        if options.print_caught {
+            self.message(&format!(
+                "{} ... {}\n",
+                style_mutant(mutant),
+                style("assumed caught").green()
+            ));
+        }
     }
 
     /// Update that a test timeout was auto-set.
@@ -190,8 +234,49 @@ impl Console {
         ));
     }
 
+    /// Show a mutant and its diff, and ask whether to test it.
+    ///
+    /// The progress bar is hidden while waiting for an answer. If standard input is
+    /// closed, this returns [Confirmation::Quit].
+    pub fn confirm_mutant(&self, mutant: &Mutant) -> Result<Confirmation> {
+        self.view.suspend();
+        let term = ::console::Term::stdout();
+        let result = (|| {
+            term.write_line(&style_mutant(mutant))?;
+            term.write_line(&mutant.diff())?;
+            loop {
+                term.write_str(&format!(
+                    "{} [{}]est, [{}]kip, [{}]uit? ",
+                    style("Test this mutant?").bold(),
+                    style("t").bold(),
+                    style("s").bold(),
+                    style("q").bold(),
+                ))?;
+                let mut answer = String::new();
+                if std::io::stdin().read_line(&mut answer)? == 0 {
+                    term.write_line("")?;
+                    return Ok(Confirmation::Quit);
+                }
+                if let Some(confirmation) = parse_confirmation(&answer) {
+                    return Ok(confirmation);
+                }
+                term.write_line(&format!("Unrecognized answer {:?}", answer.trim()))?;
+            }
+        })();
+        self.view.resume();
+        result
+    }
+
     pub fn message(&self, message: &str) {
-        self.view.message(message)
+        if !self.quiet {
+            self.view.message(message)
+        }
+    }
+
+    /// Show a report that was specifically requested, such as annotations or a
+    /// comparison, even if other messages are suppressed.
+    pub fn report(&self, report: &str) {
+        self.view.message(report)
     }
 
     pub fn tick(&self) {
@@ -530,30 +615,272 @@ pub fn style_outcome(outcome: &ScenarioOutcome) -> StyledObject<&'static str> {
     }
 }
 
-pub fn list_mutants(mutants: &[Mutant], show_diffs: bool) {
+/// The format of output while testing mutants.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Show progress and results for people to read.
+    #[default]
+    Human,
+    /// Write one JSON object per line to stdout as each mutant's result is known.
+    Jsonl,
+}
+
+/// How to group the list of missed mutants printed after testing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupOutputBy {
+    /// Don't list the missed mutants again after testing.
+    #[default]
+    None,
+    /// List the missed mutants under a heading for each source file, by line.
+    File,
+}
+
+/// Format a list of the missed mutants in a lab, grouped under each source file and
+/// sorted by line, or an empty string if there are none.
+///
+/// Mutants that timed out are included if timeouts are counted as missed.
+pub fn format_missed_by_file(lab_outcome: &LabOutcome, options: &Options) -> String {
+    let mut missed = lab_outcome
+        .outcomes
+        .iter()
+        .filter(|outcome| match outcome.summary() {
+            SummaryOutcome::MissedMutant => true,
+            SummaryOutcome::Timeout => options.timeout_policy == TimeoutPolicy::Survived,
+            _ => false,
+        })
+        .filter_map(|outcome| match &outcome.scenario {
+            Scenario::Mutant(mutant) => Some(mutant),
+            Scenario::Baseline => None,
+        })
+        .collect_vec();
+    if missed.is_empty() {
+        return String::new();
+    }
+    missed.sort_by_key(|mutant| {
+        (
+            mutant.source_file.tree_relative_slashes(),
+            mutant.span.start.line,
+            mutant.describe_change(),
+        )
+    });
+    let mut s = String::from("Missed mutants by file:\n");
+    for (file, mutants) in &missed
+        .into_iter()
+        .group_by(|mutant| mutant.source_file.tree_relative_slashes())
+    {
+        writeln!(s, "\n{}", style(file).bold()).unwrap();
+        for mutant in mutants {
+            writeln!(
+                s,
+                "    {:>4}: {}",
+                mutant.span.start.line,
+                mutant.describe_change()
+            )
+            .unwrap();
+        }
+    }
+    s
+}
+
+/// Format a list of the unviable mutants in a lab, each with the start of the compiler
+/// error from its log.
+pub fn format_unviable_mutants(lab_outcome: &LabOutcome) -> String {
+    let mut s = String::new();
+    for outcome in &lab_outcome.outcomes {
+        let Scenario::Mutant(mutant) = &outcome.scenario else {
+            continue;
+        };
+        if outcome.summary() != SummaryOutcome::Unviable {
+            continue;
+        }
+        if s.is_empty() {
+            s.push_str("Unviable mutants:\n");
+        }
+        writeln!(s, "{}", style_mutant(mutant)).unwrap();
+        let error = outcome
+            .get_log_content()
+            .ok()
+            .and_then(|log| first_compiler_error(&log));
+        match error {
+            Some(error) => {
+                for line in error.lines() {
+                    writeln!(s, "    {line}").unwrap();
+                }
+            }
+            None => writeln!(s, "    (no compiler error found in {})", outcome.log_path()).unwrap(),
+        }
+    }
+    s
+}
+
+/// Interpret an answer to [Console::confirm_mutant]; an empty answer means to test it.
+fn parse_confirmation(answer: &str) -> Option<Confirmation> {
+    match answer.trim().to_lowercase().as_str() {
+        "" | "t" | "test" | "y" | "yes" => Some(Confirmation::Test),
+        "s" | "skip" | "n" | "no" => Some(Confirmation::Skip),
+        "q" | "quit" => Some(Confirmation::Quit),
+        _ => None,
+    }
+}
+
+/// Print a list of mutants, optionally with their diffs, or with the original value
+/// of the functions whose return value is replaced.
+pub fn list_mutants(mutants: &[Mutant], show_diffs: bool, show_original: bool) {
     for mutant in mutants {
         println!("{}", style_mutant(mutant));
+        if show_original {
+            if let Some(original) = mutant.original_tail_expression() {
+                if original == mutant.replacement_text() {
+                    println!(
+                        "    original: {} (same as replacement)",
+                        style(original).red()
+                    );
+                } else {
+                    println!("    original: {}", style(original).yellow());
+                }
+            }
+        }
         if show_diffs {
             println!("{}", mutant.diff());
         }
     }
 }
 
+/// Format a list of mutants as a Markdown table, with the columns padded to line up.
+pub fn format_mutants_markdown(mutants: &[Mutant]) -> String {
+    let header = ["File", "Line", "Function", "Genre", "Replacement"].map(str::to_owned);
+    let rows = mutants
+        .iter()
+        .map(|mutant| {
+            [
+                mutant.source_file.tree_relative_slashes(),
+                mutant.span.start.line.to_string(),
+                mutant.function_name().to_owned(),
+                mutant.genre.cli_name().to_owned(),
+                mutant.replacement_text().to_owned(),
+            ]
+            .map(|cell| escape_markdown_cell(&cell))
+        })
+        .collect_vec();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let format_row = |cells: &[String]| {
+        let padded = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:width$}"))
+            .join(" | ");
+        format!("| {padded} |\n")
+    };
+    let mut s = format_row(&header);
+    s.push_str(&format_row(
+        &widths.iter().map(|&w| "-".repeat(w)).collect_vec(),
+    ));
+    for row in &rows {
+        s.push_str(&format_row(row));
+    }
+    s
+}
+
+/// Describe how many mutants there are of each genre, with the most common genres
+/// first, and a few examples of each.
+pub fn format_mutants_by_genre(mutants: &[Mutant]) -> String {
+    const MAX_EXAMPLES: usize = 3;
+    let mut s = String::new();
+    // The sort is stable, so genres with the same count stay in their usual order.
+    for (genre, mutants) in Genre::all()
+        .map(|genre| {
+            let of_genre = mutants.iter().filter(|m| m.genre == genre).collect_vec();
+            (genre, of_genre)
+        })
+        .filter(|(_, of_genre)| !of_genre.is_empty())
+        .sorted_by_key(|(_, of_genre)| Reverse(of_genre.len()))
+    {
+        writeln!(
+            s,
+            "{}: {}",
+            genre.cli_name(),
+            plural(mutants.len(), "mutant")
+        )
+        .unwrap();
+        for mutant in mutants.iter().take(MAX_EXAMPLES) {
+            writeln!(s, "    {mutant}").unwrap();
+        }
+        if mutants.len() > MAX_EXAMPLES {
+            writeln!(s, "    ... and {} more", mutants.len() - MAX_EXAMPLES).unwrap();
+        }
+    }
+    s
+}
+
+/// Escape text so that it can go in a Markdown table cell, which can't contain
+/// unescaped pipes or newlines.
+fn escape_markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
 fn style_mutant(mutant: &Mutant) -> String {
     // This is like `impl Display for Mutant`, but with colors.
     // The text content should be the same.
-    format!(
-        "{}: replace {}{}{} with {}",
-        mutant.describe_location(),
-        style(mutant.function_name()).bright().magenta(),
-        if mutant.return_type().is_empty() {
-            ""
-        } else {
-            " "
-        },
-        style(mutant.return_type()).magenta(),
-        style(mutant.replacement_text()).yellow(),
-    )
+    if let Some((a, b)) = &mutant.swapped_patterns {
+        return format!(
+            "{}: swap match arm patterns {} and {} in {}",
+            mutant.describe_location(),
+            style(a).yellow(),
+            style(b).bright().yellow(),
+            style(mutant.function_name()).bright().magenta(),
+        );
+    }
+    match mutant.genre {
+        Genre::FnValue => format!(
+            "{}: replace {}{}{} with {}",
+            mutant.describe_location(),
+            style(mutant.function_name()).bright().magenta(),
+            if mutant.return_type().is_empty() {
+                ""
+            } else {
+                " "
+            },
+            style(mutant.return_type()).magenta(),
+            style(mutant.replacement_text()).yellow(),
+        ),
+        Genre::StructUpdate => format!(
+            "{}: delete field {} in {}",
+            mutant.describe_location(),
+            style(mutant.original_text_one_line().trim_end_matches(',')).yellow(),
+            style(mutant.function_name()).bright().magenta(),
+        ),
+        Genre::StatementDelete | Genre::MemCall | Genre::CollectionPush
+            if mutant.replacement_text().is_empty() =>
+        {
+            format!(
+                "{}: delete {} in {}",
+                mutant.describe_location(),
+                style(mutant.original_text_one_line()).yellow(),
+                style(mutant.function_name()).bright().magenta(),
+            )
+        }
+        Genre::WhileLetBreak => format!(
+            "{}: {} in {}",
+            mutant.describe_location(),
+            style(mutant.describe_while_let_break()).yellow(),
+            style(mutant.function_name()).bright().magenta(),
+        ),
+        _ => format!(
+            "{}: replace {} with {} in {}",
+            mutant.describe_location(),
+            style(mutant.original_text_one_line()).yellow(),
+            style(mutant.replacement_text()).bright().yellow(),
+            style(mutant.function_name()).bright().magenta(),
+        ),
+    }
 }
 
 fn style_elapsed_secs(since: Instant) -> String {
@@ -603,6 +930,42 @@ mod test {
     use super::*;
     use std::time::Duration;
 
+    #[test]
+    fn escape_markdown_table_cells() {
+        assert_eq!(escape_markdown_cell("a || b"), "a \\|\\| b");
+        assert_eq!(escape_markdown_cell("one\ntwo"), "one two");
+    }
+
+    #[test]
+    fn parse_confirmation_answers() {
+        assert_eq!(parse_confirmation("\n"), Some(Confirmation::Test));
+        assert_eq!(parse_confirmation("T\n"), Some(Confirmation::Test));
+        assert_eq!(parse_confirmation(" skip "), Some(Confirmation::Skip));
+        assert_eq!(parse_confirmation("n"), Some(Confirmation::Skip));
+        assert_eq!(parse_confirmation("q\n"), Some(Confirmation::Quit));
+        assert_eq!(parse_confirmation("maybe"), None);
+    }
+
+    #[test]
+    fn markdown_table_columns_line_up() {
+        let tool = CargoTool::new();
+        let source_tree = tool
+            .find_root(Utf8Path::new("testdata/tree/factorial"))
+            .unwrap();
+        let mutants = walk_tree(&tool, &source_tree, &Options::default())
+            .unwrap()
+            .mutants;
+        assert_eq!(
+            format_mutants_markdown(&mutants[..2]),
+            indoc::indoc! { "
+                | File                 | Line | Function  | Genre    | Replacement |
+                | -------------------- | ---- | --------- | -------- | ----------- |
+                | src/bin/factorial.rs | 1    | main      | fn-value | ()          |
+                | src/bin/factorial.rs | 7    | factorial | fn-value | 0           |
+            " }
+        );
+    }
+
     #[test]
     fn test_duration_minutes_seconds() {
         assert_eq!(duration_minutes_seconds(Duration::ZERO), "0:00");
@@ -0,0 +1,176 @@
+// Copyright 2023 Martin Pool
+
+//! Read test coverage from an lcov tracefile, to predict which tests could catch
+//! each mutant before running anything.
+//!
+//! To tell which tests cover a line, the tracefile must have a separate record for
+//! each test, named by its `TN:` line. A tracefile from a single run of the whole test
+//! suite, with one unnamed test, only shows whether a line is covered at all.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs::read_to_string;
+
+use anyhow::{anyhow, Context};
+use serde_json::{json, Value};
+
+use crate::console::plural;
+use crate::*;
+
+/// The tests that cover each line of each source file.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Keyed by the source file path, relative to the tree root if it was inside the
+    /// tree, and then by line number.
+    tests_by_line: HashMap<String, HashMap<usize, BTreeSet<String>>>,
+}
+
+impl Coverage {
+    /// Read an lcov tracefile, where source files within `root` are converted to
+    /// tree-relative paths.
+    pub fn read(path: &Utf8Path, root: &Utf8Path) -> Result<Coverage> {
+        let lcov = read_to_string(path).with_context(|| format!("read {path}"))?;
+        Coverage::parse(&lcov, root).with_context(|| format!("parse {path}"))
+    }
+
+    fn parse(lcov: &str, root: &Utf8Path) -> Result<Coverage> {
+        let mut coverage = Coverage::default();
+        let root_prefix = format!("{}/", root.as_str().replace('\\', "/"));
+        let mut test_name = "";
+        let mut source_file: Option<String> = None;
+        for (line_number, line) in lcov.lines().enumerate() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix("TN:") {
+                test_name = name;
+            } else if let Some(path) = line.strip_prefix("SF:") {
+                let path = path.replace('\\', "/");
+                source_file = Some(match path.strip_prefix(&root_prefix) {
+                    Some(relative) => relative.to_owned(),
+                    None => path,
+                });
+            } else if let Some(fields) = line.strip_prefix("DA:") {
+                // Like `DA:12,3`, possibly followed by a checksum.
+                let mut fields = fields.split(',');
+                let (Some(covered_line), Some(count)) = (fields.next(), fields.next()) else {
+                    return Err(anyhow!("malformed DA on line {}", line_number + 1));
+                };
+                let covered_line: usize = covered_line
+                    .parse()
+                    .with_context(|| format!("line number on line {}", line_number + 1))?;
+                let count: u64 = count
+                    .parse()
+                    .with_context(|| format!("execution count on line {}", line_number + 1))?;
+                if count > 0 {
+                    let file = source_file
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("DA before SF on line {}", line_number + 1))?;
+                    coverage
+                        .tests_by_line
+                        .entry(file.clone())
+                        .or_default()
+                        .entry(covered_line)
+                        .or_default()
+                        .insert(test_name.to_owned());
+                }
+            } else if line == "end_of_record" {
+                source_file = None;
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// Return the names of the tests that cover any line of the mutated region.
+    ///
+    /// Only source files at the same tree-relative path as the mutant match, so that
+    /// a dependency's file with a similar path isn't mistaken for it.
+    pub fn tests_covering(&self, mutant: &Mutant) -> BTreeSet<&str> {
+        let mut tests = BTreeSet::new();
+        if let Some(lines) = self
+            .tests_by_line
+            .get(&mutant.source_file.tree_relative_slashes())
+        {
+            for line in mutant.span.start.line..=mutant.span.end.line {
+                if let Some(line_tests) = lines.get(&line) {
+                    tests.extend(line_tests.iter().map(String::as_str));
+                }
+            }
+        }
+        tests
+    }
+
+    /// Describe how many tests cover each mutant, one per line.
+    pub fn format_affected_tests(&self, mutants: &[Mutant]) -> String {
+        let mut s = String::new();
+        for mutant in mutants {
+            let tests = self.tests_covering(mutant);
+            if tests.is_empty() {
+                writeln!(s, "{mutant} ... not covered").unwrap();
+            } else {
+                writeln!(s, "{mutant} ... covered by {}", plural(tests.len(), "test")).unwrap();
+            }
+        }
+        s
+    }
+
+    /// List the names of the tests covering each mutant, as json.
+    pub fn affected_tests_json(&self, mutants: &[Mutant]) -> Value {
+        Value::Array(
+            mutants
+                .iter()
+                .map(|mutant| {
+                    json!({
+                        "id": mutant.id,
+                        "name": mutant.to_string(),
+                        "tests": self.tests_covering(mutant),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LCOV: &str = "\
+TN:test::small
+SF:/src/tree/src/lib.rs
+DA:1,1
+DA:2,4
+DA:3,0
+end_of_record
+TN:test::large
+SF:/src/tree/src/lib.rs
+DA:1,1
+DA:3,2,abcdef
+end_of_record
+SF:/home/ci/checkout/src/other.rs
+DA:7,1
+end_of_record
+";
+
+    #[test]
+    fn lines_are_mapped_to_the_tests_that_run_them() {
+        let coverage = Coverage::parse(LCOV, Utf8Path::new("/src/tree")).unwrap();
+        let lib = &coverage.tests_by_line["src/lib.rs"];
+        assert_eq!(
+            lib[&1],
+            BTreeSet::from(["test::large".into(), "test::small".into()])
+        );
+        assert_eq!(lib[&2], BTreeSet::from(["test::small".into()]));
+        assert_eq!(lib[&3], BTreeSet::from(["test::large".into()]));
+        assert_eq!(
+            coverage.tests_by_line["/home/ci/checkout/src/other.rs"][&7],
+            BTreeSet::from(["test::large".into()])
+        );
+    }
+
+    #[test]
+    fn malformed_lcov_is_an_error() {
+        let root = Utf8Path::new("/src/tree");
+        assert!(Coverage::parse("DA:1,1\n", root).is_err());
+        assert!(Coverage::parse("SF:src/lib.rs\nDA:x,1\n", root).is_err());
+        assert!(Coverage::parse("SF:src/lib.rs\nDA:1\n", root).is_err());
+    }
+}
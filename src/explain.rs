@@ -0,0 +1,77 @@
+// Copyright 2023 Martin Pool
+
+//! Test a single mutant, chosen by its id, and explain its outcome.
+
+use anyhow::{anyhow, Context};
+use tempfile::TempDir;
+
+use crate::outcome::{LabOutcome, SummaryOutcome};
+use crate::*;
+
+/// Find the mutant with the given id, test it, and show the outcome and the full log
+/// of building and testing it, which starts with the mutant's diff.
+///
+/// Unless an output directory is given, the log is written to a temporary directory,
+/// so that the results of an earlier run in `mutants.out` are kept.
+pub fn explain_mutant(
+    tool: &dyn Tool,
+    source_tree: &Utf8Path,
+    id: &str,
+    options: &Options,
+    console: &Console,
+) -> Result<LabOutcome> {
+    let mutant = walk_tree(tool, source_tree, options)?
+        .mutants
+        .into_iter()
+        .find(|mutant| mutant.id == id)
+        .ok_or_else(|| {
+            anyhow!("No mutant has id {id:?}: the ids are shown by `cargo mutants --list --json`")
+        })?;
+    console.message(&format!("{mutant}\n"));
+    let temp_dir;
+    let output_in_dir = match &options.output_in_dir {
+        Some(dir) => dir.as_path(),
+        None => {
+            temp_dir = TempDir::new().context("create temporary output directory")?;
+            Utf8Path::from_path(temp_dir.path()).context("temporary directory is not UTF-8")?
+        }
+    };
+    let lab_outcome =
+        lab::test_one_mutant(tool, source_tree, output_in_dir, mutant, options, console)?;
+    let outcome = lab_outcome.outcomes.last().expect("mutant was tested");
+    console.message(&format!(
+        "\n{}\n\n{}",
+        explanation(outcome),
+        outcome.get_log_content()?
+    ));
+    Ok(lab_outcome)
+}
+
+/// Explain what the outcome of testing a mutant means.
+fn explanation(outcome: &ScenarioOutcome) -> String {
+    match outcome.summary() {
+        SummaryOutcome::CaughtMutant => "Caught: the tests failed with this mutant applied, \
+            so they detect this change. The log below shows which tests failed."
+            .to_owned(),
+        SummaryOutcome::MissedMutant => "Missed: the tests passed with this mutant applied, \
+            so no test detects this change. The log below shows which tests ran."
+            .to_owned(),
+        SummaryOutcome::Unviable => format!(
+            "Unviable: cargo {} failed with this mutant applied, so it couldn't be tested. \
+            This says nothing about the tests. The log below shows the compiler errors.",
+            outcome.last_phase()
+        ),
+        SummaryOutcome::Timeout => "Timeout: the tests didn't finish within the timeout \
+            with this mutant applied, so it probably causes a hang."
+            .to_owned(),
+        SummaryOutcome::Success | SummaryOutcome::Failure => format!(
+            "cargo {} {} with this mutant applied.",
+            outcome.last_phase(),
+            if outcome.success() {
+                "succeeded"
+            } else {
+                "failed"
+            },
+        ),
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright 2023 Martin Pool
+
+//! Export mutants as patch files, without testing them.
+
+use std::fs;
+
+use anyhow::Context;
+use serde_json::json;
+
+use crate::*;
+
+/// The name of the file listing the exported mutants, within the export directory.
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Write one `.patch` file for each mutant into `dir`, named by the mutant's id, and
+/// a `manifest.json` mapping ids to patch files.
+///
+/// The directory is created if necessary. Existing patch files with the same names
+/// are overwritten.
+pub fn export_patches(mutants: &[Mutant], dir: &Utf8Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("create export directory {dir:?}"))?;
+    let mut manifest = Vec::with_capacity(mutants.len());
+    for mutant in mutants {
+        let patch_name = format!("{}.patch", mutant.id);
+        let patch_path = dir.join(&patch_name);
+        fs::write(&patch_path, mutant.patch())
+            .with_context(|| format!("write patch {patch_path:?}"))?;
+        manifest.push(json!({
+            "id": mutant.id,
+            "patch": patch_name,
+            "mutant": mutant,
+        }));
+    }
+    let manifest_path = dir.join(MANIFEST_NAME);
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("write {manifest_path:?}"))?;
+    Ok(())
+}
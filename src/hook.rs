@@ -0,0 +1,121 @@
+// Copyright 2023 Martin Pool
+
+//! Run a user's command after testing all the mutants, for example to post a notification.
+//!
+//! The command is run by the shell, with the outcome described in environment variables:
+//!
+//! * `CARGO_MUTANTS_EXIT_CODE`: the code cargo-mutants is about to exit with.
+//! * `CARGO_MUTANTS_MUTATION_SCORE`: the mutation score as a percentage, if there were
+//!   any viable mutants.
+//! * `CARGO_MUTANTS_SUMMARY`: the path of a temporary json file with the counts of each
+//!   outcome, in the same format as `--summary-json`. It's deleted after the command
+//!   finishes.
+
+use std::io::Write;
+use std::process::Command;
+
+use anyhow::Context;
+use tempfile::NamedTempFile;
+use tracing::{info, warn};
+
+use crate::outcome::LabSummary;
+use crate::*;
+
+/// Run the `--on-finish` command, describing the outcome of the run.
+///
+/// A command that fails is reported, but doesn't change the exit code of cargo-mutants.
+pub fn run_on_finish(command: &str, summary: &LabSummary, exit_code: i32) -> Result<()> {
+    let mut summary_file = NamedTempFile::new().context("create summary file for hook")?;
+    summary_file.write_all((serde_json::to_string_pretty(summary)? + "\n").as_bytes())?;
+    summary_file.flush()?;
+    let summary_path = summary_file
+        .path()
+        .to_str()
+        .context("temp path is not UTF-8")?;
+    info!("Run --on-finish command: {command}");
+    let status = shell_command(command)
+        .envs(hook_env(summary, exit_code, summary_path))
+        .status()
+        .with_context(|| format!("run --on-finish command {command:?}"))?;
+    if !status.success() {
+        warn!("--on-finish command failed: {status}");
+    }
+    Ok(())
+}
+
+/// Return the environment variables describing the outcome to the hook.
+fn hook_env(summary: &LabSummary, exit_code: i32, summary_path: &str) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("CARGO_MUTANTS_EXIT_CODE".to_owned(), exit_code.to_string()),
+        ("CARGO_MUTANTS_SUMMARY".to_owned(), summary_path.to_owned()),
+    ];
+    if let Some(score) = summary.mutation_score {
+        env.push((
+            "CARGO_MUTANTS_MUTATION_SCORE".to_owned(),
+            format!("{score:.1}"),
+        ));
+    }
+    env
+}
+
+fn shell_command(command: &str) -> Command {
+    let argv = shell_argv(command);
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]);
+    cmd
+}
+
+/// Return the arguments to run a command line through the platform's shell.
+#[cfg(unix)]
+pub fn shell_argv(command: &str) -> Vec<String> {
+    vec!["sh".to_owned(), "-c".to_owned(), command.to_owned()]
+}
+
+/// Return the arguments to run a command line through the platform's shell.
+#[cfg(windows)]
+pub fn shell_argv(command: &str) -> Vec<String> {
+    vec!["cmd".to_owned(), "/C".to_owned(), command.to_owned()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn summary(mutation_score: Option<f64>) -> LabSummary {
+        LabSummary {
+            total_mutants: 3,
+            missed: 1,
+            caught: 2,
+            timeout: 0,
+            unviable: 0,
+            success: 0,
+            failure: 0,
+            assumed_caught: 0,
+            mutation_score,
+            elapsed_secs: 1.5,
+        }
+    }
+
+    #[test]
+    fn env_describes_outcome() {
+        assert_eq!(
+            hook_env(&summary(Some(200.0 / 3.0)), 2, "/tmp/summary.json"),
+            [
+                ("CARGO_MUTANTS_EXIT_CODE".to_owned(), "2".to_owned()),
+                (
+                    "CARGO_MUTANTS_SUMMARY".to_owned(),
+                    "/tmp/summary.json".to_owned()
+                ),
+                ("CARGO_MUTANTS_MUTATION_SCORE".to_owned(), "66.7".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn score_is_unset_without_viable_mutants() {
+        let env = hook_env(&summary(None), 0, "/tmp/summary.json");
+        assert!(!env
+            .iter()
+            .any(|(name, _)| name == "CARGO_MUTANTS_MUTATION_SCORE"));
+    }
+}
@@ -0,0 +1,429 @@
+// Copyright 2023 Martin Pool
+
+//! Select mutants in code that was changed, as recorded by git.
+//!
+//! The changes are found by running `git diff` with no context lines, and then
+//! remembering which lines of the new version of each file were added or modified.
+//! A mutant is selected if the function containing it overlaps those lines.
+//!
+//! With `--only-new-files`, only files that were added in the diff, or that are not yet
+//! tracked by git, are selected, and every mutant in them is selected.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use anyhow::{bail, Context};
+use itertools::Itertools;
+use tracing::debug;
+
+use crate::process::get_command_output;
+use crate::source::Package;
+use crate::*;
+
+/// The hash of the empty tree in git, used as the base when the history is shorter
+/// than the requested number of commits.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Lines changed in each file, as line numbers in the new version of the file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangedLines {
+    /// For each changed file, by tree-relative path with forward slashes, the first and
+    /// last changed lines of each hunk.
+    ///
+    /// For a pure deletion the first line is one more than the last: the removed lines
+    /// were between those two lines.
+    files: HashMap<String, Vec<(usize, usize)>>,
+
+    /// Files that were added in the diff, or are untracked in the working tree if that
+    /// was requested, by tree-relative path with forward slashes.
+    new_files: HashSet<String>,
+}
+
+impl ChangedLines {
+    /// Find the lines changed since the git revision selected by `--since-commits` or
+    /// `--since-tag`, or None if neither is set.
+    ///
+    /// With `--only-new-files`, files that git doesn't track yet also count as new.
+    pub fn for_options(root: &Utf8Path, options: &Options) -> Result<Option<ChangedLines>> {
+        let mut changed = if let Some(n) = options.since_commits {
+            ChangedLines::since_commits(root, n)?
+        } else if let Some(tag) = &options.since_tag {
+            ChangedLines::since_tag(root, tag)?
+        } else {
+            return Ok(None);
+        };
+        if options.only_new_files {
+            changed.add_untracked_files(root)?;
+        }
+        Ok(Some(changed))
+    }
+
+    /// Find the lines changed in the last `n` commits, up to and including `HEAD`.
+    ///
+    /// Commits are counted along the first-parent chain, so a merge commit counts as one
+    /// commit and includes everything it merged. If there are fewer than `n` commits
+    /// then everything in the tree counts as changed.
+    pub fn since_commits(root: &Utf8Path, n: u64) -> Result<ChangedLines> {
+        let count_output = get_command_output(
+            &["git", "rev-list", "--count", "--first-parent", "HEAD"],
+            root,
+        )
+        .context("count git commits")?;
+        let count: u64 = count_output
+            .trim()
+            .parse()
+            .with_context(|| format!("parse commit count {count_output:?}"))?;
+        let base = if count > n {
+            format!("HEAD~{n}")
+        } else {
+            EMPTY_TREE.to_owned()
+        };
+        ChangedLines::between(root, &base, "HEAD")
+    }
+
+    /// Find the lines changed since a git tag, up to and including `HEAD`.
+    ///
+    /// The tag `latest` means the most recent tag reachable from `HEAD`, unless there's
+    /// a tag actually called `latest`.
+    pub fn since_tag(root: &Utf8Path, tag: &str) -> Result<ChangedLines> {
+        let matching_tags =
+            get_command_output(&["git", "tag", "--list", tag], root).context("list git tags")?;
+        let tag = if matching_tags.lines().any(|line| line == tag) {
+            tag.to_owned()
+        } else if tag == "latest" {
+            let latest = get_command_output(&["git", "describe", "--tags", "--abbrev=0"], root)
+                .context("find the most recent git tag")?;
+            debug!(latest = latest.trim(), "most recent tag");
+            latest.trim().to_owned()
+        } else {
+            bail!("git tag {tag:?} not found");
+        };
+        ChangedLines::between(root, &format!("refs/tags/{tag}"), "HEAD")
+    }
+
+    /// Find the lines changed between two git revisions, within the tree at `root`.
+    fn between(root: &Utf8Path, base: &str, head: &str) -> Result<ChangedLines> {
+        let diff = get_command_output(
+            &[
+                "git",
+                "-c",
+                "core.quotePath=false",
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--no-prefix",
+                "--relative",
+                "-U0",
+                base,
+                head,
+            ],
+            root,
+        )
+        .with_context(|| format!("get git diff from {base} to {head}"))?;
+        let changed = ChangedLines::parse(&diff)?;
+        debug!(?changed, "changed lines from {base} to {head}");
+        Ok(changed)
+    }
+
+    /// Add the files in the working tree that aren't tracked by git, and aren't ignored,
+    /// to the new files.
+    fn add_untracked_files(&mut self, root: &Utf8Path) -> Result<()> {
+        let untracked = get_command_output(
+            &[
+                "git",
+                "-c",
+                "core.quotePath=false",
+                "ls-files",
+                "--others",
+                "--exclude-standard",
+            ],
+            root,
+        )
+        .context("list untracked files")?;
+        debug!(?untracked, "untracked files");
+        self.new_files
+            .extend(untracked.lines().map(|line| line.to_owned()));
+        Ok(())
+    }
+
+    /// Parse the output of `git diff -U0 --no-prefix`.
+    fn parse(diff: &str) -> Result<ChangedLines> {
+        let mut files: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        let mut new_files = HashSet::new();
+        let mut current_file: Option<String> = None;
+        let mut added = false;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("--- ") {
+                // Added files have an old name of `/dev/null`.
+                added = path == "/dev/null";
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                // Deleted files have a new name of `/dev/null`, and no lines to mutate.
+                current_file = if path == "/dev/null" {
+                    None
+                } else {
+                    if added {
+                        new_files.insert(path.to_owned());
+                    }
+                    Some(path.to_owned())
+                };
+            } else if line.starts_with("@@ ") {
+                let Some(path) = &current_file else {
+                    continue;
+                };
+                let range = parse_hunk_header(line)
+                    .with_context(|| format!("parse diff hunk header {line:?}"))?;
+                files.entry(path.clone()).or_default().push(range);
+            }
+        }
+        Ok(ChangedLines { files, new_files })
+    }
+
+    /// True if any lines in this file were changed.
+    pub fn touches_file(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// True if this file was added in the diff, or is untracked and untracked files were
+    /// requested.
+    pub fn is_new_file(&self, path: &str) -> bool {
+        self.new_files.contains(path)
+    }
+
+    /// True if the changes touch the inclusive range of lines from `first` to `last`.
+    pub fn touches_lines(&self, path: &str, first: usize, last: usize) -> bool {
+        self.files.get(path).map_or(false, |ranges| {
+            ranges
+                .iter()
+                .any(|&(hunk_first, hunk_last)| first <= hunk_last && last >= hunk_first)
+        })
+    }
+
+    /// Find the names of the packages containing changed files.
+    ///
+    /// Each file belongs to the package with the innermost directory containing it;
+    /// changes outside of any package, such as to a workspace's top-level files, are
+    /// ignored. If `include_dependents` is true, packages in the workspace that depend
+    /// on a changed package, directly or indirectly, are also included.
+    pub fn packages_with_changes<'p>(
+        &self,
+        packages: impl IntoIterator<Item = &'p Package>,
+        include_dependents: bool,
+    ) -> BTreeSet<String> {
+        let packages = packages
+            .into_iter()
+            .unique_by(|package| &package.name)
+            .collect_vec();
+        let mut changed: BTreeSet<String> = self
+            .files
+            .keys()
+            .filter_map(|path| {
+                packages
+                    .iter()
+                    .filter_map(|package| {
+                        let dir = package.relative_manifest_path.parent()?.as_str();
+                        (dir.is_empty() || path.starts_with(&format!("{dir}/")))
+                            .then_some((dir.len(), &package.name))
+                    })
+                    .max()
+                    .map(|(_, name)| name.clone())
+            })
+            .collect();
+        if include_dependents {
+            loop {
+                let dependents = packages
+                    .iter()
+                    .filter(|package| {
+                        !changed.contains(&package.name)
+                            && package
+                                .workspace_dependencies
+                                .iter()
+                                .any(|dep| changed.contains(dep))
+                    })
+                    .map(|package| package.name.clone())
+                    .collect_vec();
+                if dependents.is_empty() {
+                    break;
+                }
+                changed.extend(dependents);
+            }
+        }
+        changed
+    }
+
+    /// Describe the changed lines of each file, one file per line, sorted by path.
+    ///
+    /// For example, `src/lib.rs: 3-4, deleted after 20`.
+    pub fn describe(&self) -> String {
+        let mut s = String::new();
+        for (path, ranges) in self.files.iter().sorted_by_key(|(path, _)| *path) {
+            let ranges = ranges
+                .iter()
+                .map(|&(first, last)| match first.cmp(&last) {
+                    Ordering::Greater => format!("deleted after {last}"),
+                    Ordering::Equal => format!("{first}"),
+                    Ordering::Less => format!("{first}-{last}"),
+                })
+                .join(", ");
+            writeln!(s, "{path}: {ranges}").unwrap();
+        }
+        s
+    }
+}
+
+/// Parse a hunk header like `@@ -10,2 +11,3 @@ fn foo() {` into the first and last new lines.
+fn parse_hunk_header(line: &str) -> Result<(usize, usize)> {
+    let new = line
+        .split(' ')
+        .find_map(|word| word.strip_prefix('+'))
+        .context("no new line range")?;
+    let (start, len) = match new.split_once(',') {
+        Some((start, len)) => (start.parse::<usize>()?, len.parse::<usize>()?),
+        None => (new.parse::<usize>()?, 1),
+    };
+    if len == 0 {
+        // For a deletion git reports the line after which lines were removed; return
+        // a reversed pair that only overlaps spans containing both that line and the next.
+        Ok((start + 1, start))
+    } else {
+        Ok((start, start + len - 1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn parse_hunk_headers() {
+        assert_eq!(parse_hunk_header("@@ -1 +1 @@").unwrap(), (1, 1));
+        assert_eq!(
+            parse_hunk_header("@@ -10,2 +11,3 @@ fn foo() {").unwrap(),
+            (11, 13)
+        );
+        assert_eq!(parse_hunk_header("@@ -0,0 +1,20 @@").unwrap(), (1, 20));
+        assert_eq!(parse_hunk_header("@@ -8,2 +7,0 @@").unwrap(), (8, 7));
+        assert!(parse_hunk_header("@@ nonsense @@").is_err());
+    }
+
+    #[test]
+    fn find_packages_with_changes() {
+        let diff = indoc! { r#"
+            --- utils/src/lib.rs
+            +++ utils/src/lib.rs
+            @@ -3 +3 @@ pub fn one() -> u32 {
+            --- README.md
+            +++ README.md
+            @@ -1 +1 @@
+            "# };
+        let changed = ChangedLines::parse(diff).unwrap();
+        let package = |name: &str, dir: &str, deps: &[&str]| Package {
+            name: name.to_owned(),
+            relative_manifest_path: Utf8Path::new(dir).join("Cargo.toml"),
+            workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        };
+        let packages = [
+            package("utils", "utils", &[]),
+            package("main", "main", &["utils"]),
+            package("app", "main/app", &["main"]),
+            package("other", "other", &[]),
+        ];
+        assert_eq!(
+            changed
+                .packages_with_changes(&packages, false)
+                .into_iter()
+                .collect_vec(),
+            ["utils"]
+        );
+        assert_eq!(
+            changed
+                .packages_with_changes(&packages, true)
+                .into_iter()
+                .collect_vec(),
+            ["app", "main", "utils"]
+        );
+
+        // A root package owns only the files that aren't in a nested package.
+        let packages = [package("root", "", &[]), package("utils", "utils", &[])];
+        assert_eq!(
+            changed
+                .packages_with_changes(&packages, false)
+                .into_iter()
+                .collect_vec(),
+            ["root", "utils"]
+        );
+    }
+
+    #[test]
+    fn parse_diff_and_match_lines() {
+        let diff = indoc! { r#"
+            diff --git src/lib.rs src/lib.rs
+            index 3b18e51..a9b2c1d 100644
+            --- src/lib.rs
+            +++ src/lib.rs
+            @@ -3 +3,2 @@ pub fn one() -> u32 {
+            -    1
+            +    let x = 1;
+            +    x
+            @@ -20,2 +20,0 @@ pub fn two() {
+            -    println!("two");
+            -    println!("deux");
+            diff --git src/gone.rs src/gone.rs
+            deleted file mode 100644
+            index 3b18e51..0000000
+            --- src/gone.rs
+            +++ /dev/null
+            @@ -1,3 +0,0 @@
+            -pub fn gone() -> u32 {
+            -    0
+            -}
+            "# };
+        let changed = ChangedLines::parse(diff).unwrap();
+        assert!(changed.touches_file("src/lib.rs"));
+        assert!(!changed.touches_file("src/gone.rs"));
+        assert!(!changed.touches_file("src/other.rs"));
+
+        // A function containing the modified lines.
+        assert!(changed.touches_lines("src/lib.rs", 2, 5));
+        // A function entirely before or after them.
+        assert!(!changed.touches_lines("src/lib.rs", 6, 10));
+        assert!(!changed.touches_lines("src/other.rs", 1, 100));
+
+        // The deletion is between lines 20 and 21 of the new file.
+        assert!(changed.touches_lines("src/lib.rs", 18, 22));
+        assert!(!changed.touches_lines("src/lib.rs", 15, 20));
+        assert!(!changed.touches_lines("src/lib.rs", 21, 30));
+
+        assert_eq!(changed.describe(), "src/lib.rs: 3-4, deleted after 20\n");
+        assert!(!changed.is_new_file("src/lib.rs"));
+        assert!(!changed.is_new_file("src/gone.rs"));
+    }
+
+    #[test]
+    fn added_files_are_new() {
+        let diff = indoc! { r#"
+            diff --git src/lib.rs src/lib.rs
+            index 3b18e51..a9b2c1d 100644
+            --- src/lib.rs
+            +++ src/lib.rs
+            @@ -1,0 +2 @@
+            +mod added;
+            diff --git src/added.rs src/added.rs
+            new file mode 100644
+            index 0000000..3b18e51
+            --- /dev/null
+            +++ src/added.rs
+            @@ -0,0 +1,3 @@
+            +pub fn added() -> u32 {
+            +    1
+            +}
+            "# };
+        let changed = ChangedLines::parse(diff).unwrap();
+        assert!(changed.is_new_file("src/added.rs"));
+        assert!(!changed.is_new_file("src/lib.rs"));
+        assert!(changed.touches_file("src/lib.rs"));
+        assert!(changed.touches_lines("src/added.rs", 1, 3));
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2023 Martin Pool
+
+//! Read the ids of mutants from an earlier report, to select only mutants that are new.
+
+use std::collections::HashSet;
+use std::fs::read_to_string;
+
+use anyhow::{anyhow, Context};
+use serde_json::Value;
+
+use crate::*;
+
+/// Read the ids of all the mutants in a report written by an earlier run.
+///
+/// The report can be a list of mutants, as in `mutants.json` or the output of
+/// `--list --json`, or the outcomes of a run, as in `outcomes.json`.
+pub fn read_mutant_ids(path: &Utf8Path) -> Result<HashSet<String>> {
+    let json = read_to_string(path).with_context(|| format!("read mutants report {path:?}"))?;
+    let report: Value =
+        serde_json::from_str(&json).with_context(|| format!("parse mutants report {path:?}"))?;
+    mutant_ids_in_report(&report).with_context(|| format!("read mutant ids from {path:?}"))
+}
+
+fn mutant_ids_in_report(report: &Value) -> Result<HashSet<String>> {
+    let mutants: Vec<&Value> = match report {
+        Value::Array(mutants) => mutants.iter().collect(),
+        Value::Object(object) => object
+            .get("outcomes")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("report is neither a list of mutants nor outcomes"))?
+            .iter()
+            // The baseline scenario is just a string; mutants are an object.
+            .filter_map(|outcome| outcome.get("scenario")?.get("Mutant"))
+            .collect(),
+        _ => return Err(anyhow!("report is neither a list of mutants nor outcomes")),
+    };
+    mutants
+        .into_iter()
+        .map(|mutant| {
+            mutant
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .ok_or_else(|| anyhow!("mutant has no id: {mutant}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn ids_from_mutants_list() {
+        let report = json!([
+            { "id": "6f793c20f53f", "package": "a" },
+            { "id": "0a6e48aa3db6", "package": "a" },
+        ]);
+        assert_eq!(
+            mutant_ids_in_report(&report).unwrap(),
+            HashSet::from(["6f793c20f53f".to_owned(), "0a6e48aa3db6".to_owned()])
+        );
+    }
+
+    #[test]
+    fn ids_from_outcomes() {
+        let report = json!({
+            "outcomes": [
+                { "scenario": "Baseline", "summary": "Success" },
+                { "scenario": { "Mutant": { "id": "6f793c20f53f" } }, "summary": "CaughtMutant" },
+            ],
+            "total_mutants": 1,
+        });
+        assert_eq!(
+            mutant_ids_in_report(&report).unwrap(),
+            HashSet::from(["6f793c20f53f".to_owned()])
+        );
+    }
+
+    #[test]
+    fn reports_without_ids_are_errors() {
+        assert!(mutant_ids_in_report(&json!([{ "package": "a" }])).is_err());
+        assert!(mutant_ids_in_report(&json!({ "total_mutants": 1 })).is_err());
+        assert!(mutant_ids_in_report(&json!("mutants")).is_err());
+    }
+}
@@ -3,8 +3,9 @@
 //! Successively apply mutations to the source code and run cargo to check, build, and test them.
 
 use std::cmp::max;
+use std::collections::HashMap;
 use std::sync::Mutex;
-use std::thread;
+use std::thread::{self, sleep};
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
@@ -12,12 +13,23 @@ use tracing::warn;
 #[allow(unused)]
 use tracing::{debug, debug_span, error, info, trace};
 
-use crate::console::Console;
-use crate::outcome::{LabOutcome, Phase, PhaseResult, ScenarioOutcome};
+use crate::compare::Comparison;
+use crate::console::{Confirmation, Console, OutputFormat};
+use crate::hook::shell_argv;
+use crate::outcome::{LabOutcome, Phase, PhaseResult, ScenarioOutcome, SummaryOutcome};
 use crate::output::OutputDir;
-use crate::process::Process;
+use crate::process::{Process, ProcessStatus, ResourceLimits};
+use crate::textedit::LineColumn;
 use crate::*;
 
+/// The most times to retry a phase that failed for a transient reason, such as Cargo
+/// failing to get a lock held by another job.
+const MAX_TRANSIENT_RETRIES: u32 = 3;
+
+/// How long to wait before the first retry of a transient failure; later retries wait
+/// proportionally longer.
+const TRANSIENT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 /// Run all possible mutation experiments.
 ///
 /// Before testing the mutants, the lab checks that the source tree passes its tests with no
@@ -29,17 +41,29 @@ pub fn test_unmutated_then_all_mutants(
     console: &Console,
 ) -> Result<LabOutcome> {
     let start_time = Instant::now();
+    // Read the earlier outcomes first, so that a bad path fails before testing anything.
+    let previous_outcomes = options
+        .compare
+        .as_deref()
+        .map(compare::read_previous_outcomes)
+        .transpose()?;
     let output_in_dir: &Utf8Path = options
         .output_in_dir
         .as_ref()
         .map_or(source_tree, |p| p.as_path());
     let output_dir = OutputDir::new(output_in_dir)?;
     console.set_debug_log(output_dir.open_debug_log()?);
+    if let Some(max_log_age) = options.max_log_age {
+        clean::prune_older_than(source_tree, output_in_dir, max_log_age)?;
+    }
 
     let mut mutants = walk_tree(tool, source_tree, &options)?.mutants;
     if options.shuffle {
         fastrand::shuffle(&mut mutants);
     }
+    if options.prioritize_untested {
+        prioritize_untested(&mut mutants);
+    }
     output_dir.write_mutants_list(&mutants)?;
     console.discovered_mutants(&mutants);
     if mutants.is_empty() {
@@ -48,6 +72,24 @@ pub fn test_unmutated_then_all_mutants(
 
     let output_mutex = Mutex::new(output_dir);
     let mut build_dirs = vec![BuildDir::new(source_tree, &options, console)?];
+    if let Some(command) = &options.baseline_command {
+        let mut log_file = output_mutex
+            .lock()
+            .expect("lock output_dir to create log")
+            .create_named_log("baseline-command")?;
+        if !run_baseline_command(command, &build_dirs[0], &options, &mut log_file, console)? {
+            error!(
+                "baseline command {command:?} failed, so no mutants were tested; see {}",
+                log_file.path()
+            );
+            let mut lab_outcome = output_mutex
+                .into_inner()
+                .expect("lock output_dir")
+                .take_lab_outcome();
+            lab_outcome.baseline_command_failed = true;
+            return Ok(lab_outcome);
+        }
+    }
     let baseline_outcome = {
         let _span = debug_span!("baseline").entered();
         test_scenario(
@@ -56,7 +98,7 @@ pub fn test_unmutated_then_all_mutants(
             &output_mutex,
             &options,
             &Scenario::Baseline,
-            options.test_timeout.unwrap_or(Duration::MAX),
+            options.baseline_timeout.unwrap_or(Duration::MAX),
             console,
         )?
     };
@@ -74,31 +116,28 @@ pub fn test_unmutated_then_all_mutants(
             .take_lab_outcome());
     }
 
-    let mutated_test_timeout = if let Some(timeout) = options.test_timeout {
-        timeout
-    } else if let Some(baseline_test_duration) = baseline_outcome
+    let baseline_test_duration = baseline_outcome
         .phase_results()
         .iter()
         .find(|r| r.phase == Phase::Test)
-        .map(|r| r.duration)
-    {
-        let auto_timeout = max(
-            options.minimum_test_timeout,
-            baseline_test_duration.mul_f32(5.0),
-        );
-        if options.show_times {
-            console.autoset_timeout(auto_timeout);
-        }
-        auto_timeout
+        .map(|r| r.duration);
+    let mutated_test_timeout = mutant_test_timeout(&options, baseline_test_duration);
+    if options.test_timeout.is_none() && baseline_test_duration.is_some() && options.show_times {
+        console.autoset_timeout(mutated_test_timeout);
+    }
+
+    if options.confirm && options.jobs.map_or(false, |j| j > 1) {
+        warn!("--confirm tests one mutant at a time, so --jobs is ignored");
+    }
+    let jobs = if options.confirm {
+        1
     } else {
-        Duration::MAX
+        std::cmp::max(1, std::cmp::min(options.jobs.unwrap_or(1), mutants.len()))
     };
-
-    let jobs = std::cmp::max(1, std::cmp::min(options.jobs.unwrap_or(1), mutants.len()));
     console.build_dirs_start(jobs - 1);
     for i in 1..jobs {
         debug!("copy build dir {i}");
-        build_dirs.push(build_dirs[0].copy(console).context("copy build dir")?);
+        build_dirs.push(build_dirs[0].copy(i, console).context("copy build dir")?);
     }
     console.build_dirs_finished();
     debug!(build_dirs = ?build_dirs);
@@ -107,6 +146,8 @@ pub fn test_unmutated_then_all_mutants(
     // scenario to test off the queue, and then exits when there are no more left.
     console.start_testing_mutants(mutants.len());
     let numbered_mutants = Mutex::new(mutants.into_iter().enumerate());
+    // For --stop-function-after-caught, the number of mutants caught in a row in each function.
+    let caught_streaks: Mutex<HashMap<(String, LineColumn), u64>> = Mutex::new(HashMap::new());
     thread::scope(|scope| {
         let mut threads = Vec::new();
         for build_dir in build_dirs {
@@ -120,17 +161,58 @@ pub fn test_unmutated_then_all_mutants(
                     if let Some((mutant_id, mutant)) = next {
                         let _span = debug_span!("mutant", id = mutant_id).entered();
                         debug!(location = %mutant.describe_location(), change = ?mutant.describe_change());
-                        // We don't care about the outcome; it's been collected into the output_dir.
-                        let _outcome = test_scenario(
+                        if options.confirm {
+                            // There's only one thread, so nothing else is drawing.
+                            match console.confirm_mutant(&mutant).expect("ask to confirm mutant") {
+                                Confirmation::Test => (),
+                                Confirmation::Skip => continue,
+                                Confirmation::Quit => break,
+                            }
+                        }
+                        let function_key = (
+                            mutant.source_file.tree_relative_slashes(),
+                            mutant.function_span.start,
+                        );
+                        if let Some(limit) = options.stop_function_after_caught {
+                            let streak = caught_streaks
+                                .lock()
+                                .expect("lock caught streaks")
+                                .get(&function_key)
+                                .copied()
+                                .unwrap_or_default();
+                            if streak >= limit {
+                                debug!(streak, "assume mutant is caught");
+                                output_mutex
+                                    .lock()
+                                    .expect("lock output dir to add assumed caught")
+                                    .add_assumed_caught(&mutant)
+                                    .expect("add assumed caught");
+                                console.mutant_assumed_caught(&mutant, &options);
+                                continue;
+                            }
+                        }
+                        let test_timeout = options.genre_test_timeout(mutant.genre, mutated_test_timeout);
+                        // The outcome has been collected into the output_dir, and is only
+                        // needed here to count caught streaks.
+                        let outcome = test_scenario(
                             tool,
                             &mut build_dir,
                             &output_mutex,
                             &options,
                             &Scenario::Mutant(mutant),
-                            mutated_test_timeout,
+                            test_timeout,
                             console,
                         )
                         .expect("scenario test");
+                        if options.stop_function_after_caught.is_some() {
+                            let mut caught_streaks = caught_streaks.lock().expect("lock caught streaks");
+                            let streak = caught_streaks.entry(function_key).or_default();
+                            if outcome.summary() == SummaryOutcome::CaughtMutant {
+                                *streak += 1;
+                            } else {
+                                *streak = 0;
+                            }
+                        }
                     } else {
                         trace!("no more work");
                         break
@@ -147,6 +229,27 @@ pub fn test_unmutated_then_all_mutants(
         .into_inner()
         .expect("final unlock mutants queue");
     console.lab_finished(&output_dir.lab_outcome, start_time, &options);
+    let annotations = annotate::format_annotations(&output_dir.lab_outcome, &options);
+    if !annotations.is_empty() {
+        console.report(&annotations);
+    }
+    if options.group_output_by == GroupOutputBy::File {
+        console.report(&console::format_missed_by_file(
+            &output_dir.lab_outcome,
+            &options,
+        ));
+    }
+    if options.print_unviable_errors {
+        console.report(&console::format_unviable_mutants(&output_dir.lab_outcome));
+    }
+    if let Some(report_template) = &options.report_template {
+        console.report(&report_template.format(&output_dir.lab_outcome));
+    }
+    if let (Some(previous_outcomes), Some(previous_path)) = (&previous_outcomes, &options.compare) {
+        let comparison = Comparison::new(previous_outcomes, &output_dir.lab_outcome);
+        console.report(&comparison.format(previous_path));
+        output_dir.write_comparison(&comparison)?;
+    }
     let lab_outcome = output_dir.take_lab_outcome();
     if lab_outcome.total_mutants == 0 {
         // This should be unreachable as we also bail out before copying
@@ -158,6 +261,82 @@ pub fn test_unmutated_then_all_mutants(
     Ok(lab_outcome)
 }
 
+/// Test a single mutant, without testing the baseline first.
+///
+/// The output directory is created in `output_in_dir`, and holds the log for the mutant.
+pub fn test_one_mutant(
+    tool: &dyn Tool,
+    source_tree: &Utf8Path,
+    output_in_dir: &Utf8Path,
+    mutant: Mutant,
+    options: &Options,
+    console: &Console,
+) -> Result<LabOutcome> {
+    let output_dir = OutputDir::new(output_in_dir)?;
+    console.set_debug_log(output_dir.open_debug_log()?);
+    let output_mutex = Mutex::new(output_dir);
+    let mut build_dir = BuildDir::new(source_tree, options, console)?;
+    let test_timeout = options.genre_test_timeout(mutant.genre, mutant_test_timeout(options, None));
+    // The outcome is also collected into the output_dir.
+    let _outcome = test_scenario(
+        tool,
+        &mut build_dir,
+        &output_mutex,
+        options,
+        &Scenario::Mutant(mutant),
+        test_timeout,
+        console,
+    )?;
+    Ok(output_mutex
+        .into_inner()
+        .expect("unlock output_dir")
+        .take_lab_outcome())
+}
+
+/// The timeout for testing mutants: from `--timeout` if it's set, or otherwise five times
+/// as long as the baseline tests took, but at least `--minimum-test-timeout`.
+///
+/// If the baseline wasn't tested, the timeout is the minimum.
+fn mutant_test_timeout(options: &Options, baseline_test_duration: Option<Duration>) -> Duration {
+    options.test_timeout.unwrap_or_else(|| {
+        max(
+            options.minimum_test_timeout,
+            baseline_test_duration.map_or(Duration::ZERO, |duration| duration.mul_f32(5.0)),
+        )
+    })
+}
+
+/// Run the user's baseline command through the shell in the build dir, before the baseline
+/// is tested, and return true if it succeeded.
+///
+/// The command is run once, in the first build dir, so anything it generates is copied
+/// into the other build dirs.
+fn run_baseline_command(
+    command: &str,
+    build_dir: &BuildDir,
+    options: &Options,
+    log_file: &mut LogFile,
+    console: &Console,
+) -> Result<bool> {
+    let _span = debug_span!("baseline command").entered();
+    let argv = shell_argv(command);
+    if options.verbose {
+        log_file.echo_with_prefix("[baseline-command]".to_owned());
+    }
+    let process_status = Process::run(
+        &argv,
+        &[],
+        build_dir.path(),
+        options.baseline_timeout.unwrap_or(Duration::MAX),
+        &ResourceLimits::default(),
+        log_file,
+        console,
+    )?;
+    check_interrupted()?;
+    debug!(?process_status);
+    Ok(process_status.success())
+}
+
 /// Test various phases of one scenario in a build dir.
 ///
 /// The [BuildDir] is passed as mutable because it's for the exclusive use of this function for the
@@ -180,6 +359,12 @@ fn test_scenario(
         log_file.message(&format!("mutation diff:\n{}", mutant.diff()));
         mutant.apply(build_dir)?;
     }
+    if options.verbose {
+        log_file.echo_with_prefix(match scenario {
+            Scenario::Mutant(mutant) => format!("[{}]", mutant.id),
+            _ => "[baseline]".to_owned(),
+        });
+    }
     console.scenario_started(scenario, log_file.path());
 
     let mut outcome = ScenarioOutcome::new(&log_file, scenario.clone());
@@ -198,14 +383,37 @@ fn test_scenario(
         };
         let argv = tool.compose_argv(build_dir, scenario, phase, options)?;
         let env = tool.compose_env(scenario, phase, options)?;
-        let process_status = Process::run(
-            &argv,
-            &env,
-            build_dir.path(),
-            timeout,
-            &mut log_file,
-            console,
-        )?;
+        let limits = match (phase, scenario) {
+            (Phase::Test, Scenario::Mutant(_)) => options.resource_limits,
+            _ => ResourceLimits::default(),
+        };
+        let mut retries = 0;
+        let process_status = loop {
+            let log_start = log_file.end_offset();
+            let process_status = Process::run(
+                &argv,
+                &env,
+                build_dir.path(),
+                timeout,
+                &limits,
+                &mut log_file,
+                console,
+            )?;
+            if process_status == ProcessStatus::Failure
+                && retries < MAX_TRANSIENT_RETRIES
+                && tool.is_transient_failure(&log_file.read_from(log_start)?)
+            {
+                retries += 1;
+                log_file.message(&format!(
+                    "{phase} failed for a transient reason; retry {retries} of {MAX_TRANSIENT_RETRIES}"
+                ));
+                debug!(?phase, retries, "retry after transient failure");
+                sleep(TRANSIENT_RETRY_DELAY * retries);
+                check_interrupted()?;
+                continue;
+            }
+            break process_status;
+        };
         check_interrupted()?;
         debug!(?process_status, elapsed = ?start.elapsed());
         let phase_result = PhaseResult {
@@ -228,6 +436,12 @@ fn test_scenario(
         .expect("lock output dir to add outcome")
         .add_scenario_outcome(&outcome)?;
     debug!(outcome = ?outcome.summary());
+    if options.output_format == OutputFormat::Jsonl && scenario.is_mutant() {
+        println!(
+            "{}",
+            serde_json::to_string(&outcome).context("serialize outcome")?
+        );
+    }
     console.scenario_finished(scenario, &outcome, options);
 
     Ok(outcome)
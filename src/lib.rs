@@ -0,0 +1,822 @@
+// Copyright 2021-2023 Martin Pool
+
+//! `cargo-mutants`: Find inadequately-tested code that can be removed without any tests failing.
+//!
+//! This is mostly used through the `cargo mutants` command, which is a thin wrapper
+//! around this library. Programs that want to drive mutation testing themselves, such
+//! as test orchestrators, can call [run] and inspect the [RunReport]:
+//!
+//! ```no_run
+//! use camino::Utf8Path;
+//! use cargo_mutants::{run, MutantOutcome};
+//!
+//! let report = run(Utf8Path::new("."), ["--jobs", "4", "--timeout", "60"])?;
+//! for mutant in &report.mutants {
+//!     if mutant.outcome == MutantOutcome::Missed {
+//!         println!("missed: {}", mutant.name);
+//!     }
+//! }
+//! println!("{} of {} mutants caught", report.caught, report.total_mutants);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Only [run], [RunReport], and the types they contain are meant to be
+//! used from other crates; the rest of the library may change without notice.
+
+mod annotate;
+mod badge;
+mod build_dir;
+mod cargo;
+mod clean;
+mod compare;
+mod config;
+mod console;
+mod coverage;
+mod exit_code;
+mod explain;
+mod export;
+mod hook;
+mod in_diff;
+mod interrupt;
+mod known_mutants;
+mod lab;
+mod log_file;
+mod manifest;
+mod mutate;
+mod options;
+mod outcome;
+mod output;
+mod path;
+mod process;
+mod report;
+mod report_template;
+mod scenario;
+mod source;
+mod textedit;
+mod tool;
+mod visit;
+
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use clap::CommandFactory;
+use clap::{Parser, Subcommand};
+use clap_complete::{generate, Shell};
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+// Imports of public names from this crate.
+use crate::annotate::AnnotationFormat;
+use crate::build_dir::BuildDir;
+use crate::cargo::CargoTool;
+use crate::console::{Console, GroupOutputBy, OutputFormat};
+use crate::in_diff::ChangedLines;
+use crate::interrupt::check_interrupted;
+use crate::log_file::{last_line, LogFile};
+use crate::manifest::fix_manifest;
+use crate::mutate::Mutant;
+use crate::outcome::{LabOutcome, Phase, ScenarioOutcome, TimeoutPolicy};
+use crate::path::Utf8PathSlashes;
+use crate::process::{parse_memory_size, ResourceLimits};
+use crate::scenario::Scenario;
+use crate::source::SourceFile;
+use crate::tool::Tool;
+use crate::visit::{prioritize_untested, walk_tree};
+
+// The public API, for use by other crates.
+pub use crate::mutate::Genre;
+pub use crate::options::Options;
+pub use crate::report::{MutantOutcome, MutantReport, RunReport};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const NAME: &str = env!("CARGO_PKG_NAME");
+
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    #[command(name = "mutants")]
+    Mutants(Args),
+}
+
+/// Find inadequately-tested code that can be removed without any tests failing.
+///
+/// See <https://github.com/sourcefrog/cargo-mutants> for more information.
+#[derive(Parser, PartialEq, Debug)]
+#[command(author, about)]
+struct Args {
+    /// show cargo output for all invocations (very verbose).
+    #[arg(long)]
+    all_logs: bool,
+
+    /// stream cargo output for every scenario to the terminal while it runs, with each
+    /// line prefixed by the mutant id.
+    #[arg(long)]
+    verbose: bool,
+
+    /// print annotations for missed mutants in this format; by default, github
+    /// if running in GitHub Actions.
+    #[arg(long, value_enum)]
+    annotate: Option<AnnotationFormat>,
+
+    /// print mutants that were caught by tests.
+    #[arg(long, short = 'v')]
+    caught: bool,
+
+    /// cargo check generated mutants, but don't run tests.
+    #[arg(long)]
+    check: bool,
+
+    /// show each mutant's diff and ask whether to test it, skip it, or quit;
+    /// only when running in a terminal.
+    #[arg(long)]
+    confirm: bool,
+
+    /// read configuration from this file, instead of .cargo/mutants.toml.
+    #[arg(long, conflicts_with = "no_config")]
+    config: Option<Utf8PathBuf>,
+
+    /// merge the named profile from the config file, like `[profiles.quick]`, over the
+    /// rest of the config.
+    #[arg(long, value_name = "NAME", conflicts_with = "no_config")]
+    config_profile: Option<String>,
+
+    /// generate autocompletions for the given shell.
+    #[arg(long)]
+    completions: Option<Shell>,
+
+    /// show the mutation diffs.
+    #[arg(long)]
+    diff: bool,
+
+    /// rust crate directory to examine.
+    #[arg(long, short = 'd')]
+    dir: Option<Utf8PathBuf>,
+
+    /// return this error values from functions returning Result:
+    /// for example, `::anyhow::anyhow!("mutated")`.
+    #[arg(long)]
+    error: Vec<String>,
+
+    /// regex for mutations to examine, matched against the names shown by `--list`.
+    #[arg(long = "re", short = 'F')]
+    examine_re: Vec<String>,
+
+    /// fail if the percentage of viable mutants that are caught is below this, rather than
+    /// if any are missed.
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    fail_under: Option<f64>,
+
+    /// stop testing the mutants in a function once this many in a row have been caught,
+    /// and report the rest as assumed caught: faster, but only an approximation.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    stop_function_after_caught: Option<u64>,
+
+    /// after testing, write the mutation score to this file as a shields.io badge.
+    #[arg(long, value_name = "PATH")]
+    badge_json: Option<Utf8PathBuf>,
+
+    /// after testing, write the counts of each outcome and the mutation score to this
+    /// file as json.
+    #[arg(long, value_name = "PATH")]
+    summary_json: Option<Utf8PathBuf>,
+
+    /// after testing, print a line for each mutant from the template in this file, with
+    /// placeholders like `{file}`, `{line}`, `{function}`, `{replacement}`, and `{outcome}`.
+    #[arg(long, value_name = "FILE")]
+    report_template: Option<Utf8PathBuf>,
+
+    /// after testing, run this shell command, with the outcome in environment variables.
+    #[arg(long, value_name = "COMMAND")]
+    on_finish: Option<String>,
+
+    /// write a patch file for each mutant into this directory, don't run anything.
+    #[arg(long, value_name = "DIR")]
+    export_patches: Option<Utf8PathBuf>,
+
+    /// glob for files to exclude; with no glob, all files are included; globs containing
+    /// slash match the entire path. If used together with `--file` argument, then the files to be examined are matched before the files to be excluded.
+    #[arg(long, short = 'e')]
+    exclude: Vec<String>,
+
+    /// exclude the files listed in this file, one tree-relative path per line.
+    #[arg(long, value_name = "FILE")]
+    exclude_file_list: Option<Utf8PathBuf>,
+
+    /// regex for mutations to exclude, matched against the names shown by `--list`.
+    #[arg(long, short = 'E')]
+    exclude_re: Vec<String>,
+
+    /// don't mutate functions, impls, or modules marked `#[doc(hidden)]`.
+    #[arg(long)]
+    exclude_doc_hidden: bool,
+
+    /// replace the bodies of functions that always panic or loop forever, which are
+    /// skipped by default.
+    #[arg(long)]
+    mutate_diverging_fns: bool,
+
+    /// don't mutate functions with type parameters, whose mutants are often unviable.
+    #[arg(long)]
+    exclude_generics: bool,
+
+    /// glob for files to examine; with no glob, all files are examined; globs containing
+    /// slash match the entire path. If used together with `--exclude` argument, then the files to be examined are matched before the files to be excluded.
+    #[arg(long, short = 'f')]
+    file: Vec<String>,
+
+    /// examine only the files listed in this file, one tree-relative path per line.
+    #[arg(long, value_name = "FILE")]
+    examine_file_list: Option<Utf8PathBuf>,
+
+    /// after testing, list the missed mutants grouped by source file.
+    #[arg(long, value_enum, default_value_t = GroupOutputBy::None)]
+    group_output_by: GroupOutputBy,
+
+    /// also generate mutants of this genre, which is not generated by default.
+    #[arg(long, value_enum)]
+    genre: Vec<Genre>,
+
+    /// don't generate the genres that are enabled by default: only those named by
+    /// --genre, or in the config file.
+    #[arg(long)]
+    no_default_genres: bool,
+
+    /// skip impls of this trait, like `Display`, instead of the default set of
+    /// `Debug`, `Display` and `Error`.
+    #[arg(long, value_name = "TRAIT")]
+    skip_trait: Vec<String>,
+
+    /// generate at most this many mutants from each function.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_mutants_per_function: Option<u64>,
+
+    /// run this many cargo build/test jobs in parallel.
+    #[arg(long, short = 'j', env = "CARGO_MUTANTS_JOBS")]
+    jobs: Option<usize>,
+
+    /// output json (only for --list).
+    #[arg(long)]
+    json: bool,
+
+    /// output a markdown table (only for --list).
+    #[arg(long, conflicts_with = "json")]
+    markdown: bool,
+
+    /// with --list, show the original value returned by each function whose body is
+    /// replaced.
+    #[arg(long, requires = "list", conflicts_with_all = ["json", "markdown"])]
+    show_original: bool,
+
+    /// with --list, show how many mutants there are of each genre, with some examples.
+    #[arg(long, requires = "list", conflicts_with_all = ["json", "markdown", "diff"])]
+    group_by_genre: bool,
+
+    /// don't delete the scratch directories, for debugging.
+    #[arg(long)]
+    leak_dirs: bool,
+
+    /// keep the build directories in mutants.build between runs, so that dependencies
+    /// don't need to be rebuilt each time.
+    #[arg(long)]
+    reuse_build_dir: bool,
+
+    /// delete mutants.out, mutants.out.old, mutants.build, and scratch directories left
+    /// by --leak-dirs, then exit.
+    #[arg(long)]
+    clean: bool,
+
+    /// before testing, delete mutants.out.old and scratch directories left by
+    /// --leak-dirs if they're older than this many days.
+    #[arg(long, value_name = "DAYS", value_parser = parse_days)]
+    max_log_age: Option<f64>,
+
+    /// log level for stdout (trace, debug, info, warn, error).
+    #[arg(
+        long,
+        short = 'L',
+        default_value = "info",
+        env = "CARGO_MUTANTS_TRACE_LEVEL"
+    )]
+    level: tracing::Level,
+
+    /// just list possible mutants, don't run them.
+    #[arg(long)]
+    list: bool,
+
+    /// list source files, don't run anything.
+    #[arg(long)]
+    list_files: bool,
+
+    /// list the mutants missed in this earlier outcomes.json, grouped by file with
+    /// their diffs, without running anything.
+    #[arg(long, value_name = "REPORT", conflicts_with_all = ["list", "list_files"])]
+    list_survivors: Option<Utf8PathBuf>,
+
+    /// list how many tests cover each mutant, according to this lcov tracefile with a
+    /// record for each test, without running anything.
+    #[arg(long, value_name = "LCOV", conflicts_with_all = ["list_files", "list_survivors"])]
+    list_affected_tests: Option<Utf8PathBuf>,
+
+    /// the directory of the tree where the `--list-affected-tests` tracefile was
+    /// collected, if it's not this one, such as another checkout.
+    #[arg(long, value_name = "DIR", requires = "list_affected_tests")]
+    coverage_prefix: Option<Utf8PathBuf>,
+
+    /// show how cargo-mutants sees the functions, impls, and modules in one source file,
+    /// relative to the tree root, and why any were skipped, without running anything.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["list", "list_files", "list_survivors", "list_affected_tests"]
+    )]
+    dump_ast: Option<Utf8PathBuf>,
+
+    /// print the effective options, after reading the config file, as json, and don't
+    /// run anything.
+    #[arg(long)]
+    print_config: bool,
+
+    /// list the genres of mutation that can be generated, don't run anything.
+    #[arg(long)]
+    list_genres: bool,
+
+    /// don't read .cargo/mutants.toml.
+    #[arg(long)]
+    no_config: bool,
+
+    /// don't copy the /target directory, and don't build the source tree first.
+    #[arg(long)]
+    no_copy_target: bool,
+
+    /// don't print times or tree sizes, to make output deterministic.
+    #[arg(long)]
+    no_times: bool,
+
+    /// create mutants.out within this directory.
+    #[arg(long, short = 'o')]
+    output: Option<Utf8PathBuf>,
+
+    /// format of the output while testing: jsonl writes one json object per line to
+    /// stdout for the result of each mutant, instead of output for people.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human, conflicts_with = "confirm")]
+    output_format: OutputFormat,
+
+    /// run mutants in random order.
+    #[arg(long)]
+    shuffle: bool,
+
+    /// with --since-commits or --since-tag, show the lines changed in git and the
+    /// mutants selected by them, don't run anything.
+    #[arg(long, requires = "diff_base")]
+    dry_run_diff_only: bool,
+
+    /// only mutate functions changed in this many of the most recent git commits.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        group = "diff_base"
+    )]
+    since_commits: Option<u64>,
+
+    /// only mutate functions changed since this git tag, or since the most recent tag
+    /// if the tag is `latest`.
+    #[arg(long, value_name = "TAG", group = "diff_base")]
+    since_tag: Option<String>,
+
+    /// with --since-commits or --since-tag, mutate everything in the packages
+    /// containing changed files, rather than only the changed functions.
+    #[arg(long, requires = "diff_base")]
+    only_package_with_changes: bool,
+
+    /// with --since-commits or --since-tag, only mutate files added since then, or not
+    /// yet tracked by git, skipping files that were only modified.
+    #[arg(
+        long,
+        requires = "diff_base",
+        conflicts_with = "only_package_with_changes"
+    )]
+    only_new_files: bool,
+
+    /// with --only-package-with-changes, also mutate packages in the workspace that
+    /// depend on the changed packages.
+    #[arg(long, requires = "only_package_with_changes")]
+    include_dependents: bool,
+
+    /// after testing, compare the outcomes with this outcomes.json from an earlier run.
+    #[arg(long, value_name = "REPORT")]
+    compare: Option<Utf8PathBuf>,
+
+    /// only test mutants that are not in this earlier mutants.json or outcomes.json.
+    #[arg(long, value_name = "REPORT")]
+    only_missing_from: Option<Utf8PathBuf>,
+
+    /// run mutants in the fixed order they occur in the source tree.
+    #[arg(long)]
+    no_shuffle: bool,
+
+    /// test mutants first in functions not named by any test in the same file.
+    #[arg(long)]
+    prioritize_untested: bool,
+
+    /// maximum run time for the tests of each mutant, in seconds.
+    #[arg(long, short = 't')]
+    timeout: Option<f64>,
+
+    /// maximum run time for the tests of the unmutated baseline, in seconds; by default
+    /// there is no limit.
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    baseline_timeout: Option<f64>,
+
+    /// run this shell command in the build directory before testing the baseline, for
+    /// example to generate code; if it fails, no mutants are tested.
+    #[arg(long, value_name = "COMMAND")]
+    baseline_command: Option<String>,
+
+    /// run the tests of the baseline and every mutant with this many test threads.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    test_threads: Option<u64>,
+
+    /// how to count mutants whose tests time out.
+    #[arg(long, value_enum, default_value_t = TimeoutPolicy::Separate)]
+    timeout_is: TimeoutPolicy,
+
+    /// test timeout for mutants of one genre, in seconds, like `fn-value=30`.
+    #[arg(long, value_name = "GENRE=SECS")]
+    timeout_per_genre: Vec<String>,
+
+    /// limit the virtual memory of cargo test and each process it starts, including rustc
+    /// and the test binaries, when testing a mutant, like `2G`; Unix only.
+    #[arg(long, value_name = "SIZE", value_parser = parse_memory_size)]
+    memory_limit: Option<u64>,
+
+    /// limit the CPU time of cargo test and each process it starts, including rustc and the
+    /// test binaries, when testing a mutant, in seconds; Unix only.
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    cpu_limit: Option<f64>,
+
+    /// minimum timeout for tests, in seconds, as a lower bound on the auto-set time.
+    #[arg(long, env = "CARGO_MUTANTS_MINIMUM_TEST_TIMEOUT")]
+    minimum_test_timeout: Option<f64>,
+
+    /// print mutations that failed to check or build.
+    #[arg(long, short = 'V')]
+    unviable: bool,
+
+    /// after testing, list unviable mutants with the start of their compiler errors.
+    #[arg(long)]
+    unviable_errors: bool,
+
+    /// show version and quit.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    version: bool,
+
+    /// additional args for all cargo invocations.
+    #[arg(long, short = 'C', allow_hyphen_values = true)]
+    cargo_arg: Vec<String>,
+
+    /// build and test with this cargo profile, like `release`; by default, cargo's `dev`
+    /// profile.
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    // The following option captures all the remaining non-option args, to
+    // send to cargo.
+    /// pass remaining arguments to cargo test after all options and after `--`.
+    #[arg(last = true)]
+    cargo_test_args: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, PartialEq, Debug)]
+enum Command {
+    /// test one mutant, chosen by its id, and explain the outcome.
+    Explain {
+        /// the id of the mutant, as shown by `--list --json` or in `mutants.json`.
+        id: String,
+    },
+}
+
+/// Test all the mutants in the source tree containing `source_dir`, after checking
+/// that the unmutated tree passes its tests, and return the outcome of each.
+///
+/// `args` are the same options as for `cargo mutants`, like `["--timeout", "60"]`, and
+/// the tree's `.cargo/mutants.toml` is read just as it is by the command line, unless
+/// `--no-config` is given. Options that choose another mode, such as `--list`, are
+/// ignored. Progress is drawn on the terminal as it is by `cargo mutants`, and the logs
+/// and results are written to `mutants.out` as usual.
+///
+/// An error is returned if the arguments are invalid or the run could not be
+/// completed, for example because the tree could not be found or copied. Mutants that
+/// are missed, and a baseline that fails its tests, are reported in the [RunReport]
+/// rather than as errors.
+pub fn run<I, S>(source_dir: &Utf8Path, args: I) -> Result<RunReport>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args = Args::try_parse_from(
+        ["mutants".to_owned()]
+            .into_iter()
+            .chain(args.into_iter().map(|s| s.as_ref().to_owned())),
+    )?;
+    let tool = CargoTool::new();
+    let source_tree_root = tool.find_root(source_dir)?;
+    let config = read_config(&args, source_dir, &source_tree_root)?;
+    let mut options = Options::new(&args, &config)?;
+    options.manifest_profiles = manifest::read_profiles(&source_tree_root)?;
+    let lab_outcome = test_mutants(&tool, &source_tree_root, &args, &options, &Console::new())?;
+    Ok(RunReport::new(&lab_outcome, &options))
+}
+
+/// Run the `cargo mutants` command line, as the `cargo-mutants` binary does.
+#[doc(hidden)]
+pub fn cli_main() -> Result<()> {
+    let args = match Cargo::try_parse() {
+        Ok(Cargo::Mutants(args)) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(exit_code::USAGE);
+        }
+    };
+    let console = match args.output_format {
+        OutputFormat::Human => Console::new(),
+        OutputFormat::Jsonl => Console::new_for_json_lines(),
+    };
+    console.setup_global_trace(args.level)?;
+    interrupt::install_handler();
+
+    if args.version {
+        println!("{NAME} {VERSION}");
+        return Ok(());
+    } else if let Some(shell) = args.completions {
+        generate(shell, &mut Cargo::command(), "cargo", &mut io::stdout());
+        return Ok(());
+    } else if args.list_genres {
+        list_genres(args.json)?;
+        return Ok(());
+    }
+
+    let source_path: &Utf8Path = if let Some(p) = &args.dir {
+        p
+    } else {
+        Utf8Path::new(".")
+    };
+    let tool = CargoTool::new();
+    let source_tree_root = tool.find_root(source_path)?;
+    let config = read_config(&args, source_path, &source_tree_root)?;
+    let mut options = Options::new(&args, &config)?;
+    options.manifest_profiles = manifest::read_profiles(&source_tree_root)?;
+    debug!(?options);
+    if args.print_config {
+        serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &options)?;
+        println!();
+    } else if let Some(Command::Explain { id }) = &args.command {
+        let lab_outcome =
+            explain::explain_mutant(&tool, &source_tree_root, id, &options, &console)?;
+        exit(lab_outcome.exit_code(&options));
+    } else if args.clean {
+        let output_in_dir = options
+            .output_in_dir
+            .as_deref()
+            .unwrap_or(&source_tree_root);
+        for path in clean::clean(&source_tree_root, output_in_dir)? {
+            println!("Removed {}", path.display());
+        }
+    } else if args.list_files {
+        list_files(&tool, &source_tree_root, &options, args.json)?;
+    } else if let Some(report_path) = &args.list_survivors {
+        let previous = compare::read_previous_outcomes(report_path)?;
+        let discovered = walk_tree(&tool, &source_tree_root, &options)?;
+        let survivors = compare::Survivors::new(&previous, discovered.mutants);
+        print!("{}", survivors.format(report_path));
+    } else if let Some(lcov_path) = &args.list_affected_tests {
+        let coverage = coverage::Coverage::read(
+            lcov_path,
+            args.coverage_prefix.as_deref().unwrap_or(&source_tree_root),
+        )?;
+        let discovered = walk_tree(&tool, &source_tree_root, &options)?;
+        if args.json {
+            serde_json::to_writer_pretty(
+                io::BufWriter::new(io::stdout()),
+                &coverage.affected_tests_json(&discovered.mutants),
+            )?;
+        } else {
+            print!("{}", coverage.format_affected_tests(&discovered.mutants));
+        }
+    } else if let Some(path) = &args.dump_ast {
+        let path = path.strip_prefix(&source_tree_root).unwrap_or(path);
+        print!("{}", visit::dump_ast(&source_tree_root, path, &options)?);
+    } else if let Some(export_dir) = &args.export_patches {
+        let discovered = walk_tree(&tool, &source_tree_root, &options)?;
+        export::export_patches(&discovered.mutants, export_dir)?;
+    } else if args.dry_run_diff_only {
+        let changed_lines = ChangedLines::for_options(&source_tree_root, &options)?
+            .expect("--dry-run-diff-only requires --since-commits or --since-tag");
+        println!("Changed lines:");
+        print!("{}", changed_lines.describe());
+        let discovered = walk_tree(&tool, &source_tree_root, &options)?;
+        println!("Selected mutants:");
+        console::list_mutants(&discovered.mutants, args.diff, false);
+    } else if args.list {
+        let discovered = walk_tree(&tool, &source_tree_root, &options)?;
+        if args.json {
+            if args.diff {
+                eprintln!("--list --diff --json is not (yet) supported");
+                exit(exit_code::USAGE);
+            }
+            serde_json::to_writer_pretty(io::BufWriter::new(io::stdout()), &discovered.mutants)?;
+        } else if args.markdown {
+            if args.diff {
+                eprintln!("--list --diff --markdown is not supported");
+                exit(exit_code::USAGE);
+            }
+            print!("{}", console::format_mutants_markdown(&discovered.mutants));
+        } else if args.group_by_genre {
+            print!("{}", console::format_mutants_by_genre(&discovered.mutants));
+        } else {
+            console::list_mutants(&discovered.mutants, args.diff, args.show_original);
+        }
+    } else {
+        let lab_outcome = test_mutants(&tool, &source_tree_root, &args, &options, &console)?;
+        exit(lab_outcome.exit_code(&options));
+    }
+    Ok(())
+}
+
+/// Read the config for the tree, from the file given by `--config`, or otherwise found
+/// in the tree or `CARGO_MUTANTS_CONFIG_DIR`, or none with `--no-config`.
+fn read_config(
+    args: &Args,
+    source_path: &Utf8Path,
+    source_tree_root: &Utf8Path,
+) -> Result<config::Config> {
+    if args.no_config {
+        return Ok(config::Config::default());
+    }
+    let config = if let Some(config_path) = &args.config {
+        config::Config::read_explicit_file(config_path, args.config_profile.as_deref())?
+    } else {
+        let config_dir = env::var_os("CARGO_MUTANTS_CONFIG_DIR")
+            .map(|dir| Utf8PathBuf::try_from(PathBuf::from(dir)))
+            .transpose()
+            .context("CARGO_MUTANTS_CONFIG_DIR is not UTF-8")?;
+        if let Some(config_path) =
+            config::find_config_file(config_dir.as_deref(), source_path, source_tree_root)?
+        {
+            let shown_path = config_path
+                .strip_prefix(source_tree_root)
+                .unwrap_or(&config_path);
+            // Listings go to stdout and are meant to be parsed, so don't add to them.
+            if args.list
+                || args.list_files
+                || args.list_affected_tests.is_some()
+                || args.print_config
+                || args.dump_ast.is_some()
+            {
+                debug!("Read config from {shown_path}");
+            } else {
+                info!("Read config from {shown_path}");
+            }
+            config::Config::read_file(&config_path, args.config_profile.as_deref())?
+        } else if let Some(profile) = &args.config_profile {
+            bail!("config profile {profile:?} was requested, but no config file was found");
+        } else {
+            config::Config::default()
+        }
+    };
+    debug!(?config);
+    Ok(config)
+}
+
+/// Test the baseline and then all the mutants, and write the summary and badge and run
+/// the hook requested by `args`.
+fn test_mutants(
+    tool: &CargoTool,
+    source_tree_root: &Utf8Path,
+    args: &Args,
+    options: &Options,
+    console: &Console,
+) -> Result<LabOutcome> {
+    let start_time = Instant::now();
+    let lab_outcome =
+        lab::test_unmutated_then_all_mutants(tool, source_tree_root, options.clone(), console)?;
+    if let Some(badge_path) = &args.badge_json {
+        badge::write_badge_json(&lab_outcome, options, badge_path)?;
+    }
+    let elapsed = start_time.elapsed();
+    if let Some(summary_path) = &args.summary_json {
+        lab_outcome.write_summary_json(options, elapsed, summary_path)?;
+    }
+    if let Some(command) = &args.on_finish {
+        hook::run_on_finish(
+            command,
+            &lab_outcome.lab_summary(options, elapsed),
+            lab_outcome.exit_code(options),
+        )?;
+    }
+    Ok(lab_outcome)
+}
+
+/// Parse a percentage between 0 and 100.
+fn parse_percent(s: &str) -> Result<f64> {
+    let percent: f64 = s.parse().context("not a number")?;
+    if !(0.0..=100.0).contains(&percent) {
+        anyhow::bail!("must be between 0 and 100");
+    }
+    Ok(percent)
+}
+
+fn parse_days(s: &str) -> Result<f64> {
+    let days: f64 = s.parse().context("not a number")?;
+    if days.is_finite() && days >= 0.0 && days * 86400.0 < u64::MAX as f64 {
+        Ok(days)
+    } else {
+        anyhow::bail!("must be a non-negative number of days")
+    }
+}
+
+/// Parse a positive number of seconds.
+fn parse_secs(s: &str) -> Result<f64> {
+    let secs: f64 = s.parse().context("not a number")?;
+    if secs.is_finite() && secs > 0.0 && secs < u64::MAX as f64 {
+        Ok(secs)
+    } else {
+        anyhow::bail!("must be a positive number of seconds")
+    }
+}
+
+fn list_genres(json: bool) -> Result<()> {
+    let mut out = io::BufWriter::new(io::stdout());
+    if json {
+        let json_list = Value::Array(
+            Genre::all()
+                .map(|genre| {
+                    json!({
+                        "genre": genre,
+                        "name": genre.cli_name(),
+                        "description": genre.description(),
+                        "enabled_by_default": genre.enabled_by_default(),
+                    })
+                })
+                .collect(),
+        );
+        serde_json::to_writer_pretty(out, &json_list)?;
+    } else {
+        let name_width = Genre::all().map(|g| g.cli_name().len()).max().unwrap_or(0) + 2;
+        let genre_width = Genre::all()
+            .map(|g| format!("{g:?}").len())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        for genre in Genre::all() {
+            writeln!(
+                out,
+                "{:<name_width$}{:<genre_width$}{}{}",
+                genre.cli_name(),
+                format!("{genre:?}"),
+                genre.description(),
+                if genre.enabled_by_default() {
+                    ""
+                } else {
+                    " (opt-in)"
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn list_files(tool: &dyn Tool, source: &Utf8Path, options: &Options, json: bool) -> Result<()> {
+    let discovered = walk_tree(tool, source, options)?;
+    let mut out = io::BufWriter::new(io::stdout());
+    if json {
+        let json_list = Value::Array(
+            discovered
+                .files
+                .iter()
+                .map(|source_file| {
+                    json!({
+                        // to_string so that we get it with slashes.
+                        "path": source_file.tree_relative_path.to_string(),
+                        "package": source_file.package.name,
+                    })
+                })
+                .collect(),
+        );
+        serde_json::to_writer_pretty(out, &json_list)?;
+    } else {
+        for file in discovered.files {
+            writeln!(out, "{}", file.tree_relative_path)?;
+        }
+    }
+    Ok(())
+}
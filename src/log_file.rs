@@ -4,7 +4,7 @@
 //! and test cases, mixed with commentary from cargo-mutants.
 
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 use anyhow::Context;
 use camino::{Utf8Path, Utf8PathBuf};
@@ -19,6 +19,11 @@ pub const LOG_MARKER: &str = "***";
 pub struct LogFile {
     path: Utf8PathBuf,
     write_to: File,
+    /// If set, output added to the log is also echoed to the terminal, with this
+    /// prefix on each line.
+    echo_prefix: Option<String>,
+    /// The offset up to which the log has been echoed.
+    echoed_to: u64,
 }
 
 impl LogFile {
@@ -38,7 +43,14 @@ impl LogFile {
                 .create_new(true)
                 .open(&path)
             {
-                Ok(write_to) => return Ok(LogFile { path, write_to }),
+                Ok(write_to) => {
+                    return Ok(LogFile {
+                        path,
+                        write_to,
+                        echo_prefix: None,
+                        echoed_to: 0,
+                    })
+                }
                 Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
                 Err(e) => return Err(anyhow::Error::from(e).context("create test log file")),
             }
@@ -65,6 +77,52 @@ impl LogFile {
     pub fn path(&self) -> &Utf8Path {
         &self.path
     }
+
+    /// Return the current length of the log, to later read what was written after this point.
+    pub fn end_offset(&self) -> u64 {
+        fs::metadata(&self.path).map_or(0, |m| m.len())
+    }
+
+    /// From now on, echo everything added to the log to the terminal, with a prefix on
+    /// each line.
+    pub fn echo_with_prefix(&mut self, prefix: String) {
+        self.echoed_to = self.end_offset();
+        self.echo_prefix = Some(prefix);
+    }
+
+    /// Return the lines added to the log since this was last called, each with the echo
+    /// prefix, or an empty string if the log isn't echoed.
+    ///
+    /// A partial line at the end is held back until it's complete, unless `finished`.
+    pub fn take_echo_lines(&mut self, finished: bool) -> Result<String> {
+        let Some(prefix) = &self.echo_prefix else {
+            return Ok(String::new());
+        };
+        let mut file = File::open(&self.path).with_context(|| format!("open {}", self.path))?;
+        file.seek(SeekFrom::Start(self.echoed_to))?;
+        let mut new = Vec::new();
+        file.read_to_end(&mut new)
+            .with_context(|| format!("read log {}", self.path))?;
+        let len = if finished {
+            new.len()
+        } else {
+            new.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1)
+        };
+        self.echoed_to += len as u64;
+        Ok(String::from_utf8_lossy(&new[..len])
+            .lines()
+            .map(|line| format!("{prefix} {line}\n"))
+            .collect())
+    }
+
+    /// Read everything written to the log from `offset` onwards.
+    pub fn read_from(&self, offset: u64) -> Result<String> {
+        let content = fs::read(&self.path).with_context(|| format!("read log {}", self.path))?;
+        Ok(
+            String::from_utf8_lossy(content.get(offset as usize..).unwrap_or_default())
+                .into_owned(),
+        )
+    }
 }
 
 /// Return the last non-empty line from a file, if it has any content.
@@ -82,6 +140,25 @@ pub fn last_line(path: &Utf8Path) -> Result<String> {
         .to_owned())
 }
 
+/// The most lines of a compiler error to return from [first_compiler_error].
+const MAX_ERROR_LINES: usize = 20;
+
+/// Return the first compiler error message from a log, up to the blank line that ends
+/// it, or None if there is no error in the log.
+pub fn first_compiler_error(log: &str) -> Option<String> {
+    let mut lines = log.lines().skip_while(|line| !line.starts_with("error"));
+    let first = lines.next()?;
+    let mut error = first.to_owned();
+    for line in lines
+        .take_while(|line| !line.trim().is_empty())
+        .take(MAX_ERROR_LINES - 1)
+    {
+        error.push('\n');
+        error.push_str(line);
+    }
+    Some(error)
+}
+
 fn clean_filename(s: &str) -> String {
     let s = s.replace('/', "__");
     s.chars()
@@ -104,6 +181,60 @@ mod test {
         );
     }
 
+    #[test]
+    fn first_compiler_error_from_log() {
+        let log = indoc::indoc! { r#"
+            *** run cargo build --tests
+               Compiling foo v0.0.0 (/tmp/foo)
+            error[E0277]: the trait bound `S: Default` is not satisfied
+              --> src/lib.rs:12:1
+               |
+            12 | Default::default()
+               | ^^^^^^^^^^^^^^^^^^ the trait `Default` is not implemented for `S`
+
+            For more information about this error, try `rustc --explain E0277`.
+            error: could not compile `foo` (lib test) due to 1 previous error
+
+            *** result: Failure
+            "# };
+        assert_eq!(
+            first_compiler_error(log).unwrap(),
+            indoc::indoc! { r#"
+                error[E0277]: the trait bound `S: Default` is not satisfied
+                  --> src/lib.rs:12:1
+                   |
+                12 | Default::default()
+                   | ^^^^^^^^^^^^^^^^^^ the trait `Default` is not implemented for `S`"# }
+        );
+        assert_eq!(first_compiler_error("*** result: Success\n"), None);
+    }
+
+    #[test]
+    fn echo_complete_lines_with_prefix() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let log_dir = Utf8Path::from_path(tmp.path()).unwrap();
+        let mut log_file = LogFile::create_in(log_dir, "baseline").unwrap();
+        log_file.message("not echoed");
+        assert_eq!(log_file.take_echo_lines(false).unwrap(), "");
+        log_file.echo_with_prefix("[abc]".to_owned());
+        let mut file = log_file.open_append().unwrap();
+        file.write_all(b"Compiling foo\nRunning").unwrap();
+        assert_eq!(
+            log_file.take_echo_lines(false).unwrap(),
+            "[abc] Compiling foo\n"
+        );
+        file.write_all(b" tests\ntest result").unwrap();
+        assert_eq!(
+            log_file.take_echo_lines(false).unwrap(),
+            "[abc] Running tests\n"
+        );
+        assert_eq!(
+            log_file.take_echo_lines(true).unwrap(),
+            "[abc] test result\n"
+        );
+        assert_eq!(log_file.take_echo_lines(true).unwrap(), "");
+    }
+
     #[test]
     fn last_line_of_file() {
         let mut tempfile = tempfile::NamedTempFile::new().unwrap();
@@ -5,15 +5,44 @@
 //! In particular, when the tree is copied we have to fix up relative paths, so
 //! that they still work from the new location of the scratch directory.
 
+use std::collections::HashMap;
 use std::fs;
 
 use anyhow::Context;
 use camino::Utf8Path;
+use serde::Deserialize;
 use tracing::debug;
 
 use crate::path::ascent;
 use crate::Result;
 
+/// Settings from one `[profile.NAME]` table of a manifest that affect which code is
+/// compiled.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    /// The profile that this one takes unset settings from.
+    pub inherits: Option<String>,
+    /// Whether `cfg(debug_assertions)` is set.
+    pub debug_assertions: Option<bool>,
+}
+
+/// Read the `[profile]` tables from the manifest at the root of a tree, by profile name.
+pub fn read_profiles(root: &Utf8Path) -> Result<HashMap<String, Profile>> {
+    let manifest_path = root.join("Cargo.toml");
+    let toml_str = fs::read_to_string(&manifest_path).context("read manifest")?;
+    parse_profiles(&toml_str).with_context(|| format!("read profiles from {manifest_path}"))
+}
+
+fn parse_profiles(manifest_toml: &str) -> Result<HashMap<String, Profile>> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        #[serde(default)]
+        profile: HashMap<String, Profile>,
+    }
+    Ok(toml::from_str::<Manifest>(manifest_toml)?.profile)
+}
+
 /// Rewrite the scratch copy of a manifest to have absolute paths.
 ///
 /// `manifest_source_dir` is the directory originally containing the manifest, from
@@ -328,4 +357,28 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn profiles_are_read_from_manifest() {
+        let profiles = super::parse_profiles(indoc! { r#"
+            [package]
+            name = "foo"
+
+            [profile.release]
+            debug-assertions = true
+            lto = true
+
+            [profile.mutants]
+            inherits = "test"
+        "# })
+        .unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles["release"].debug_assertions, Some(true));
+        assert_eq!(profiles["release"].inherits, None);
+        assert_eq!(profiles["mutants"].inherits.as_deref(), Some("test"));
+        assert_eq!(profiles["mutants"].debug_assertions, None);
+        assert!(super::parse_profiles("[package]\nname = \"foo\"\n")
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -2,12 +2,16 @@
 
 //! Mutations to source files, and inference of interesting mutations to apply.
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fs;
 use std::sync::Arc;
 
 use anyhow::Context;
 use anyhow::Result;
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
+use itertools::Itertools;
 use serde::ser::{SerializeStruct, Serializer};
 use serde::Serialize;
 use similar::TextDiff;
@@ -15,16 +19,116 @@ use similar::TextDiff;
 use crate::build_dir::BuildDir;
 use crate::source::Package;
 use crate::source::SourceFile;
-use crate::textedit::{replace_region, Span};
+use crate::textedit::{extract_region, replace_region, Span};
 
 /// A comment marker inserted next to changes, so they can be easily found.
 const MUTATION_MARKER_COMMENT: &str = "/* ~ changed by cargo-mutants ~ */";
 
 /// Various broad categories of mutants.
-#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+///
+/// On the command line and in the config file, genres are named in kebab case,
+/// like `fn-value`.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Serialize, ValueEnum)]
 pub enum Genre {
     /// Replace the body of a function with a fixed value.
     FnValue,
+    /// Replace a literal default value passed to a method like `unwrap_or`.
+    DefaultValue,
+    /// Swap saturating and wrapping arithmetic methods, like `saturating_add` and `wrapping_add`.
+    OverflowMethod,
+    /// Replace an `if`/`else` expression with the value of one of its branches, or
+    /// the condition of an `if cond { return x; }` guard with `true` or `false`.
+    IfBranch,
+    /// Replace a plain string literal in `format!` or `write!` with `"xyzzy"`.
+    FormatString,
+    /// Replace `cond.then_some(x)` or `cond.then(|| x)` with `Some(x)` or `None`.
+    BoolThen,
+    /// Change literal values in `lazy_static!` and `Lazy` static initializers.
+    LazyStatic,
+    /// Replace a final `Ok(x)` in a function returning `Result` with an error, or a
+    /// final `Err(e)` or early `return Err(e)` with `Ok(Default::default())`.
+    ResultFlip,
+    /// Delete an explicit field from a struct expression ending in
+    /// `..Default::default()`, so that it takes the default value.
+    StructUpdate,
+    /// Change the literal value of an associated constant like `const MAX: usize = 100;`.
+    AssocConst,
+    /// Delete one statement from the body of a function returning `()`.
+    StatementDelete,
+    /// Swap the literal or unit patterns of two adjacent `match` arms, keeping their bodies,
+    /// or replace the body of a wildcard `_` arm with `unreachable!()`.
+    MatchArmSwap,
+    /// Make a `while let` loop stop before running its body, or after running it once.
+    WhileLetBreak,
+    /// Replace the body of a predicate closure passed to `filter`, `take_while`,
+    /// `skip_while`, or `find` with `true` or `false`.
+    PredicateClosure,
+    /// Change the collection in `collect::<Vec<_>>()` to `HashSet`, or the reverse.
+    CollectTarget,
+    /// Replace `result.ok()` with `result.err()` or `None`, and `result.err()` with
+    /// `result.ok()` or `None`.
+    ResultToOption,
+    /// Delete a statement calling `mem::swap`, `mem::replace`, or `mem::take`, or
+    /// replace such a call by a value that leaves its destination unchanged.
+    MemCall,
+    /// Delete a statement that adds to a collection with `push`, `insert`, `extend`, or
+    /// `append`.
+    CollectionPush,
+    /// Change the literal passed to a `Duration` constructor like `Duration::from_secs(5)`
+    /// to zero, or to twice its value.
+    DurationLiteral,
+    /// Swap a short-circuiting `&&` or `||` with the non-short-circuiting `&` or `|`, or
+    /// the reverse, between booleans.
+    BinaryOperator,
+    /// Replace the value of one explicit field in a struct literal returned by a function
+    /// with `Default::default()`.
+    StructFieldDefault,
+}
+
+impl Genre {
+    /// All the genres of mutant that can be generated.
+    pub fn all() -> impl Iterator<Item = Genre> {
+        Genre::value_variants().iter().copied()
+    }
+
+    /// True if mutants of this genre are generated unless the user opts out.
+    ///
+    /// Other genres are only generated when requested with `--genre`.
+    pub fn enabled_by_default(&self) -> bool {
+        !matches!(
+            self,
+            Genre::FormatString
+                | Genre::BoolThen
+                | Genre::LazyStatic
+                | Genre::StatementDelete
+                | Genre::MatchArmSwap
+                | Genre::CollectTarget
+                | Genre::ResultToOption
+                | Genre::MemCall
+                | Genre::CollectionPush
+                | Genre::DurationLiteral
+                | Genre::BinaryOperator
+                | Genre::StructFieldDefault
+        )
+    }
+
+    /// The name of the genre as used on the command line and in config, like `fn-value`.
+    pub fn cli_name(&self) -> String {
+        self.possible_value().get_name().to_owned()
+    }
+
+    /// A one-line description of the genre, taken from its doc comment.
+    pub fn description(&self) -> String {
+        self.possible_value()
+            .get_help()
+            .map(|help| help.to_string())
+            .unwrap_or_default()
+    }
+
+    fn possible_value(&self) -> PossibleValue {
+        self.to_possible_value()
+            .expect("genres are never skipped as values")
+    }
 }
 
 /// A mutation applied to source code.
@@ -39,6 +143,9 @@ pub struct Mutant {
     /// The return type of the function, as a fragment of Rust syntax.
     pub return_type: Arc<String>,
 
+    /// The span of the whole function containing the mutation, including its signature.
+    pub function_span: Span,
+
     /// The mutated textual region.
     pub span: Span,
 
@@ -47,19 +154,43 @@ pub struct Mutant {
 
     /// What general category of mutant this is.
     pub genre: Genre,
+
+    /// For a [Genre::MatchArmSwap] mutant that swaps the patterns of two arms, rather
+    /// than one that makes a wildcard arm unreachable, the two patterns in their
+    /// original order, each on one line.
+    pub swapped_patterns: Option<(String, String)>,
+
+    /// An identifier for this mutant that stays the same across runs, and when
+    /// unrelated code is changed, assigned by [assign_ids].
+    pub id: String,
 }
 
 impl Mutant {
     /// Return text of the whole file with the mutation applied.
     pub fn mutated_code(&self) -> String {
+        let replacement = match self.genre {
+            Genre::FnValue => format!("{{\n{} {}\n}}", self.replacement, MUTATION_MARKER_COMMENT),
+            _ => format!("{} {}", self.replacement, MUTATION_MARKER_COMMENT),
+        };
         replace_region(
             &self.source_file.code,
             &self.span.start,
             &self.span.end,
-            &format!("{{\n{} {}\n}}\n", self.replacement, MUTATION_MARKER_COMMENT),
+            &replacement,
         )
     }
 
+    /// Return the original text of the region replaced by this mutation.
+    pub fn original_text(&self) -> String {
+        extract_region(&self.source_file.code, &self.span.start, &self.span.end)
+    }
+
+    /// Return the original text of the replaced region on a single line, for use in
+    /// descriptions: line breaks and the indentation around them become a single space.
+    pub fn original_text_one_line(&self) -> String {
+        self.original_text().lines().map(str::trim).join(" ")
+    }
+
     /// Return the original code for the entire file affected by this mutation.
     pub fn original_code(&self) -> &str {
         &self.source_file.code
@@ -80,18 +211,86 @@ impl Mutant {
 
     /// Describe the mutant briefly, not including the location.
     ///
-    /// The result is like `replace factorial -> u32 with Default::default()`.
+    /// The result is like `replace factorial -> u32 with Default::default()`,
+    /// or for mutants within a function body, like `replace 0 with 1 in factorial`.
     pub fn describe_change(&self) -> String {
-        format!(
-            "replace {name}{space}{type} with {replacement}",
-            name = self.function_name(),
-            space = if self.return_type.is_empty() {
-                ""
-            } else {
-                " "
-            },
-            type = self.return_type(),
-            replacement = self.replacement
+        if let Some((a, b)) = &self.swapped_patterns {
+            return format!(
+                "swap match arm patterns {a} and {b} in {name}",
+                name = self.function_name(),
+            );
+        }
+        match self.genre {
+            Genre::FnValue => format!(
+                "replace {name}{space}{type} with {replacement}",
+                name = self.function_name(),
+                space = if self.return_type.is_empty() {
+                    ""
+                } else {
+                    " "
+                },
+                type = self.return_type(),
+                replacement = self.replacement
+            ),
+            Genre::StructUpdate => format!(
+                "delete field {field} in {name}",
+                field = self.original_text_one_line().trim_end_matches(','),
+                name = self.function_name(),
+            ),
+            Genre::StatementDelete | Genre::MemCall | Genre::CollectionPush
+                if self.replacement.is_empty() =>
+            {
+                format!(
+                    "delete {statement} in {name}",
+                    statement = self.original_text_one_line(),
+                    name = self.function_name(),
+                )
+            }
+            Genre::WhileLetBreak => format!(
+                "{change} in {name}",
+                change = self.describe_while_let_break(),
+                name = self.function_name(),
+            ),
+            _ => format!(
+                "replace {original} with {replacement} in {name}",
+                original = self.original_text_one_line(),
+                replacement = self.replacement,
+                name = self.function_name(),
+            ),
+        }
+    }
+
+    /// For a [Genre::WhileLetBreak] mutant, describe how the loop is changed.
+    pub fn describe_while_let_break(&self) -> &'static str {
+        // The whole body is replaced to stop before running it; only the closing brace
+        // is replaced to stop after running it once.
+        if self.replacement.starts_with('{') {
+            "replace while let loop body with break"
+        } else {
+            "break after the first iteration of while let loop"
+        }
+    }
+
+    /// For a [Genre::FnValue] mutant, return the final expression of the original body
+    /// that gives the return value, on a single line.
+    ///
+    /// Returns None for other genres, or if the body doesn't end with an expression.
+    pub fn original_tail_expression(&self) -> Option<String> {
+        if self.genre != Genre::FnValue {
+            return None;
+        }
+        // Positions in the parsed body are relative to the start of its text.
+        let body = self.original_text();
+        let block: syn::Block = syn::parse_str(&body).ok()?;
+        let Some(syn::Stmt::Expr(tail, None)) = block.stmts.last() else {
+            return None;
+        };
+        let span = Span::from(syn::spanned::Spanned::span(tail));
+        Some(
+            extract_region(&body, &span.start, &span.end)
+                .lines()
+                .map(str::trim)
+                .join(" "),
         )
     }
 
@@ -122,10 +321,24 @@ impl Mutant {
         let old_label = self.source_file.tree_relative_slashes();
         // There shouldn't be any newlines, but just in case...
         let new_label = self.describe_change().replace('\n', " ");
+        self.unified_diff(&old_label, &new_label)
+    }
+
+    /// Return a patch for the mutant that can be applied to the source tree with
+    /// `git apply` or `patch -p1`.
+    pub fn patch(&self) -> String {
+        let path = self.source_file.tree_relative_slashes();
+        format!(
+            "{self}\n\n{diff}",
+            diff = self.unified_diff(&format!("a/{path}"), &format!("b/{path}"))
+        )
+    }
+
+    fn unified_diff(&self, old_label: &str, new_label: &str) -> String {
         TextDiff::from_lines(self.original_code(), &self.mutated_code())
             .unified_diff()
             .context_radius(8)
-            .header(&old_label, &new_label)
+            .header(old_label, new_label)
             .to_string()
     }
 
@@ -195,7 +408,8 @@ impl Serialize for Mutant {
         S: Serializer,
     {
         // custom serialize to omit inessential info
-        let mut ss = serializer.serialize_struct("Mutant", 7)?;
+        let mut ss = serializer.serialize_struct("Mutant", 8)?;
+        ss.serialize_field("id", &self.id)?;
         ss.serialize_field("package", &self.package_name())?;
         ss.serialize_field("file", &self.source_file.tree_relative_slashes())?;
         ss.serialize_field("line", &self.span.start.line)?;
@@ -207,6 +421,50 @@ impl Serialize for Mutant {
     }
 }
 
+/// Assign [Mutant::id] to all the mutants from one source file.
+///
+/// The id is a hash of the package, file, genre, and description of the change,
+/// so it doesn't depend on line numbers or on which other mutants were generated.
+/// If several mutants in the file have the same description, such as two identical
+/// expressions in one function, the later ones get a suffix counting from `-2`.
+pub fn assign_ids(mutants: &mut [Mutant]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for mutant in mutants {
+        let hash = stable_hash(
+            [
+                mutant.package_name(),
+                &mutant.source_file.tree_relative_slashes(),
+                &mutant.genre.cli_name(),
+                &mutant.describe_change(),
+            ]
+            .join("\0")
+            .as_bytes(),
+        );
+        // 48 bits is enough to make collisions unlikely while keeping ids short.
+        let base = format!("{:012x}", hash >> 16);
+        let count = seen.entry(base.clone()).or_default();
+        *count += 1;
+        mutant.id = if *count == 1 {
+            base
+        } else {
+            format!("{base}-{count}")
+        };
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hasher is stable across
+/// releases, followed by the MurmurHash3 finalizer so that all the bits are well mixed.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes.iter().fold(0xcbf29ce484222325, |hash: u64, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
 #[cfg(test)]
 mod test {
     use camino::Utf8Path;
@@ -5,16 +5,31 @@
 //! The [Options] structure is built from command-line options and then widely passed around.
 //! Options are also merged from the [config] after reading the command line arguments.
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::read_to_string;
 use std::time::Duration;
 
-use anyhow::Context;
-use camino::Utf8PathBuf;
+use anyhow::{anyhow, Context};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use itertools::Itertools;
 use regex::RegexSet;
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
 use tracing::warn;
 
+use crate::path::Utf8PathSlashes;
+use crate::report_template::ReportTemplate;
 use crate::{config::Config, *};
 
+/// Traits whose impls are skipped unless the user chooses a different set: they're
+/// usually just formatting, and their mutants are rarely interesting; or they're
+/// operators returning references, whose mutants are almost always unviable.
+pub const DEFAULT_SKIP_TRAITS: &[&str] = &[
+    "Debug", "Display", "Error", "Index", "IndexMut", "Deref", "DerefMut",
+];
+
 /// Options for mutation testing, based on both command-line arguments and the
 /// config file.
 #[derive(Default, Debug, Clone)]
@@ -22,44 +37,143 @@ pub struct Options {
     /// Don't run the tests, just see if each mutant builds.
     pub check_only: bool,
 
+    /// Show each mutant before testing it, and ask whether to test it.
+    ///
+    /// This is only set when running interactively in a terminal.
+    pub confirm: bool,
+
     /// Don't delete scratch directories.
     pub leak_dirs: bool,
 
-    /// The time limit for test tasks, if set.
+    /// Keep build directories in `mutants.build` between runs.
+    pub reuse_build_dir: bool,
+
+    /// Before testing, delete old output and leaked scratch directories that were last
+    /// modified longer ago than this.
+    pub max_log_age: Option<Duration>,
+
+    /// The time limit for testing each mutant, if set.
     ///
-    /// If this is not set by the user it's None, in which case there is no time limit
-    /// on the baseline test, and then the mutated tests get a multiple of the time
-    /// taken by the baseline test.
+    /// If this is not set by the user it's None, in which case the mutated tests get
+    /// a multiple of the time taken by the baseline test.
     pub test_timeout: Option<Duration>,
 
+    /// The time limit for the baseline test, if set; by default there's no limit.
+    pub baseline_timeout: Option<Duration>,
+
+    /// A shell command run in the build directory before the baseline, which must succeed.
+    pub baseline_command: Option<String>,
+
     /// The minimum test timeout, as a floor on the autoset value.
     pub minimum_test_timeout: Duration,
 
+    /// Test timeouts for mutants of particular genres, overriding the general
+    /// mutant test timeout.
+    pub genre_test_timeouts: HashMap<Genre, Duration>,
+
+    /// Limits on memory and CPU time for each process testing a mutant.
+    pub resource_limits: ResourceLimits,
+
+    /// Genres requested with `--genre` or in the config, in addition to those enabled by
+    /// default unless `no_default_genres` is set.
+    pub opt_in_genres: Vec<Genre>,
+
+    /// Generate only the genres in `opt_in_genres`, not those enabled by default.
+    pub no_default_genres: bool,
+
+    /// How to count mutants whose tests time out.
+    pub timeout_policy: TimeoutPolicy,
+
+    /// Fail if the mutation score, as a percentage, is below this.
+    pub fail_under: Option<f64>,
+
+    /// Stop testing mutants in a function after this many in a row were caught, and
+    /// assume the rest are caught too.
+    pub stop_function_after_caught: Option<u64>,
+
+    /// Print annotations for missed mutants in this format.
+    pub annotate: AnnotationFormat,
+
+    /// The format of output while testing.
+    pub output_format: OutputFormat,
+
+    /// After testing, list the missed mutants grouped in this way.
+    pub group_output_by: GroupOutputBy,
+
     pub print_caught: bool,
     pub print_unviable: bool,
 
+    /// After testing, list the unviable mutants with their compiler errors.
+    pub print_unviable_errors: bool,
+
+    /// After testing, print a line for each mutant from this template.
+    pub report_template: Option<ReportTemplate>,
+
     pub show_times: bool,
 
     /// Show logs even from mutants that were caught, or source/unmutated builds.
     pub show_all_logs: bool,
 
+    /// Echo the output of cargo to the terminal while it runs, prefixed by the mutant id.
+    pub verbose: bool,
+
     /// Test mutants in random order.
     ///
     /// This is now the default, so that repeated partial runs are more likely to find
     /// interesting results.
     pub shuffle: bool,
 
+    /// Test mutants in functions that look untested first.
+    pub prioritize_untested: bool,
+
     /// Additional arguments for every cargo invocation.
     pub additional_cargo_args: Vec<String>,
 
     /// Additional arguments to `cargo test`.
     pub additional_cargo_test_args: Vec<String>,
 
+    /// Build and test with this cargo profile, rather than the default `dev` profile.
+    pub profile: Option<String>,
+
+    /// The `[profile]` settings in the manifest at the root of the tree, by name.
+    pub manifest_profiles: HashMap<String, manifest::Profile>,
+
     /// Files to examine.
-    pub examine_globset: Option<GlobSet>,
+    pub examine_globset: Option<SourceGlobs>,
 
     /// Files to exclude.
-    pub exclude_globset: Option<GlobSet>,
+    pub exclude_globset: Option<SourceGlobs>,
+
+    /// If set, examine only files with these tree-relative paths.
+    pub examine_file_list: Option<HashSet<String>>,
+
+    /// Exclude files with these tree-relative paths.
+    pub exclude_file_list: Option<HashSet<String>>,
+
+    /// Only mutate functions changed in this many of the most recent git commits.
+    pub since_commits: Option<u64>,
+
+    /// Only mutate functions changed since this git tag, or since the most recent tag
+    /// if it's `latest`.
+    pub since_tag: Option<String>,
+
+    /// With `since_commits` or `since_tag`, select whole packages containing changed files, rather
+    /// than only changed functions.
+    pub only_package_with_changes: bool,
+
+    /// With `only_package_with_changes`, also select packages that depend on the
+    /// changed packages.
+    pub include_dependents: bool,
+
+    /// With `since_commits` or `since_tag`, select only files that were added, or that
+    /// are untracked, rather than changed functions.
+    pub only_new_files: bool,
+
+    /// After testing, compare the outcomes with this `outcomes.json` from an earlier run.
+    pub compare: Option<Utf8PathBuf>,
+
+    /// Only test mutants whose ids are not in this earlier report, such as `mutants.json`.
+    pub only_missing_from: Option<Utf8PathBuf>,
 
     /// Mutants to examine, as a regexp matched against the full name.
     pub examine_names: Option<RegexSet>,
@@ -67,14 +181,36 @@ pub struct Options {
     /// Mutants to skip, as a regexp matched against the full name.
     pub exclude_names: Option<RegexSet>,
 
+    /// Don't mutate functions, impls, or modules marked `#[doc(hidden)]`.
+    pub exclude_doc_hidden: bool,
+
+    /// Replace the bodies of functions that always diverge, like those ending in `panic!()`.
+    pub mutate_diverging_fns: bool,
+
+    /// Don't mutate functions with type parameters.
+    pub exclude_generics: bool,
+
     /// Create `mutants.out` within this directory (by default, the source directory).
     pub output_in_dir: Option<Utf8PathBuf>,
 
     /// Run this many `cargo build` or `cargo test` tasks in parallel.
     pub jobs: Option<usize>,
 
+    /// Run the tests in each test binary with this many threads, for both the
+    /// baseline and mutants.
+    pub test_threads: Option<u64>,
+
     /// Insert these values as errors from functions returning `Result`.
     pub error_values: Vec<String>,
+
+    /// Skip impls of traits with these names, or if None, those in [DEFAULT_SKIP_TRAITS].
+    pub skip_traits: Option<Vec<String>>,
+
+    /// Keep only the first this many mutants from each function.
+    pub max_mutants_per_function: Option<usize>,
+
+    /// Don't generate `Default::default()` for types whose name ends with one of these.
+    pub no_default_types: Vec<String>,
 }
 
 fn join_slices(a: &[String], b: &[String]) -> Vec<String> {
@@ -97,19 +233,57 @@ impl Options {
                 .unwrap_or(20f64),
         );
 
+        let confirm = args.confirm && ::console::user_attended();
+        if args.confirm && !confirm {
+            warn!("--confirm needs an interactive terminal; testing all mutants without asking");
+        }
+
+        let mut opt_in_genres = args.genre.clone();
+        for genre_name in &config.genres {
+            opt_in_genres.push(parse_genre(genre_name)?);
+        }
+
+        let mut genre_test_timeouts = HashMap::new();
+        for (genre_name, secs) in config.timeout_per_genre.iter() {
+            let timeout = timeout_secs(*secs)
+                .with_context(|| format!("timeout_per_genre for {genre_name:?} in config"))?;
+            genre_test_timeouts.insert(parse_genre(genre_name)?, timeout);
+        }
+        // Command line options are inserted later so that they take precedence.
+        for arg in &args.timeout_per_genre {
+            let (genre, timeout) = parse_genre_timeout(arg)?;
+            genre_test_timeouts.insert(genre, timeout);
+        }
+
         let options = Options {
             additional_cargo_args: join_slices(&args.cargo_arg, &config.additional_cargo_args),
             additional_cargo_test_args: join_slices(
                 &args.cargo_test_args,
                 &config.additional_cargo_test_args,
             ),
+            profile: args.profile.clone().or_else(|| config.profile.clone()),
+            manifest_profiles: HashMap::new(),
             check_only: args.check,
+            skip_traits: if args.skip_trait.is_empty() {
+                config.skip_traits.clone()
+            } else {
+                Some(args.skip_trait.clone())
+            },
+            max_mutants_per_function: args
+                .max_mutants_per_function
+                .map(|n| n as usize)
+                .or(config.max_mutants_per_function),
+            no_default_types: config.no_default_types.clone(),
+            confirm,
             error_values: join_slices(&args.error, &config.error_values),
             examine_names: Some(
                 RegexSet::new(args.examine_re.iter().chain(config.examine_re.iter()))
                     .context("Compiling examine_re regex")?,
             ),
             examine_globset: build_glob_set(args.file.iter().chain(config.examine_globs.iter()))?,
+            exclude_doc_hidden: args.exclude_doc_hidden || config.exclude_doc_hidden,
+            mutate_diverging_fns: args.mutate_diverging_fns || config.mutate_diverging_fns,
+            exclude_generics: args.exclude_generics || config.exclude_generics,
             exclude_names: Some(
                 RegexSet::new(args.exclude_re.iter().chain(config.exclude_re.iter()))
                     .context("Compiling exclude_re regex")?,
@@ -117,17 +291,75 @@ impl Options {
             exclude_globset: build_glob_set(
                 args.exclude.iter().chain(config.exclude_globs.iter()),
             )?,
+            examine_file_list: args
+                .examine_file_list
+                .as_deref()
+                .map(read_file_list)
+                .transpose()?,
+            report_template: args
+                .report_template
+                .as_deref()
+                .map(ReportTemplate::read_file)
+                .transpose()?,
+            exclude_file_list: args
+                .exclude_file_list
+                .as_deref()
+                .map(read_file_list)
+                .transpose()?,
             jobs: args.jobs,
+            test_threads: args.test_threads,
             leak_dirs: args.leak_dirs,
+            reuse_build_dir: args.reuse_build_dir,
+            max_log_age: args
+                .max_log_age
+                .map(|days| Duration::from_secs_f64(days * 24.0 * 3600.0)),
             output_in_dir: args.output.clone(),
             print_caught: args.caught,
             print_unviable: args.unviable,
+            print_unviable_errors: args.unviable_errors,
             shuffle: !args.no_shuffle,
+            prioritize_untested: args.prioritize_untested,
+            since_commits: args.since_commits,
+            since_tag: args.since_tag.clone(),
+            only_package_with_changes: args.only_package_with_changes,
+            include_dependents: args.include_dependents,
+            only_new_files: args.only_new_files,
+            only_missing_from: args.only_missing_from.clone(),
+            compare: args.compare.clone(),
             show_times: !args.no_times,
             show_all_logs: args.all_logs,
+            verbose: args.verbose,
             test_timeout: args.timeout.map(Duration::from_secs_f64),
+            baseline_timeout: args.baseline_timeout.map(Duration::from_secs_f64),
+            baseline_command: args
+                .baseline_command
+                .clone()
+                .or_else(|| config.baseline_command.clone()),
             minimum_test_timeout,
+            genre_test_timeouts,
+            resource_limits: ResourceLimits {
+                memory_bytes: args.memory_limit,
+                cpu_time: args.cpu_limit.map(Duration::from_secs_f64),
+            },
+            opt_in_genres,
+            no_default_genres: args.no_default_genres,
+            timeout_policy: args.timeout_is,
+            fail_under: args.fail_under,
+            stop_function_after_caught: args.stop_function_after_caught,
+            annotate: AnnotationFormat::from_arg_or_env(args.annotate),
+            group_output_by: args.group_output_by,
+            output_format: args.output_format,
         };
+        if options.max_mutants_per_function == Some(0) {
+            return Err(anyhow!(
+                "max_mutants_per_function in config must be at least 1"
+            ));
+        }
+        if !options.resource_limits.is_unlimited() && !ResourceLimits::supported() {
+            return Err(anyhow!(
+                "--memory-limit and --cpu-limit are only supported on Unix"
+            ));
+        }
         options.error_values.iter().for_each(|e| {
             if e.starts_with("Err(") {
                 warn!(
@@ -138,24 +370,645 @@ impl Options {
         });
         Ok(options)
     }
+
+    /// Whether `cfg(debug_assertions)` is true when the mutants are built, if it's known.
+    ///
+    /// This is the `debug-assertions` setting of the profile in the manifest, or of the
+    /// profiles it inherits from, or else cargo's default: true in `dev` and `test`, and
+    /// false in `release` and `bench`. This doesn't notice profile settings in
+    /// `.cargo/config.toml` or the environment, or `-C debug-assertions` in `RUSTFLAGS`.
+    pub fn debug_assertions(&self) -> Option<bool> {
+        let release_arg = self
+            .additional_cargo_args
+            .iter()
+            .any(|arg| arg == "--release" || arg == "-r");
+        let mut name = match (self.profile.as_deref(), release_arg) {
+            (None, false) => "dev",
+            (None | Some("release"), true) => "release",
+            (Some(profile), false) => profile,
+            (Some(_), true) => return None,
+        };
+        // Profiles can only inherit from a few levels up, but stop if there's a cycle.
+        for _ in 0..8 {
+            let profile = self.manifest_profiles.get(name);
+            if let Some(debug_assertions) = profile.and_then(|p| p.debug_assertions) {
+                return Some(debug_assertions);
+            }
+            name = match name {
+                "dev" => return Some(true),
+                "release" => return Some(false),
+                "test" => "dev",
+                "bench" => "release",
+                _ => profile?.inherits.as_deref()?,
+            };
+        }
+        None
+    }
+
+    /// True if mutants of this genre should be generated.
+    pub fn genre_enabled(&self, genre: Genre) -> bool {
+        (genre.enabled_by_default() && !self.no_default_genres)
+            || self.opt_in_genres.contains(&genre)
+    }
+
+    /// True if impls of the trait with this name should not be mutated.
+    ///
+    /// Only the last component of the trait path is compared, so both `fmt::Display`
+    /// and `Display` match `Display`.
+    pub fn trait_skipped(&self, trait_name: &str) -> bool {
+        match &self.skip_traits {
+            Some(skip_traits) => skip_traits.iter().any(|name| name == trait_name),
+            None => DEFAULT_SKIP_TRAITS.contains(&trait_name),
+        }
+    }
+
+    /// Return the test timeout for a mutant of a given genre, or `default` if
+    /// no specific timeout is set for that genre.
+    pub fn genre_test_timeout(&self, genre: Genre, default: Duration) -> Duration {
+        self.genre_test_timeouts
+            .get(&genre)
+            .copied()
+            .unwrap_or(default)
+    }
+}
+
+fn parse_genre(name: &str) -> Result<Genre> {
+    Genre::from_str(name, true).map_err(|_| {
+        anyhow!(
+            "unknown genre {name:?}: expected one of {}",
+            Genre::all().map(|genre| genre.cli_name()).join(", ")
+        )
+    })
+}
+
+/// Parse a genre timeout like `fn-value=30`.
+fn parse_genre_timeout(s: &str) -> Result<(Genre, Duration)> {
+    let (genre_name, secs) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("genre timeout {s:?} should be like GENRE=SECS"))?;
+    let secs: f64 = secs
+        .parse()
+        .with_context(|| format!("parse timeout in {s:?}"))?;
+    let timeout = timeout_secs(secs).with_context(|| format!("genre timeout {s:?}"))?;
+    Ok((parse_genre(genre_name)?, timeout))
+}
+
+/// Convert a timeout in seconds to a duration, if it's a non-negative finite number that
+/// fits.
+fn timeout_secs(secs: f64) -> Result<Duration> {
+    if secs.is_finite() && secs >= 0.0 && secs < u64::MAX as f64 {
+        Ok(Duration::from_secs_f64(secs))
+    } else {
+        Err(anyhow!(
+            "timeout {secs} should be a non-negative number of seconds"
+        ))
+    }
+}
+
+/// A set of globs matching source files.
+///
+/// Globs are matched against the path of the file relative to the root of the
+/// source tree, with forward slashes as separators on every platform: for example
+/// `src/foo/bar.rs`. Globs that are absolute paths are instead matched against the
+/// absolute path of the file.
+#[derive(Debug, Clone)]
+pub struct SourceGlobs {
+    relative: GlobSet,
+    absolute: GlobSet,
+    /// The normalized globs, as given, for showing to the user.
+    patterns: Vec<String>,
+}
+
+impl SourceGlobs {
+    /// True if a source file matches any of the globs.
+    ///
+    /// `tree_relative_slashes` is the path relative to the tree root with forward slashes,
+    /// and `absolute` is the full path of the file.
+    pub fn is_match(&self, tree_relative_slashes: &str, absolute: &Utf8Path) -> bool {
+        self.relative.is_match(tree_relative_slashes)
+            || (!self.absolute.is_empty() && self.absolute.is_match(absolute.to_slash_path()))
+    }
+}
+
+/// Show the effective options, after merging the command line and config, for
+/// `--print-config`.
+///
+/// Where there's a matching key in the config file, the same name is used.
+impl Serialize for Options {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        fn secs(duration: &Duration) -> f64 {
+            duration.as_secs_f64()
+        }
+        fn value_name<T: ValueEnum>(value: &T) -> Option<String> {
+            value
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+        }
+        fn globs(globs: &Option<SourceGlobs>) -> &[String] {
+            globs
+                .as_ref()
+                .map_or(&[], |globs| globs.patterns.as_slice())
+        }
+        fn regexes(regexes: &Option<RegexSet>) -> &[String] {
+            regexes.as_ref().map_or(&[], |regexes| regexes.patterns())
+        }
+        fn sorted(paths: &Option<HashSet<String>>) -> Option<Vec<&String>> {
+            paths.as_ref().map(|paths| paths.iter().sorted().collect())
+        }
+        let mut ss = serializer.serialize_struct("Options", 48)?;
+        ss.serialize_field(
+            "genres",
+            &Genre::all()
+                .filter(|genre| self.genre_enabled(*genre))
+                .map(|genre| genre.cli_name())
+                .collect_vec(),
+        )?;
+        ss.serialize_field("error_values", &self.error_values)?;
+        ss.serialize_field(
+            "skip_traits",
+            &self.skip_traits.clone().unwrap_or_else(|| {
+                DEFAULT_SKIP_TRAITS
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect()
+            }),
+        )?;
+        ss.serialize_field("max_mutants_per_function", &self.max_mutants_per_function)?;
+        ss.serialize_field("no_default_types", &self.no_default_types)?;
+        ss.serialize_field("examine_globs", globs(&self.examine_globset))?;
+        ss.serialize_field("exclude_globs", globs(&self.exclude_globset))?;
+        ss.serialize_field("examine_re", regexes(&self.examine_names))?;
+        ss.serialize_field("exclude_re", regexes(&self.exclude_names))?;
+        ss.serialize_field("exclude_doc_hidden", &self.exclude_doc_hidden)?;
+        ss.serialize_field("mutate_diverging_fns", &self.mutate_diverging_fns)?;
+        ss.serialize_field("exclude_generics", &self.exclude_generics)?;
+        ss.serialize_field("examine_file_list", &sorted(&self.examine_file_list))?;
+        ss.serialize_field("exclude_file_list", &sorted(&self.exclude_file_list))?;
+        ss.serialize_field("since_commits", &self.since_commits)?;
+        ss.serialize_field("since_tag", &self.since_tag)?;
+        ss.serialize_field("only_package_with_changes", &self.only_package_with_changes)?;
+        ss.serialize_field("include_dependents", &self.include_dependents)?;
+        ss.serialize_field("only_new_files", &self.only_new_files)?;
+        ss.serialize_field("only_missing_from", &self.only_missing_from)?;
+        ss.serialize_field("additional_cargo_args", &self.additional_cargo_args)?;
+        ss.serialize_field(
+            "additional_cargo_test_args",
+            &self.additional_cargo_test_args,
+        )?;
+        ss.serialize_field("profile", &self.profile)?;
+        ss.serialize_field("check_only", &self.check_only)?;
+        ss.serialize_field("jobs", &self.jobs)?;
+        ss.serialize_field("test_threads", &self.test_threads)?;
+        ss.serialize_field("shuffle", &self.shuffle)?;
+        ss.serialize_field("prioritize_untested", &self.prioritize_untested)?;
+        ss.serialize_field("timeout", &self.test_timeout.as_ref().map(secs))?;
+        ss.serialize_field(
+            "baseline_timeout",
+            &self.baseline_timeout.as_ref().map(secs),
+        )?;
+        ss.serialize_field("baseline_command", &self.baseline_command)?;
+        ss.serialize_field("minimum_test_timeout", &secs(&self.minimum_test_timeout))?;
+        ss.serialize_field(
+            "timeout_per_genre",
+            &self
+                .genre_test_timeouts
+                .iter()
+                .map(|(genre, timeout)| (genre.cli_name(), secs(timeout)))
+                .collect::<BTreeMap<_, _>>(),
+        )?;
+        ss.serialize_field("timeout_is", &value_name(&self.timeout_policy))?;
+        ss.serialize_field("memory_limit", &self.resource_limits.memory_bytes)?;
+        ss.serialize_field(
+            "cpu_limit",
+            &self.resource_limits.cpu_time.as_ref().map(secs),
+        )?;
+        ss.serialize_field("fail_under", &self.fail_under)?;
+        ss.serialize_field(
+            "stop_function_after_caught",
+            &self.stop_function_after_caught,
+        )?;
+        ss.serialize_field(
+            "report_template",
+            &self.report_template.as_ref().map(ToString::to_string),
+        )?;
+        ss.serialize_field("annotate", &value_name(&self.annotate))?;
+        ss.serialize_field("output_format", &value_name(&self.output_format))?;
+        ss.serialize_field("group_output_by", &value_name(&self.group_output_by))?;
+        ss.serialize_field("output", &self.output_in_dir)?;
+        ss.serialize_field("compare", &self.compare)?;
+        ss.serialize_field("confirm", &self.confirm)?;
+        ss.serialize_field("leak_dirs", &self.leak_dirs)?;
+        ss.serialize_field("reuse_build_dir", &self.reuse_build_dir)?;
+        ss.serialize_field("max_log_age", &self.max_log_age.as_ref().map(secs))?;
+        ss.end()
+    }
+}
+
+/// Read a list of tree-relative file paths, one per line.
+///
+/// Blank lines and lines starting with `#` are ignored. Paths are normalized in the
+/// same way as globs, so a leading `./` is ignored.
+fn read_file_list(path: &Utf8Path) -> Result<HashSet<String>> {
+    let content = read_to_string(path).with_context(|| format!("read file list {path:?}"))?;
+    Ok(parse_file_list(&content))
+}
+
+fn parse_file_list(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(normalize_glob)
+        .collect()
 }
 
 fn build_glob_set<S: AsRef<str>, I: IntoIterator<Item = S>>(
     glob_set: I,
-) -> Result<Option<GlobSet>> {
+) -> Result<Option<SourceGlobs>> {
     let mut glob_set = glob_set.into_iter().peekable();
     if glob_set.peek().is_none() {
         return Ok(None);
     }
 
-    let mut builder = GlobSetBuilder::new();
+    let mut relative = GlobSetBuilder::new();
+    let mut absolute = GlobSetBuilder::new();
+    let mut patterns = Vec::new();
     for glob_str in glob_set {
-        let glob_str = glob_str.as_ref();
-        if glob_str.contains('/') || glob_str.contains(std::path::MAIN_SEPARATOR) {
-            builder.add(Glob::new(glob_str)?);
+        let glob_str = normalize_glob(glob_str.as_ref());
+        patterns.push(glob_str.clone());
+        if Utf8Path::new(&glob_str).is_absolute() {
+            absolute.add(Glob::new(&glob_str)?);
+        } else if glob_str.contains('/') {
+            relative.add(Glob::new(&glob_str)?);
         } else {
-            builder.add(Glob::new(&format!("**/{glob_str}"))?);
+            relative.add(Glob::new(&format!("**/{glob_str}"))?);
+        }
+    }
+    Ok(Some(SourceGlobs {
+        relative: relative.build()?,
+        absolute: absolute.build()?,
+        patterns,
+    }))
+}
+
+/// Normalize a glob so that it can be matched against slash-separated paths.
+///
+/// On Windows, backslashes are converted to forward slashes. (Elsewhere, backslash
+/// is the glob escape character.) Any leading `./` is removed, since paths are
+/// matched relative to the tree root.
+fn normalize_glob(glob_str: &str) -> String {
+    let mut glob_str = if cfg!(windows) {
+        glob_str.replace('\\', "/")
+    } else {
+        glob_str.to_owned()
+    };
+    while let Some(rest) = glob_str.strip_prefix("./") {
+        glob_str = rest.to_owned();
+    }
+    glob_str
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parse_file_list_skips_comments_and_blank_lines() {
+        let list = parse_file_list(indoc::indoc! { "
+            # Files changed in this review
+            src/lib.rs
+
+              ./src/cache/mod.rs  
+            # src/old.rs
+        " });
+        assert_eq!(
+            list,
+            HashSet::from(["src/lib.rs".to_owned(), "src/cache/mod.rs".to_owned()])
+        );
+    }
+
+    #[test]
+    fn genre_timeouts_from_command_line_override_config() {
+        let args = Args::parse_from(["mutants", "--timeout-per-genre", "default-value=2.5"]);
+        let config: Config = toml::de::from_str(
+            r#"
+            timeout_per_genre = { fn-value = 30, default-value = 10 }
+            "#,
+        )
+        .unwrap();
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(
+            options.genre_test_timeout(Genre::DefaultValue, Duration::MAX),
+            Duration::from_secs_f64(2.5)
+        );
+        assert_eq!(
+            options.genre_test_timeout(Genre::FnValue, Duration::MAX),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn baseline_command_from_command_line_overrides_config() {
+        let config: Config = toml::de::from_str(r#"baseline_command = "make gen""#).unwrap();
+        let args = Args::parse_from(["mutants"]);
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(options.baseline_command.as_deref(), Some("make gen"));
+
+        let args = Args::parse_from(["mutants", "--baseline-command", "./setup.sh"]);
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(options.baseline_command.as_deref(), Some("./setup.sh"));
+    }
+
+    #[test]
+    fn debug_assertions_depend_on_profile() {
+        let debug_assertions = |args: &[&str]| {
+            let args = Args::parse_from(["mutants"].iter().chain(args));
+            Options::new(&args, &Config::default())
+                .unwrap()
+                .debug_assertions()
+        };
+        assert_eq!(debug_assertions(&[]), Some(true));
+        assert_eq!(debug_assertions(&["--profile", "test"]), Some(true));
+        assert_eq!(debug_assertions(&["--profile", "release"]), Some(false));
+        assert_eq!(debug_assertions(&["--cargo-arg", "--release"]), Some(false));
+        assert_eq!(debug_assertions(&["--profile", "mutants"]), None);
+    }
+
+    #[test]
+    fn debug_assertions_follow_manifest_profiles() {
+        let mut options = Options {
+            manifest_profiles: toml::from_str(indoc::indoc! { r#"
+                release = { debug-assertions = true }
+                mutants = { inherits = "bench" }
+                fast = { inherits = "dev", debug-assertions = false }
+                quick = { inherits = "fast" }
+                loop = { inherits = "loop" }
+            "# })
+            .unwrap(),
+            ..Default::default()
+        };
+        let mut debug_assertions = |profile: &str| {
+            options.profile = Some(profile.to_owned());
+            options.debug_assertions()
+        };
+        assert_eq!(debug_assertions("release"), Some(true));
+        assert_eq!(debug_assertions("bench"), Some(true));
+        assert_eq!(debug_assertions("mutants"), Some(true));
+        assert_eq!(debug_assertions("test"), Some(true));
+        assert_eq!(debug_assertions("fast"), Some(false));
+        assert_eq!(debug_assertions("quick"), Some(false));
+        assert_eq!(debug_assertions("loop"), None);
+        assert_eq!(debug_assertions("unknown"), None);
+    }
+
+    #[test]
+    fn profile_from_command_line_overrides_config() {
+        let config: Config = toml::de::from_str(r#"profile = "release""#).unwrap();
+        let args = Args::parse_from(["mutants"]);
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(options.profile.as_deref(), Some("release"));
+
+        let args = Args::parse_from(["mutants", "--profile", "mutants"]);
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(options.profile.as_deref(), Some("mutants"));
+
+        let options = Options::new(&args, &Config::default()).unwrap();
+        assert_eq!(options.profile.as_deref(), Some("mutants"));
+        assert_eq!(Options::default().profile, None);
+    }
+
+    #[test]
+    fn serialize_effective_options() {
+        let args = Args::parse_from([
+            "mutants",
+            "--timeout",
+            "20",
+            "--genre",
+            "format-string",
+            "--exclude",
+            "./src/main.rs",
+        ]);
+        let config: Config = toml::de::from_str(
+            r#"
+            exclude_globs = ["*.gen.rs"]
+            error_values = ["anyhow::anyhow!(\"mutated\")"]
+            timeout_per_genre = { fn-value = 30 }
+            "#,
+        )
+        .unwrap();
+        let options = Options::new(&args, &config).unwrap();
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["timeout"], 20.0);
+        assert_eq!(json["baseline_timeout"], serde_json::Value::Null);
+        assert_eq!(
+            json["timeout_per_genre"],
+            serde_json::json!({ "fn-value": 30.0 })
+        );
+        assert_eq!(
+            json["exclude_globs"],
+            serde_json::json!(["src/main.rs", "*.gen.rs"])
+        );
+        assert_eq!(
+            json["error_values"],
+            serde_json::json!(["anyhow::anyhow!(\"mutated\")"])
+        );
+        let genres = json["genres"].as_array().unwrap();
+        assert!(genres.contains(&"fn-value".into()));
+        assert!(genres.contains(&"format-string".into()));
+        assert!(!genres.contains(&"statement-delete".into()));
+        assert_eq!(json["timeout_is"], "separate");
+    }
+
+    #[test]
+    fn resource_limits_are_rejected_where_unsupported() {
+        let args = Args::parse_from(["mutants", "--memory-limit", "4G"]);
+        assert_eq!(
+            Options::new(&args, &Config::default()).is_ok(),
+            ResourceLimits::supported()
+        );
+    }
+
+    #[test]
+    fn unconfigured_genre_gets_default_timeout() {
+        let options = Options::default();
+        assert_eq!(
+            options.genre_test_timeout(Genre::FnValue, Duration::from_secs(7)),
+            Duration::from_secs(7)
+        );
+    }
+
+    #[test]
+    fn bad_genre_timeouts_are_errors() {
+        assert!(parse_genre_timeout("fn-value").is_err());
+        assert!(parse_genre_timeout("fn-value=soon").is_err());
+        let err = parse_genre_timeout("nonsense=3").unwrap_err().to_string();
+        assert!(err.contains("fn-value"), "{err}");
+        for bad in [
+            "fn-value=-1",
+            "fn-value=NaN",
+            "fn-value=inf",
+            "fn-value=1e300",
+        ] {
+            assert!(
+                parse_genre_timeout(bad).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_genre_timeouts_in_config_are_errors() {
+        let args = Args::parse_from(["mutants"]);
+        let config: Config = toml::de::from_str("timeout_per_genre = { fn-value = -5 }").unwrap();
+        let err = Options::new(&args, &config).unwrap_err();
+        assert!(format!("{err:#}").contains("fn-value"), "{err:#}");
+    }
+
+    #[test]
+    fn max_mutants_per_function_must_be_positive() {
+        assert!(Args::try_parse_from(["mutants", "--max-mutants-per-function", "0"]).is_err());
+        let args = Args::parse_from(["mutants"]);
+        let config: Config = toml::de::from_str("max_mutants_per_function = 0").unwrap();
+        let err = Options::new(&args, &config).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("max_mutants_per_function"),
+            "{err:#}"
+        );
+        let config: Config = toml::de::from_str("max_mutants_per_function = 2").unwrap();
+        let options = Options::new(&args, &config).unwrap();
+        assert_eq!(options.max_mutants_per_function, Some(2));
+    }
+
+    #[test]
+    fn max_log_age_must_be_a_non_negative_number() {
+        for bad in ["-1", "NaN", "inf", "1e300", "soon"] {
+            assert!(
+                Args::try_parse_from(["mutants", "--max-log-age", bad]).is_err(),
+                "{bad} should be rejected"
+            );
         }
+        let args = Args::parse_from(["mutants", "--max-log-age", "0.5"]);
+        let options = Options::new(&args, &Config::default()).unwrap();
+        assert_eq!(options.max_log_age, Some(Duration::from_secs(12 * 3600)));
+    }
+
+    #[test]
+    fn opt_in_genres_from_command_line_and_config() {
+        let options = Options::default();
+        assert!(options.genre_enabled(Genre::FnValue));
+        assert!(!options.genre_enabled(Genre::FormatString));
+
+        let args = Args::parse_from(["mutants", "--genre", "format-string"]);
+        let options = Options::new(&args, &Config::default()).unwrap();
+        assert!(options.genre_enabled(Genre::FormatString));
+
+        let args = Args::parse_from(["mutants"]);
+        let config: Config = toml::de::from_str(r#"genres = ["format-string"]"#).unwrap();
+        let options = Options::new(&args, &config).unwrap();
+        assert!(options.genre_enabled(Genre::FormatString));
+    }
+
+    #[test]
+    fn no_default_genres_enables_only_requested_genres() {
+        let args = Args::parse_from([
+            "mutants",
+            "--no-default-genres",
+            "--genre",
+            "fn-value",
+            "--genre",
+            "binary-operator",
+        ]);
+        let options = Options::new(&args, &Config::default()).unwrap();
+        let enabled = Genre::all()
+            .filter(|genre| options.genre_enabled(*genre))
+            .collect_vec();
+        assert_eq!(enabled, [Genre::FnValue, Genre::BinaryOperator]);
+
+        let args = Args::parse_from(["mutants", "--no-default-genres"]);
+        let config: Config = toml::de::from_str(r#"genres = ["if-branch"]"#).unwrap();
+        let options = Options::new(&args, &config).unwrap();
+        assert!(options.genre_enabled(Genre::IfBranch));
+        assert!(!options.genre_enabled(Genre::FnValue));
+    }
+
+    #[test]
+    fn skip_traits_from_command_line_or_config() {
+        let options = Options::default();
+        assert!(options.trait_skipped("Display"));
+        assert!(options.trait_skipped("Error"));
+        assert!(options.trait_skipped("Index"));
+        assert!(options.trait_skipped("DerefMut"));
+        assert!(!options.trait_skipped("Iterator"));
+
+        let config: Config = toml::de::from_str(r#"skip_traits = []"#).unwrap();
+        let options = Options::new(&Args::parse_from(["mutants"]), &config).unwrap();
+        assert!(!options.trait_skipped("Display"));
+
+        let args = Args::parse_from(["mutants", "--skip-trait", "Iterator"]);
+        let options = Options::new(&args, &config).unwrap();
+        assert!(options.trait_skipped("Iterator"));
+        assert!(!options.trait_skipped("Display"));
+    }
+
+    #[test]
+    fn glob_without_slash_matches_file_name_in_any_directory() {
+        let globs = build_glob_set(["foo.rs"]).unwrap().unwrap();
+        assert!(globs.is_match("foo.rs", Utf8Path::new("/src/tree/foo.rs")));
+        assert!(globs.is_match("src/a/foo.rs", Utf8Path::new("/src/tree/src/a/foo.rs")));
+        assert!(!globs.is_match("src/bar.rs", Utf8Path::new("/src/tree/src/bar.rs")));
+    }
+
+    #[test]
+    fn glob_with_slash_matches_tree_relative_path() {
+        let globs = build_glob_set(["src/*.rs"]).unwrap().unwrap();
+        assert!(globs.is_match("src/foo.rs", Utf8Path::new("/tree/src/foo.rs")));
+        assert!(!globs.is_match("lib/src/foo.rs", Utf8Path::new("/tree/lib/src/foo.rs")));
+    }
+
+    #[test]
+    fn leading_dot_slash_is_ignored() {
+        let globs = build_glob_set(["./src/foo.rs", "././src/bar.rs"])
+            .unwrap()
+            .unwrap();
+        assert!(globs.is_match("src/foo.rs", Utf8Path::new("/tree/src/foo.rs")));
+        assert!(globs.is_match("src/bar.rs", Utf8Path::new("/tree/src/bar.rs")));
+    }
+
+    #[test]
+    fn double_star_prefix_matches_at_any_depth() {
+        let globs = build_glob_set(["**/foo.rs"]).unwrap().unwrap();
+        assert!(globs.is_match("foo.rs", Utf8Path::new("/tree/foo.rs")));
+        assert!(globs.is_match("src/a/b/foo.rs", Utf8Path::new("/tree/src/a/b/foo.rs")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn absolute_glob_matches_absolute_path_only() {
+        let globs = build_glob_set(["/home/me/tree/src/*.rs"]).unwrap().unwrap();
+        assert!(globs.is_match("src/foo.rs", Utf8Path::new("/home/me/tree/src/foo.rs")));
+        assert!(!globs.is_match("src/foo.rs", Utf8Path::new("/elsewhere/src/foo.rs")));
+
+        // Relative globs are not matched against the absolute path, so a directory
+        // name above the tree root doesn't affect matching.
+        let globs = build_glob_set(["**/home/**"]).unwrap().unwrap();
+        assert!(!globs.is_match("src/foo.rs", Utf8Path::new("/home/me/tree/src/foo.rs")));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn backslashes_in_globs_are_path_separators_on_windows() {
+        let path: crate::path::TreeRelativePathBuf = "src\\sub\\foo.rs".parse().unwrap();
+        // Tree-relative paths are always shown and matched with forward slashes.
+        assert_eq!(path.to_string(), "src/sub/foo.rs");
+        let globs = build_glob_set(["src\\sub\\*.rs"]).unwrap().unwrap();
+        assert!(globs.is_match(
+            &path.to_string(),
+            Utf8Path::new("c:\\tree\\src\\sub\\foo.rs")
+        ));
     }
-    Ok(Some(builder.build()?))
 }
@@ -2,12 +2,13 @@
 
 //! The outcome of running a single mutation scenario, or a whole lab.
 
-use std::fmt;
+use std::fmt::{self, Write};
 use std::fs;
 use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Context;
+use clap::ValueEnum;
 use serde::ser::SerializeStruct;
 use serde::Serialize;
 use serde::Serializer;
@@ -52,6 +53,18 @@ impl fmt::Display for Phase {
     }
 }
 
+/// How mutants whose tests timed out are classified in the overall result.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeoutPolicy {
+    /// Timeouts count as missed mutants: no test failed, it was just slow.
+    Survived,
+    /// Timeouts count as caught mutants: the tests detected the hang.
+    Caught,
+    /// Timeouts are reported separately, and give a distinct exit code.
+    #[default]
+    Separate,
+}
+
 /// The outcome from a whole lab run containing multiple mutants.
 #[derive(Debug, Default, Serialize)]
 pub struct LabOutcome {
@@ -64,6 +77,11 @@ pub struct LabOutcome {
     pub unviable: usize,
     pub success: usize,
     pub failure: usize,
+    /// Mutants that weren't tested, because `--stop-function-after-caught` assumed they
+    /// would be caught. These aren't included in `total_mutants` or the mutation score.
+    pub assumed_caught: usize,
+    /// True if the `--baseline-command` failed, so nothing was tested.
+    pub baseline_command_failed: bool,
 }
 
 impl LabOutcome {
@@ -87,18 +105,70 @@ impl LabOutcome {
         self.outcomes.push(outcome);
     }
 
+    /// The number of missed mutants, including timeouts if they're counted as missed.
+    pub fn missed_count(&self, timeout_policy: TimeoutPolicy) -> usize {
+        match timeout_policy {
+            TimeoutPolicy::Survived => self.missed + self.timeout,
+            _ => self.missed,
+        }
+    }
+
+    /// The number of caught mutants, including timeouts if they're counted as caught.
+    pub fn caught_count(&self, timeout_policy: TimeoutPolicy) -> usize {
+        match timeout_policy {
+            TimeoutPolicy::Caught => self.caught + self.timeout,
+            _ => self.caught,
+        }
+    }
+
+    /// The mutation score: the percentage of viable mutants that were caught, or None if
+    /// there were no viable mutants.
+    ///
+    /// Unviable mutants are not counted. Timeouts are counted as caught or missed
+    /// according to the policy, or not counted at all if they're reported separately.
+    pub fn mutation_score(&self, timeout_policy: TimeoutPolicy) -> Option<f64> {
+        let caught = self.caught_count(timeout_policy);
+        let viable = caught + self.missed_count(timeout_policy);
+        if viable == 0 {
+            None
+        } else {
+            Some(caught as f64 * 100.0 / viable as f64)
+        }
+    }
+
+    /// True if the mutation score is below the `--fail-under` threshold, if there is one.
+    fn score_below_threshold(&self, options: &Options) -> bool {
+        match (
+            options.fail_under,
+            self.mutation_score(options.timeout_policy),
+        ) {
+            (Some(threshold), Some(score)) => score < threshold,
+            _ => false,
+        }
+    }
+
     /// Return the overall program exit code reflecting this outcome.
-    pub fn exit_code(&self) -> i32 {
+    ///
+    /// If there's a `--fail-under` threshold then missed mutants only cause a failure if
+    /// the mutation score is below it.
+    pub fn exit_code(&self, options: &Options) -> i32 {
         // TODO: Maybe move this into an error returned from experiment()?
-        if self
-            .outcomes
-            .iter()
-            .any(|o| !o.scenario.is_mutant() && !o.success())
+        if self.baseline_command_failed
+            || self
+                .outcomes
+                .iter()
+                .any(|o| !o.scenario.is_mutant() && !o.success())
         {
             exit_code::CLEAN_TESTS_FAILED
-        } else if self.timeout > 0 {
+        } else if self.timeout > 0 && options.timeout_policy == TimeoutPolicy::Separate {
             exit_code::TIMEOUT
-        } else if self.missed > 0 {
+        } else if options.fail_under.is_some() {
+            if self.score_below_threshold(options) {
+                exit_code::FOUND_PROBLEMS
+            } else {
+                exit_code::SUCCESS
+            }
+        } else if self.missed_count(options.timeout_policy) > 0 {
             exit_code::FOUND_PROBLEMS
         } else {
             exit_code::SUCCESS
@@ -120,11 +190,18 @@ impl LabOutcome {
         if self.caught > 0 {
             parts.push(format!("{} caught", self.caught));
         }
+        if self.assumed_caught > 0 {
+            parts.push(format!("{} assumed caught", self.assumed_caught));
+        }
         if self.unviable > 0 {
             parts.push(format!("{} unviable", self.unviable));
         }
         if self.timeout > 0 {
-            parts.push(format!("{} timeouts", self.timeout));
+            parts.push(match options.timeout_policy {
+                TimeoutPolicy::Separate => format!("{} timeouts", self.timeout),
+                TimeoutPolicy::Caught => format!("{} timeouts counted as caught", self.timeout),
+                TimeoutPolicy::Survived => format!("{} timeouts counted as missed", self.timeout),
+            });
         }
         if self.success > 0 {
             parts.push(format!("{} succeeded", self.success));
@@ -133,10 +210,65 @@ impl LabOutcome {
             parts.push(format!("{} failed", self.failure));
         }
         s.push_str(&parts.join(", "));
+        if let Some(threshold) = options.fail_under {
+            if let Some(score) = self.mutation_score(options.timeout_policy) {
+                write!(s, "; mutation score {score:.1}%").unwrap();
+                if score < threshold {
+                    write!(s, " is below the required {threshold}%").unwrap();
+                }
+            }
+        }
         s
     }
 }
 
+/// Aggregate counts, score, and time from a lab run, without the individual outcomes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LabSummary {
+    pub total_mutants: usize,
+    pub missed: usize,
+    pub caught: usize,
+    pub timeout: usize,
+    pub unviable: usize,
+    pub success: usize,
+    pub failure: usize,
+    pub assumed_caught: usize,
+    /// The mutation score as a percentage, or None if there were no viable mutants.
+    pub mutation_score: Option<f64>,
+    /// The wall-clock time for the whole run, in seconds.
+    pub elapsed_secs: f64,
+}
+
+impl LabOutcome {
+    /// Summarize the counts and score, with the given elapsed time for the run.
+    pub fn lab_summary(&self, options: &Options, elapsed: Duration) -> LabSummary {
+        LabSummary {
+            total_mutants: self.total_mutants,
+            missed: self.missed,
+            caught: self.caught,
+            timeout: self.timeout,
+            unviable: self.unviable,
+            success: self.success,
+            failure: self.failure,
+            assumed_caught: self.assumed_caught,
+            mutation_score: self.mutation_score(options.timeout_policy),
+            elapsed_secs: elapsed.as_secs_f64(),
+        }
+    }
+
+    /// Write the summary of this outcome as json to `path`.
+    pub fn write_summary_json(
+        &self,
+        options: &Options,
+        elapsed: Duration,
+        path: &Utf8Path,
+    ) -> Result<()> {
+        let summary = self.lab_summary(options, elapsed);
+        fs::write(path, serde_json::to_string_pretty(&summary)? + "\n")
+            .with_context(|| format!("write summary json {path:?}"))
+    }
+}
+
 /// The result of running one mutation scenario.
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[must_use]
@@ -178,6 +310,11 @@ impl ScenarioOutcome {
         self.phase_results.push(phase_result);
     }
 
+    /// The path of the log file for this scenario.
+    pub fn log_path(&self) -> &Utf8Path {
+        &self.log_path
+    }
+
     pub fn get_log_content(&self) -> Result<String> {
         fs::read_to_string(&self.log_path).context("read log file")
     }
@@ -204,10 +341,11 @@ impl ScenarioOutcome {
         self.last_phase_result().success()
     }
 
+    /// True if any phase timed out, or exceeded a resource limit which is counted the same way.
     pub fn has_timeout(&self) -> bool {
-        self.phase_results
-            .iter()
-            .any(|pr| pr.process_status.timeout())
+        self.phase_results.iter().any(|pr| {
+            pr.process_status.timeout() || pr.process_status == ProcessStatus::LimitExceeded
+        })
     }
 
     pub fn check_or_build_failed(&self) -> bool {
@@ -297,3 +435,91 @@ pub enum SummaryOutcome {
     Failure,
     Timeout,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mutation_score_counts_timeouts_by_policy() {
+        let lab_outcome = LabOutcome {
+            caught: 6,
+            missed: 2,
+            timeout: 2,
+            unviable: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            lab_outcome.mutation_score(TimeoutPolicy::Separate),
+            Some(75.0)
+        );
+        assert_eq!(
+            lab_outcome.mutation_score(TimeoutPolicy::Caught),
+            Some(80.0)
+        );
+        assert_eq!(
+            lab_outcome.mutation_score(TimeoutPolicy::Survived),
+            Some(60.0)
+        );
+        assert_eq!(
+            LabOutcome {
+                unviable: 3,
+                ..Default::default()
+            }
+            .mutation_score(TimeoutPolicy::Separate),
+            None
+        );
+    }
+
+    #[test]
+    fn lab_summary_has_counts_and_score() {
+        let lab_outcome = LabOutcome {
+            total_mutants: 10,
+            caught: 6,
+            missed: 2,
+            unviable: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(
+                lab_outcome.lab_summary(&Options::default(), Duration::from_millis(2500))
+            )
+            .unwrap(),
+            serde_json::json!({
+                "total_mutants": 10,
+                "missed": 2,
+                "caught": 6,
+                "timeout": 0,
+                "unviable": 2,
+                "success": 0,
+                "failure": 0,
+                "assumed_caught": 0,
+                "mutation_score": 75.0,
+                "elapsed_secs": 2.5,
+            })
+        );
+    }
+
+    #[test]
+    fn fail_under_controls_exit_code_for_missed_mutants() {
+        let lab_outcome = LabOutcome {
+            caught: 9,
+            missed: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            lab_outcome.exit_code(&Options::default()),
+            exit_code::FOUND_PROBLEMS
+        );
+        let options = Options {
+            fail_under: Some(90.0),
+            ..Default::default()
+        };
+        assert_eq!(lab_outcome.exit_code(&options), exit_code::SUCCESS);
+        let options = Options {
+            fail_under: Some(95.0),
+            ..Default::default()
+        };
+        assert_eq!(lab_outcome.exit_code(&options), exit_code::FOUND_PROBLEMS);
+    }
+}
@@ -17,11 +17,12 @@ use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use tracing::info;
 
+use crate::compare::Comparison;
 use crate::outcome::{LabOutcome, SummaryOutcome};
 use crate::*;
 
-const OUTDIR_NAME: &str = "mutants.out";
-const ROTATED_NAME: &str = "mutants.out.old";
+pub const OUTDIR_NAME: &str = "mutants.out";
+pub const ROTATED_NAME: &str = "mutants.out.old";
 const LOCK_JSON: &str = "lock.json";
 const LOCK_POLL: Duration = Duration::from_millis(100);
 
@@ -29,7 +30,7 @@ const LOCK_POLL: Duration = Duration::from_millis(100);
 /// a lock file to ensure that two cargo-mutants invocations don't try to write
 /// to the same `mutants.out` simultneously.
 #[derive(Debug, Serialize)]
-struct LockFile {
+pub(crate) struct LockFile {
     cargo_mutants_version: String,
     start_time: String,
     hostname: String,
@@ -172,6 +173,11 @@ impl OutputDir {
         LogFile::create_in(&self.log_dir, &scenario.log_file_name_base())
     }
 
+    /// Create a new log for something other than a scenario, such as a setup command.
+    pub fn create_named_log(&self, name: &str) -> Result<LogFile> {
+        LogFile::create_in(&self.log_dir, name)
+    }
+
     #[allow(dead_code)]
     /// Return the path of the `mutants.out` directory.
     pub fn path(&self) -> &Utf8Path {
@@ -207,6 +213,21 @@ impl OutputDir {
         Ok(())
     }
 
+    /// Record a mutant that wasn't tested because `--stop-function-after-caught` assumed
+    /// it would be caught.
+    ///
+    /// These are listed in `assumed_caught.txt`, which is only created if there are any.
+    pub fn add_assumed_caught(&mut self, mutant: &Mutant) -> Result<()> {
+        self.lab_outcome.assumed_caught += 1;
+        self.write_lab_outcome()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.join("assumed_caught.txt"))
+            .context("open assumed_caught.txt")?;
+        writeln!(file, "{mutant}").context("write to assumed_caught.txt")
+    }
+
     pub fn open_debug_log(&self) -> Result<File> {
         let debug_log_path = self.path.join("debug.log");
         OpenOptions::new()
@@ -224,6 +245,15 @@ impl OutputDir {
         .context("write mutants.json")
     }
 
+    /// Write the comparison with an earlier run.
+    pub fn write_comparison(&self, comparison: &Comparison) -> Result<()> {
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::create(self.path.join("compare.json"))?),
+            comparison,
+        )
+        .context("write compare.json")
+    }
+
     pub fn take_lab_outcome(self) -> LabOutcome {
         self.lab_outcome
     }
@@ -7,6 +7,10 @@
 //!
 //! On Unix, the subprocess runs as its own process group, so that any
 //! grandchild processses are also signalled if it's interrupted.
+//!
+//! Also on Unix, the subprocess can be run under [ResourceLimits], which are
+//! set with `ulimit` in a shell that then runs the real command, and so are
+//! inherited by each process it starts.
 
 use std::ffi::OsString;
 use std::io::Read;
@@ -31,6 +35,95 @@ const METADATA_TIMEOUT: Duration = Duration::from_secs(20);
 /// How frequently to check if a subprocess finished.
 const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
+/// Messages in the output of a process showing that it, or one of its children, was
+/// stopped by a resource limit.
+const LIMIT_EXCEEDED_MESSAGES: &[&str] = &[
+    // Shown by Cargo when a test binary is killed for using too much CPU time; older
+    // versions show only the signal number, which is the same on Linux and macOS.
+    "SIGXCPU",
+    "(signal: 24)",
+    // Shown by the Rust runtime when an allocation fails, before it aborts.
+    "memory allocation of ",
+];
+
+/// Limits on the resources used by each process started for a scenario.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// The maximum virtual memory size of each process, in bytes.
+    pub memory_bytes: Option<u64>,
+    /// The maximum CPU time used by each process.
+    pub cpu_time: Option<Duration>,
+}
+
+impl ResourceLimits {
+    /// True if no limits are set.
+    pub fn is_unlimited(&self) -> bool {
+        self.memory_bytes.is_none() && self.cpu_time.is_none()
+    }
+
+    /// True if limits can be applied to processes on this platform.
+    pub fn supported() -> bool {
+        cfg!(unix)
+    }
+
+    /// Return the command to run `argv` under these limits.
+    ///
+    /// On platforms where limits aren't supported, the command is unchanged.
+    fn wrap_argv(&self, argv: &[String]) -> Vec<String> {
+        if self.is_unlimited() || !ResourceLimits::supported() {
+            return argv.to_vec();
+        }
+        let mut script = String::new();
+        if let Some(bytes) = self.memory_bytes {
+            // ulimit counts memory in kilobytes.
+            script.push_str(&format!("ulimit -S -v {} && ", (bytes / 1024).max(1)));
+        }
+        if let Some(cpu_time) = self.cpu_time {
+            // ulimit counts whole seconds; round up so that small limits aren't zero.
+            let secs = cpu_time.as_secs() + u64::from(cpu_time.subsec_nanos() > 0);
+            script.push_str(&format!("ulimit -S -t {secs} && "));
+        }
+        script.push_str("exec \"$@\"");
+        ["sh", "-c", &script, "sh"]
+            .into_iter()
+            .map(str::to_owned)
+            .chain(argv.iter().cloned())
+            .collect()
+    }
+}
+
+/// Show new output from the process on the console, if the log is echoed.
+fn echo_output(log_file: &mut LogFile, console: &Console, finished: bool) -> Result<()> {
+    let lines = log_file.take_echo_lines(finished)?;
+    if !lines.is_empty() {
+        console.message(&lines);
+    }
+    Ok(())
+}
+
+/// Parse a memory size like `512M` or `2G` into bytes.
+///
+/// The suffixes `K`, `M`, `G` and `T` are powers of 1024, and a number without a
+/// suffix is in bytes.
+pub fn parse_memory_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let number: u64 = number.parse().context("not a number")?;
+    let power = match unit.to_ascii_uppercase().as_str() {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return Err(anyhow!("unknown unit {unit:?}: should be K, M, G or T")),
+    };
+    match number.checked_mul(1024u64.pow(power)) {
+        Some(0) => Err(anyhow!("must be more than zero")),
+        Some(bytes) => Ok(bytes),
+        None => Err(anyhow!("too large")),
+    }
+}
+
 pub struct Process {
     child: Popen,
     start: Instant,
@@ -38,25 +131,38 @@ pub struct Process {
 }
 
 impl Process {
-    /// Run a subprocess to completion, watching for interupts, with a timeout, while
-    /// ticking the progress bar.
+    /// Run a subprocess to completion, watching for interupts, with a timeout and
+    /// resource limits, while ticking the progress bar.
+    ///
+    /// If the process fails after being stopped by a resource limit, the status is
+    /// [ProcessStatus::LimitExceeded].
     pub fn run(
         argv: &[String],
         env: &[(String, String)],
         cwd: &Utf8Path,
         timeout: Duration,
+        limits: &ResourceLimits,
         log_file: &mut LogFile,
         console: &Console,
     ) -> Result<ProcessStatus> {
-        let mut child = Process::start(argv, env, cwd, timeout, log_file)?;
-        let process_status = loop {
+        let log_start = log_file.end_offset();
+        let mut child = Process::start(&limits.wrap_argv(argv), env, cwd, timeout, log_file)?;
+        let mut process_status = loop {
             if let Some(exit_status) = child.poll()? {
                 break exit_status;
             } else {
+                echo_output(log_file, console, false)?;
                 console.tick();
                 sleep(WAIT_POLL_INTERVAL);
             }
         };
+        echo_output(log_file, console, true)?;
+        if process_status == ProcessStatus::Failure
+            && !limits.is_unlimited()
+            && output_shows_limit_exceeded(&log_file.read_from(log_start)?)
+        {
+            process_status = ProcessStatus::LimitExceeded;
+        }
         log_file.message(&format!("result: {process_status:?}"));
         Ok(process_status)
     }
@@ -187,12 +293,21 @@ fn terminate_child_impl(child: &mut Popen) -> Result<()> {
     Ok(())
 }
 
+/// True if the output of a process shows that it was stopped by a resource limit.
+fn output_shows_limit_exceeded(output: &str) -> bool {
+    LIMIT_EXCEEDED_MESSAGES
+        .iter()
+        .any(|message| output.contains(message))
+}
+
 /// The result of running a single child process.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
 pub enum ProcessStatus {
     Success,
     Failure,
     Timeout,
+    /// The process was stopped by a memory or CPU limit; this is counted as a timeout.
+    LimitExceeded,
 }
 
 impl ProcessStatus {
@@ -271,3 +386,67 @@ pub fn get_command_output(argv: &[&str], cwd: &Utf8Path) -> Result<String> {
     debug!("output: {}", stdout.trim());
     Ok(stdout)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_memory_sizes() {
+        assert_eq!(parse_memory_size("1000").unwrap(), 1000);
+        assert_eq!(parse_memory_size("512k").unwrap(), 512 << 10);
+        assert_eq!(parse_memory_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_memory_size("2G").unwrap(), 2 << 30);
+        assert_eq!(parse_memory_size("1T").unwrap(), 1 << 40);
+        assert!(parse_memory_size("").is_err());
+        assert!(parse_memory_size("0").is_err());
+        assert!(parse_memory_size("2X").is_err());
+        assert!(parse_memory_size("lots").is_err());
+        assert!(parse_memory_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn unlimited_argv_is_unchanged() {
+        let argv = ["cargo".to_owned(), "test".to_owned()];
+        assert_eq!(ResourceLimits::default().wrap_argv(&argv), argv);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn limits_are_set_by_a_shell() {
+        let argv = ["cargo".to_owned(), "test".to_owned()];
+        let limits = ResourceLimits {
+            memory_bytes: Some(2 << 30),
+            cpu_time: Some(Duration::from_secs_f64(2.5)),
+        };
+        assert_eq!(
+            limits.wrap_argv(&argv),
+            [
+                "sh",
+                "-c",
+                "ulimit -S -v 2097152 && ulimit -S -t 3 && exec \"$@\"",
+                "sh",
+                "cargo",
+                "test"
+            ]
+        );
+    }
+
+    #[test]
+    fn limit_exceeded_is_found_in_output() {
+        assert!(output_shows_limit_exceeded(
+            "error: test failed, to rerun pass `--lib`\n\
+             Caused by:\n  process didn't exit successfully: `/tmp/x/deps/foo-1234` \
+             (signal: 24, SIGXCPU: CPU time limit exceeded)\n"
+        ));
+        assert!(output_shows_limit_exceeded(
+            "process didn't exit successfully: `/tmp/x/deps/foo-1234` (signal: 24)\n"
+        ));
+        assert!(output_shows_limit_exceeded(
+            "memory allocation of 1073741824 bytes failed\n"
+        ));
+        assert!(!output_shows_limit_exceeded(
+            "thread 'test::it_works' panicked at 'assertion failed'\n"
+        ));
+    }
+}
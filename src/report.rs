@@ -0,0 +1,100 @@
+// Copyright 2023 Martin Pool
+
+//! A structured report of the outcome of a run, returned by the library API in [crate::run].
+
+use serde::Serialize;
+
+use crate::outcome::{LabOutcome, SummaryOutcome};
+use crate::*;
+
+/// The outcome of testing all the mutants in a tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunReport {
+    /// True if the unmutated tree built and passed its tests.
+    ///
+    /// If not, no mutants were tested.
+    pub baseline_passed: bool,
+    /// The outcome of each mutant that was tested, in the order they finished.
+    pub mutants: Vec<MutantReport>,
+    /// The number of mutants tested.
+    pub total_mutants: usize,
+    pub caught: usize,
+    pub missed: usize,
+    pub timeout: usize,
+    pub unviable: usize,
+    /// The exit code that `cargo mutants` would return for this outcome, as described in
+    /// the book.
+    pub exit_code: i32,
+}
+
+/// The outcome of testing one mutant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MutantReport {
+    /// The name of the mutant, as shown by `--list`, like
+    /// `src/lib.rs:12: replace even -> bool with true`.
+    pub name: String,
+    /// The stable id of the mutant, as shown by `--list --json`.
+    pub id: String,
+    /// The tree-relative path of the mutated file, with forward slashes.
+    pub file: String,
+    /// The line where the mutation starts.
+    pub line: usize,
+    /// The name of the function containing the mutation.
+    pub function: String,
+    pub genre: Genre,
+    pub outcome: MutantOutcome,
+}
+
+/// What happened when one mutant was tested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum MutantOutcome {
+    /// The tests failed, so the mutant was caught.
+    Caught,
+    /// The tests passed even with the mutation, so it was missed.
+    Missed,
+    /// The tests didn't finish within the timeout.
+    Timeout,
+    /// The mutated tree failed to build.
+    Unviable,
+}
+
+impl RunReport {
+    pub(crate) fn new(lab_outcome: &LabOutcome, options: &Options) -> RunReport {
+        let mut baseline_passed = !lab_outcome.baseline_command_failed;
+        let mut mutants = Vec::new();
+        for outcome in &lab_outcome.outcomes {
+            let outcome_summary = outcome.summary();
+            let Scenario::Mutant(mutant) = &outcome.scenario else {
+                baseline_passed &= outcome.success();
+                continue;
+            };
+            let outcome = match outcome_summary {
+                SummaryOutcome::CaughtMutant => MutantOutcome::Caught,
+                SummaryOutcome::MissedMutant => MutantOutcome::Missed,
+                SummaryOutcome::Timeout => MutantOutcome::Timeout,
+                SummaryOutcome::Unviable => MutantOutcome::Unviable,
+                // Only the baseline succeeds or fails.
+                SummaryOutcome::Success | SummaryOutcome::Failure => continue,
+            };
+            mutants.push(MutantReport {
+                name: mutant.to_string(),
+                id: mutant.id.clone(),
+                file: mutant.source_file.tree_relative_slashes(),
+                line: mutant.span.start.line,
+                function: mutant.function_name().to_owned(),
+                genre: mutant.genre,
+                outcome,
+            });
+        }
+        RunReport {
+            baseline_passed,
+            mutants,
+            total_mutants: lab_outcome.total_mutants,
+            caught: lab_outcome.caught,
+            missed: lab_outcome.missed,
+            timeout: lab_outcome.timeout,
+            unviable: lab_outcome.unviable,
+            exit_code: lab_outcome.exit_code(options),
+        }
+    }
+}
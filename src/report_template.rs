@@ -0,0 +1,180 @@
+// Copyright 2023 Martin Pool
+
+//! Custom reports of the outcome of each mutant, from a template given by the user.
+//!
+//! The template is rendered once for each mutant, replacing these placeholders:
+//!
+//! * `{file}`: the tree-relative path of the mutated file, with forward slashes.
+//! * `{line}`: the line where the mutation starts.
+//! * `{function}`: the name of the function containing the mutation.
+//! * `{replacement}`: the text inserted by the mutation.
+//! * `{outcome}`: `caught`, `missed`, `timeout`, or `unviable`.
+//!
+//! `{{` and `}}` insert literal braces.
+
+use std::fmt;
+use std::fs::read_to_string;
+
+use anyhow::{bail, Context};
+
+use crate::outcome::{LabOutcome, SummaryOutcome};
+use crate::*;
+
+/// The names of the placeholders that can be used in a template.
+const PLACEHOLDERS: &[&str] = &["file", "line", "function", "replacement", "outcome"];
+
+/// One piece of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// A parsed template for one line of the report per mutant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTemplate {
+    parts: Vec<Part>,
+}
+
+impl ReportTemplate {
+    /// Read and parse a template from a file.
+    pub fn read_file(path: &Utf8Path) -> Result<ReportTemplate> {
+        let text =
+            read_to_string(path).with_context(|| format!("read report template {path:?}"))?;
+        ReportTemplate::parse(&text).with_context(|| format!("parse report template {path:?}"))
+    }
+
+    /// Parse a template, checking that it uses only known placeholders.
+    ///
+    /// A trailing newline is ignored, since each rendered mutant is put on its own line.
+    pub fn parse(text: &str) -> Result<ReportTemplate> {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("unterminated placeholder {{{name}"),
+                        }
+                    }
+                    if !PLACEHOLDERS.contains(&name.as_str()) {
+                        bail!(
+                            "unknown placeholder {{{name}}}: should be one of {}",
+                            PLACEHOLDERS.join(", ")
+                        );
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(name));
+                }
+                '}' => bail!("unmatched '}}': use '}}}}' for a literal brace"),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(ReportTemplate { parts })
+    }
+
+    /// Render the template for each mutant in the outcome, one per line.
+    pub fn format(&self, lab_outcome: &LabOutcome) -> String {
+        let mut s = String::new();
+        for outcome in &lab_outcome.outcomes {
+            let Scenario::Mutant(mutant) = &outcome.scenario else {
+                continue;
+            };
+            let outcome_name = match outcome.summary() {
+                SummaryOutcome::CaughtMutant => "caught",
+                SummaryOutcome::MissedMutant => "missed",
+                SummaryOutcome::Timeout => "timeout",
+                SummaryOutcome::Unviable => "unviable",
+                // Only the baseline succeeds or fails.
+                SummaryOutcome::Success | SummaryOutcome::Failure => continue,
+            };
+            s.push_str(&self.render(mutant, outcome_name));
+            s.push('\n');
+        }
+        s
+    }
+
+    fn render(&self, mutant: &Mutant, outcome_name: &str) -> String {
+        let mut s = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => s.push_str(text),
+                Part::Placeholder(name) => match name.as_str() {
+                    "file" => s.push_str(&mutant.source_file.tree_relative_slashes()),
+                    "line" => s.push_str(&mutant.span.start.line.to_string()),
+                    "function" => s.push_str(mutant.function_name()),
+                    "replacement" => s.push_str(mutant.replacement_text()),
+                    "outcome" => s.push_str(outcome_name),
+                    _ => unreachable!("placeholders are checked when parsed"),
+                },
+            }
+        }
+        s
+    }
+}
+
+/// Show the template as it could be written, with literal braces escaped.
+impl fmt::Display for ReportTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => f.write_str(&text.replace('{', "{{").replace('}', "}}"))?,
+                Part::Placeholder(name) => write!(f, "{{{name}}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_placeholders_and_escapes() {
+        let template = ReportTemplate::parse("{file}:{line} {{{outcome}}}\n").unwrap();
+        assert_eq!(
+            template.parts,
+            [
+                Part::Placeholder("file".to_owned()),
+                Part::Literal(":".to_owned()),
+                Part::Placeholder("line".to_owned()),
+                Part::Literal(" {".to_owned()),
+                Part::Placeholder("outcome".to_owned()),
+                Part::Literal("}".to_owned()),
+            ]
+        );
+        assert_eq!(template.to_string(), "{file}:{line} {{{outcome}}}");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        let err = ReportTemplate::parse("{file} {mutant}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown placeholder {mutant}: should be one of file, line, function, replacement, outcome"
+        );
+        assert!(ReportTemplate::parse("{file").is_err());
+        assert!(ReportTemplate::parse("file}").is_err());
+    }
+}
@@ -68,6 +68,8 @@ pub struct Package {
     pub name: String,
     /// For Cargo, the path of the `Cargo.toml` manifest file, relative to the top of the tree.
     pub relative_manifest_path: Utf8PathBuf,
+    /// The names of other packages in the same workspace that this package depends on.
+    pub workspace_dependencies: Vec<String>,
 }
 
 #[cfg(test)]
@@ -95,6 +97,7 @@ mod test {
             &Arc::new(Package {
                 name: "imaginary-package".to_owned(),
                 relative_manifest_path: "whatever/Cargo.toml".into(),
+                workspace_dependencies: Vec::new(),
             }),
         )
         .unwrap();
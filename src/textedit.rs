@@ -5,7 +5,7 @@
 use serde::Serialize;
 
 /// A (line, column) position in a source file.
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Serialize)]
 pub struct LineColumn {
     /// 1-based line number.
     pub line: usize,
@@ -25,12 +25,12 @@ impl From<proc_macro2::LineColumn> for LineColumn {
 
 /// A contiguous text span in a file.
 ///
-/// TODO: Perhaps a semi-open range that can represent an empty span would be more general?
+/// The span is semi-open: it includes the character at `start` but not the one at `end`.
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize)]
 pub struct Span {
     /// The inclusive position where the span starts.
     pub start: LineColumn,
-    /// The inclusive position where the span ends.
+    /// The exclusive position where the span ends.
     pub end: LineColumn,
 }
 
@@ -54,8 +54,8 @@ impl From<&proc_macro2::Span> for Span {
 
 /// Replace a subregion of text.
 ///
-/// Returns a copy of `s` with the region between `start` and `end` inclusive replaced by
-/// `replacement`.
+/// Returns a copy of `s` with the region from `start` (inclusive) to `end` (exclusive)
+/// replaced by `replacement`.
 pub(crate) fn replace_region(
     s: &str,
     start: &LineColumn,
@@ -70,7 +70,7 @@ pub(crate) fn replace_region(
         if line_no < start.line
             || line_no > end.line
             || (line_no == start.line && col_no < start.column)
-            || (line_no == end.line && col_no > end.column)
+            || (line_no == end.line && col_no >= end.column)
         {
             r.push(c);
         } else if line_no == start.line && col_no == start.column {
@@ -88,6 +88,29 @@ pub(crate) fn replace_region(
     r
 }
 
+/// Return the text of a subregion, from `start` (inclusive) to `end` (exclusive).
+pub(crate) fn extract_region(s: &str, start: &LineColumn, end: &LineColumn) -> String {
+    let mut r = String::new();
+    let mut line_no = 1;
+    let mut col_no = 1;
+    for c in s.chars() {
+        if (line_no > start.line || (line_no == start.line && col_no >= start.column))
+            && (line_no < end.line || (line_no == end.line && col_no < end.column))
+        {
+            r.push(c);
+        }
+        if c == '\n' {
+            line_no += 1;
+            col_no = 1;
+        } else if c == '\r' {
+            // counts as part of the last column, not a separate column
+        } else {
+            col_no += 1;
+        }
+    }
+    r
+}
+
 #[cfg(test)]
 mod test {
     use indoc::indoc;
@@ -106,7 +129,7 @@ mod test {
                     column: 10
                 },
                 &LineColumn { line: 3, column: 2 },
-                "{}\r\n"
+                "{}"
             ),
             "fn foo() {}\r\n//hey!\r\n"
         );
@@ -130,7 +153,7 @@ mod test {
                 line: 2,
                 column: 10,
             },
-            &LineColumn { line: 5, column: 1 },
+            &LineColumn { line: 5, column: 2 },
             "{ /* body deleted */ }",
         );
         assert_eq!(
@@ -152,7 +175,7 @@ mod test {
             },
             &LineColumn {
                 line: 7,
-                column: 19,
+                column: 20,
             },
             "69",
         );
@@ -169,4 +192,52 @@ mod test {
             "# }
         );
     }
+
+    #[test]
+    fn span_from_syn_covers_exactly_the_block() {
+        use syn::spanned::Spanned;
+
+        let source = "fn foo() {\n    some();\n}\nconst BAR: u32 = 32;\n";
+        let file = syn::parse_file(source).unwrap();
+        let block = match &file.items[0] {
+            syn::Item::Fn(item_fn) => &item_fn.block,
+            _ => panic!("expected a function"),
+        };
+        let span = Span::from(block.span());
+        assert_eq!(span.end, LineColumn { line: 3, column: 2 });
+        assert_eq!(
+            replace_region(source, &span.start, &span.end, "{}"),
+            "fn foo() {}\nconst BAR: u32 = 32;\n"
+        );
+    }
+
+    #[test]
+    fn extract_single_line_and_multi_line_regions() {
+        let source = "fn foo() {\n    some();\n}\nconst BAR: u32 = 32;\n";
+        assert_eq!(
+            extract_region(
+                source,
+                &LineColumn {
+                    line: 4,
+                    column: 18
+                },
+                &LineColumn {
+                    line: 4,
+                    column: 20
+                },
+            ),
+            "32"
+        );
+        assert_eq!(
+            extract_region(
+                source,
+                &LineColumn {
+                    line: 1,
+                    column: 10
+                },
+                &LineColumn { line: 3, column: 2 },
+            ),
+            "{\n    some();\n}"
+        );
+    }
 }
@@ -50,4 +50,9 @@ pub trait Tool: Debug + Send + Sync {
         phase: Phase,
         options: &Options,
     ) -> Result<Vec<(String, String)>>;
+
+    /// True if the output of a failed phase shows that it failed for a transient
+    /// reason unrelated to the code, such as contention on a lock, so that it
+    /// should be run again.
+    fn is_transient_failure(&self, output: &str) -> bool;
 }
@@ -8,25 +8,50 @@
 //! e.g. for cargo they are identified from the targets. The tree walker then
 //! follows `mod` statements to recursively visit other referenced files.
 
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Write};
 use std::sync::Arc;
 
 use anyhow::Context;
 use itertools::Itertools;
-use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
 use quote::{quote, ToTokens};
 use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
 use syn::{
-    AngleBracketedGenericArguments, Attribute, Expr, GenericArgument, ItemFn, Path, PathArguments,
-    ReturnType, Type, TypeArray, TypeTuple,
+    AngleBracketedGenericArguments, Attribute, Expr, ExprMethodCall, GenericArgument, Generics,
+    ItemFn, Lit, Path, PathArguments, ReturnType, Token, Type, TypeArray, TypeTuple,
 };
-use tracing::{debug, debug_span, trace, trace_span, warn};
+use tracing::{debug, debug_span, info, trace, trace_span, warn};
 
+use crate::in_diff::ChangedLines;
+use crate::known_mutants::read_mutant_ids;
+use crate::mutate::assign_ids;
 use crate::path::TreeRelativePathBuf;
-use crate::source::SourceFile;
+use crate::source::{Package, SourceFile};
+use crate::textedit::{extract_region, Span};
 use crate::*;
 
+/// The most statements deleted from one function by the `StatementDelete` genre, to
+/// limit the number of mutants generated for long functions.
+const MAX_STATEMENT_DELETIONS: usize = 20;
+
+/// The most statements like `v.push(x);` deleted from one function by the
+/// `CollectionPush` genre.
+const MAX_PUSH_DELETIONS: usize = 20;
+
+/// Traits for overloaded operators, whose methods return their associated `Output` type.
+const OPERATOR_TRAITS: &[&str] = &[
+    "Add", "BitAnd", "BitOr", "BitXor", "Div", "Mul", "Neg", "Not", "Rem", "Shl", "Shr", "Sub",
+];
+
+/// The most pairs of arms swapped in one `match` by the `MatchArmSwap` genre.
+const MAX_MATCH_ARM_SWAPS: usize = 10;
+
 /// Mutants and files discovered in a source tree.
 ///
 /// Files are listed separately so that we can represent files that
@@ -36,20 +61,114 @@ pub struct Discovered {
     pub files: Vec<Arc<SourceFile>>,
 }
 
+/// Why an item such as a function, impl, or module was not mutated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Test code, marked `#[test]`, `#[bench]`, or `#[cfg(test)]`.
+    Test,
+    /// Marked `#[mutants::skip]`.
+    MutantsSkip,
+    /// Marked `#[doc(hidden)]`, and `--exclude-doc-hidden` was given.
+    DocHidden,
+    /// Gated on a `cfg` that is false in the profile being built.
+    CfgFalse,
+    /// An `unsafe fn`.
+    Unsafe,
+    /// A function with type parameters, and `--exclude-generics` was given.
+    Generic,
+    /// A method called `new`, which often has no good alternative.
+    Constructor,
+    /// A function with an empty body.
+    EmptyBody,
+    /// An `impl Default`.
+    DefaultImpl,
+    /// An impl of a trait that is skipped by the options.
+    SkippedTrait,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::Test => "test code",
+            SkipReason::MutantsSkip => "marked #[mutants::skip]",
+            SkipReason::DocHidden => "doc(hidden) and --exclude-doc-hidden",
+            SkipReason::CfgFalse => "cfg is false in this build profile",
+            SkipReason::Unsafe => "unsafe fn",
+            SkipReason::Generic => "generic fn and --exclude-generics",
+            SkipReason::Constructor => "constructor",
+            SkipReason::EmptyBody => "empty body",
+            SkipReason::DefaultImpl => "impl Default",
+            SkipReason::SkippedTrait => "impl of a skipped trait",
+        })
+    }
+}
+
 /// Discover all mutants and all source files.
 ///
 /// The list of source files includes even those with no mutants.
+///
+/// Files are visited in a deterministic order, independent of the order the tool
+/// returns the root files or the filesystem lists directories: the root files sorted
+/// by path, then the files they reference, in the order of their `mod` statements.
+/// So, mutants are also always discovered in the same order.
 pub fn walk_tree(tool: &dyn Tool, root: &Utf8Path, options: &Options) -> Result<Discovered> {
     let error_exprs = options
         .error_values
         .iter()
         .map(|e| syn::parse_str(e).with_context(|| format!("Failed to parse error value {e:?}")))
         .collect::<Result<Vec<Expr>>>()?;
+    let changed_lines = ChangedLines::for_options(root, options)?;
+    let known_ids = options
+        .only_missing_from
+        .as_deref()
+        .map(read_mutant_ids)
+        .transpose()?;
     let mut mutants = Vec::new();
     let mut files: Vec<Arc<SourceFile>> = Vec::new();
-    let mut file_queue: VecDeque<Arc<SourceFile>> = tool.root_files(root)?.into();
+    let mut root_files = tool.root_files(root)?;
+    root_files.sort_by(|a, b| a.tree_relative_path.cmp(&b.tree_relative_path));
+    // In whole-package mode, the changed lines select packages, and not mutants
+    // within them.
+    let changed_lines = match changed_lines {
+        Some(changed_lines) if options.only_package_with_changes => {
+            let packages = changed_lines.packages_with_changes(
+                root_files.iter().map(|file| file.package.as_ref()),
+                options.include_dependents,
+            );
+            debug!(?packages, "packages with changes");
+            root_files.retain(|file| packages.contains(&file.package.name));
+            None
+        }
+        changed_lines => changed_lines,
+    };
+    let canonical_root = root
+        .canonicalize_utf8()
+        .with_context(|| format!("canonicalize {root:?}"))?;
+    // Files reached through symlinks are only visited once, by their canonical path.
+    let mut seen_paths: HashSet<Utf8PathBuf> = HashSet::new();
+    let mut file_queue: VecDeque<Arc<SourceFile>> = root_files.into();
     while let Some(source_file) = file_queue.pop_front() {
         check_interrupted()?;
+        let full_path = source_file.tree_relative_path.within(root);
+        let canonical_path = full_path
+            .canonicalize_utf8()
+            .with_context(|| format!("canonicalize {full_path:?}"))?;
+        if !seen_paths.insert(canonical_path.clone()) {
+            debug!(
+                ?full_path,
+                ?canonical_path,
+                "skip file already visited by another path"
+            );
+            continue;
+        }
+        if !canonical_path.starts_with(&canonical_root) {
+            // Mutating the file would write through the symlink, outside the build
+            // directory.
+            warn!(
+                "{full_path} is a link to {canonical_path}, outside the source tree; skipping it"
+            );
+            continue;
+        }
         let (mut file_mutants, more_files) =
             walk_file(root, Arc::clone(&source_file), options, &error_exprs)?;
         // We'll still walk down through files that don't match globs, so that
@@ -59,19 +178,57 @@ pub fn walk_tree(tool: &dyn Tool, root: &Utf8Path, options: &Options) -> Result<
         for path in more_files {
             file_queue.push_back(Arc::new(SourceFile::new(root, path, &source_file.package)?));
         }
-        let path = &source_file.tree_relative_path;
+        let path = source_file.tree_relative_slashes();
+        let absolute_path = source_file.tree_relative_path.within(root);
         if let Some(examine_globset) = &options.examine_globset {
-            if !examine_globset.is_match(path.as_ref()) {
+            if !examine_globset.is_match(&path, &absolute_path) {
                 trace!("{path:?} does not match examine globset");
                 continue;
             }
         }
         if let Some(exclude_globset) = &options.exclude_globset {
-            if exclude_globset.is_match(path.as_ref()) {
+            if exclude_globset.is_match(&path, &absolute_path) {
                 trace!("{path:?} excluded by globset");
                 continue;
             }
         }
+        if let Some(examine_file_list) = &options.examine_file_list {
+            if !examine_file_list.contains(&path) {
+                trace!("{path:?} not in examine file list");
+                continue;
+            }
+        }
+        if let Some(exclude_file_list) = &options.exclude_file_list {
+            if exclude_file_list.contains(&path) {
+                trace!("{path:?} in exclude file list");
+                continue;
+            }
+        }
+        if let Some(changed_lines) = &changed_lines {
+            if options.only_new_files {
+                // Everything in a new file is new, including in files that aren't in the
+                // diff because they're untracked.
+                if !changed_lines.is_new_file(&path) {
+                    trace!("{path:?} is not a new file in git");
+                    continue;
+                }
+            } else {
+                if !changed_lines.touches_file(&path) {
+                    trace!("{path:?} not changed in git");
+                    continue;
+                }
+                file_mutants.retain(|m| {
+                    changed_lines.touches_lines(
+                        &path,
+                        m.function_span.start.line,
+                        m.function_span.end.line,
+                    )
+                });
+            }
+        }
+        if let Some(known_ids) = &known_ids {
+            file_mutants.retain(|m| !known_ids.contains(&m.id));
+        }
         if let Some(examine_names) = &options.examine_names {
             if !examine_names.is_empty() {
                 file_mutants.retain(|m| examine_names.is_match(&m.to_string()));
@@ -82,12 +239,35 @@ pub fn walk_tree(tool: &dyn Tool, root: &Utf8Path, options: &Options) -> Result<
                 file_mutants.retain(|m| !exclude_names.is_match(&m.to_string()));
             }
         }
+        if let Some(max) = options.max_mutants_per_function {
+            cap_mutants_per_function(&mut file_mutants, max);
+        }
         mutants.append(&mut file_mutants);
         files.push(Arc::clone(&source_file));
     }
     Ok(Discovered { mutants, files })
 }
 
+/// Keep only the first `max` mutants from each function in a file, in the order they
+/// were discovered, and say which functions were capped.
+fn cap_mutants_per_function(mutants: &mut Vec<Mutant>, max: usize) {
+    let counts = mutants.iter().counts_by(|m| Arc::clone(&m.function_name));
+    for (name, count) in counts.iter().sorted() {
+        if *count > max {
+            info!(
+                "Only testing {max} of {count} mutants in {path}: {name}",
+                path = mutants[0].source_file.tree_relative_slashes()
+            );
+        }
+    }
+    let mut kept: HashMap<Arc<String>, usize> = HashMap::new();
+    mutants.retain(|m| {
+        let n = kept.entry(Arc::clone(&m.function_name)).or_default();
+        *n += 1;
+        *n <= max
+    });
+}
+
 /// Find all possible mutants in a source file.
 ///
 /// Returns the mutants found, and more files discovered by `mod` statements to visit.
@@ -99,19 +279,238 @@ fn walk_file(
 ) -> Result<(Vec<Mutant>, Vec<TreeRelativePathBuf>)> {
     let _span = debug_span!("source_file", path = source_file.tree_relative_slashes()).entered();
     debug!("visit source file");
-    let syn_file = syn::parse_str::<syn::File>(&source_file.code)
-        .with_context(|| format!("failed to parse {}", source_file.tree_relative_slashes()))?;
-    let mut visitor = DiscoveryVisitor {
-        error_exprs,
-        more_files: Vec::new(),
-        mutants: Vec::new(),
-        namespace_stack: Vec::new(),
+    let syn_file = parse_source_file(&source_file)?;
+    let mut visitor = DiscoveryVisitor::new(root, source_file, &syn_file, options, error_exprs);
+    visitor.visit_file(&syn_file);
+    assign_ids(&mut visitor.mutants);
+    Ok((visitor.mutants, visitor.more_files))
+}
+
+fn parse_source_file(source_file: &SourceFile) -> Result<syn::File> {
+    syn::parse_str::<syn::File>(&source_file.code)
+        .with_context(|| format!("failed to parse {}", source_file.tree_relative_slashes()))
+}
+
+/// Describe the functions, impls, and modules in one source file as cargo-mutants sees
+/// them, with their full names, return types, and attributes, and either the number of
+/// mutants generated within them or the reason they were skipped.
+///
+/// `path` is relative to the root of the tree. `mod` statements in the file are listed
+/// but not followed.
+pub fn dump_ast(root: &Utf8Path, path: &Utf8Path, options: &Options) -> Result<String> {
+    let error_exprs = options
+        .error_values
+        .iter()
+        .map(|e| syn::parse_str(e).with_context(|| format!("Failed to parse error value {e:?}")))
+        .collect::<Result<Vec<Expr>>>()?;
+    // Names within the file don't depend on the package, so there's no need to ask the
+    // tool which package it's in.
+    let package = Arc::new(Package {
+        name: String::new(),
+        relative_manifest_path: Utf8PathBuf::new(),
+        workspace_dependencies: Vec::new(),
+    });
+    let source_file = Arc::new(SourceFile::new(
+        root,
+        TreeRelativePathBuf::new(path.to_owned()),
+        &package,
+    )?);
+    let syn_file = parse_source_file(&source_file)?;
+    let mut visitor = DiscoveryVisitor::new(
+        root,
+        Arc::clone(&source_file),
+        &syn_file,
         options,
-        root: root.to_owned(),
-        source_file,
+        &error_exprs,
+    );
+    visitor.dumped_items = Some(Vec::new());
+    visitor.visit_file(&syn_file);
+    let mut s = String::new();
+    for item in visitor.dumped_items.unwrap_or_default() {
+        write!(
+            s,
+            "{path}:{line}: {kind} {name}",
+            path = source_file.tree_relative_slashes(),
+            line = item.line,
+            kind = item.kind,
+            name = item.name,
+        )
+        .unwrap();
+        if let Some(return_type) = &item.return_type {
+            write!(s, " {return_type}").unwrap();
+        }
+        for attr in &item.attrs {
+            write!(s, " {attr}").unwrap();
+        }
+        match item.skipped {
+            Some(reason) => writeln!(s, ": skipped: {reason}").unwrap(),
+            None => writeln!(
+                s,
+                ": {} {}",
+                item.mutants,
+                if item.mutants == 1 {
+                    "mutant"
+                } else {
+                    "mutants"
+                }
+            )
+            .unwrap(),
+        }
+    }
+    Ok(s)
+}
+
+/// Find the names of structs and enums defined in this file that neither derive nor
+/// implement `Default`.
+///
+/// This only knows about this file, and matches by name, so a `Default` impl in another
+/// file isn't seen.
+fn types_without_default(syn_file: &syn::File) -> HashSet<String> {
+    #[derive(Default)]
+    struct DefaultVisitor {
+        defined: HashSet<String>,
+        defaulted: HashSet<String>,
+    }
+    impl DefaultVisitor {
+        fn type_defined(&mut self, ident: &syn::Ident, attrs: &[Attribute]) {
+            if attrs.iter().any(attr_derives_default) {
+                self.defaulted.insert(ident.to_string());
+            }
+            self.defined.insert(ident.to_string());
+        }
+    }
+    impl<'ast> Visit<'ast> for DefaultVisitor {
+        fn visit_item_struct(&mut self, i: &'ast syn::ItemStruct) {
+            self.type_defined(&i.ident, &i.attrs);
+            syn::visit::visit_item_struct(self, i);
+        }
+
+        fn visit_item_enum(&mut self, i: &'ast syn::ItemEnum) {
+            self.type_defined(&i.ident, &i.attrs);
+            syn::visit::visit_item_enum(self, i);
+        }
+
+        fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+            if let (Some((_, trait_path, _)), Type::Path(self_path)) = (&i.trait_, &*i.self_ty) {
+                if path_ends_with(trait_path, "Default") {
+                    if let Some(segment) = self_path.path.segments.last() {
+                        self.defaulted.insert(segment.ident.to_string());
+                    }
+                }
+            }
+            syn::visit::visit_item_impl(self, i);
+        }
+    }
+    let mut visitor = DefaultVisitor::default();
+    visitor.visit_file(syn_file);
+    visitor
+        .defined
+        .difference(&visitor.defaulted)
+        .cloned()
+        .collect()
+}
+
+/// Return the names used in array lengths in this file, like `SIZE` in `[u8; SIZE]`
+/// or `Self::SIZE` in `[0; Self::SIZE]`.
+///
+/// This is only matched by name, so a constant with the same name in a different type
+/// is also treated as a length.
+fn array_length_names(syn_file: &syn::File) -> HashSet<String> {
+    #[derive(Default)]
+    struct LengthVisitor {
+        in_length: bool,
+        names: HashSet<String>,
+    }
+    impl LengthVisitor {
+        fn visit_length(&mut self, len: &Expr) {
+            let in_length = std::mem::replace(&mut self.in_length, true);
+            self.visit_expr(len);
+            self.in_length = in_length;
+        }
+    }
+    impl<'ast> Visit<'ast> for LengthVisitor {
+        fn visit_type_array(&mut self, i: &'ast syn::TypeArray) {
+            self.visit_type(&i.elem);
+            self.visit_length(&i.len);
+        }
+
+        fn visit_expr_repeat(&mut self, i: &'ast syn::ExprRepeat) {
+            self.visit_expr(&i.expr);
+            self.visit_length(&i.len);
+        }
+
+        fn visit_expr_path(&mut self, i: &'ast syn::ExprPath) {
+            if self.in_length {
+                if let Some(segment) = i.path.segments.last() {
+                    self.names.insert(segment.ident.unraw().to_string());
+                }
+            }
+            syn::visit::visit_expr_path(self, i);
+        }
+    }
+    let mut visitor = LengthVisitor::default();
+    visitor.visit_file(syn_file);
+    visitor.names
+}
+
+/// Reorder mutants so that those in functions that look untested come first, keeping
+/// the order within each group.
+///
+/// This is a heuristic: a function looks tested if its name appears anywhere in a
+/// test function or `#[cfg(test)]` module in the same file. Tests in other files,
+/// such as integration tests in `tests/`, aren't seen.
+pub fn prioritize_untested(mutants: &mut [Mutant]) {
+    let mut names_by_file: HashMap<String, HashSet<String>> = HashMap::new();
+    mutants.sort_by_cached_key(|mutant| {
+        let names = names_by_file
+            .entry(mutant.source_file.tree_relative_slashes())
+            .or_insert_with(|| names_used_in_tests(&mutant.source_file.code));
+        // The last component of a name like `<impl Iterator for Counter>::next`.
+        let function_name = mutant
+            .function_name()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default();
+        names.contains(function_name)
+    });
+}
+
+/// Return all the identifiers used in test functions and `#[cfg(test)]` modules in
+/// this source code.
+fn names_used_in_tests(code: &str) -> HashSet<String> {
+    fn collect_idents(tokens: TokenStream, names: &mut HashSet<String>) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => {
+                    names.insert(ident.unraw().to_string());
+                }
+                TokenTree::Group(group) => collect_idents(group.stream(), names),
+                _ => (),
+            }
+        }
+    }
+    struct TestVisitor(HashSet<String>);
+    impl<'ast> Visit<'ast> for TestVisitor {
+        fn visit_item_fn(&mut self, i: &'ast ItemFn) {
+            if i.attrs.iter().any(attr_is_test) {
+                collect_idents(i.block.to_token_stream(), &mut self.0);
+            }
+        }
+
+        fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+            if i.attrs.iter().any(attr_is_cfg_test) {
+                collect_idents(i.to_token_stream(), &mut self.0);
+            } else {
+                syn::visit::visit_item_mod(self, i);
+            }
+        }
+    }
+    let Ok(syn_file) = syn::parse_str::<syn::File>(code) else {
+        return HashSet::new();
     };
+    let mut visitor = TestVisitor(HashSet::new());
     visitor.visit_file(&syn_file);
-    Ok((visitor.mutants, visitor.more_files))
+    visitor.0
 }
 
 /// `syn` visitor that recursively traverses the syntax tree, accumulating places
@@ -129,6 +528,9 @@ struct DiscoveryVisitor<'o> {
     /// The stack of namespaces we're currently inside.
     namespace_stack: Vec<String>,
 
+    /// The functions we're currently inside, innermost last.
+    fn_stack: Vec<FnContext>,
+
     /// Files discovered by `mod` statements.
     more_files: Vec<TreeRelativePathBuf>,
 
@@ -138,21 +540,160 @@ struct DiscoveryVisitor<'o> {
 
     /// Parsed error expressions, from the config file or command line.
     error_exprs: &'o [Expr],
+
+    /// Associated types defined in the `impl` block being visited, like `type Item = u32;`.
+    assoc_types: Vec<(syn::Ident, Type)>,
+
+    /// If the `impl` block being visited implements an operator trait like `Add`, the
+    /// type it's implemented for.
+    operator_impl_type: Option<Type>,
+
+    /// Names of structs and enums defined in this file that can't be constructed by
+    /// `Default::default()`.
+    types_without_default: HashSet<String>,
+
+    /// True if the file imports `anyhow::Result`, so that a plain `Result<T>` is
+    /// probably an `anyhow::Result`.
+    anyhow_result_imported: bool,
+
+    /// Names of constants used as array lengths in this file, like `SIZE` in `[u8; SIZE]`.
+    array_length_names: HashSet<String>,
+
+    /// If set, the items visited are recorded here, for `--dump-ast`.
+    dumped_items: Option<Vec<DumpedItem>>,
+
+    /// True if the binary expression about to be visited is an operand of an operator
+    /// that binds more tightly than `&&`, like `==`, so that replacing it by an `&&`
+    /// expression needs parentheses.
+    in_tight_operand: bool,
+}
+
+/// An item seen by the visitor, recorded for `--dump-ast`.
+struct DumpedItem {
+    kind: &'static str,
+    name: String,
+    line: usize,
+    /// The return type of a function, like `-> bool`.
+    return_type: Option<String>,
+    /// Attributes other than doc comments.
+    attrs: Vec<String>,
+    skipped: Option<SkipReason>,
+    /// The number of mutants discovered before this item.
+    first_mutant: usize,
+    /// The number of mutants generated within the item, if it wasn't skipped.
+    mutants: usize,
+}
+
+/// The name and return type of a function enclosing the code being visited.
+struct FnContext {
+    full_name: Arc<String>,
+    return_type: Arc<String>,
+    span: Span,
+    /// The number of `CollectionPush` mutants generated so far in this function.
+    push_deletions: usize,
+    /// The spans of struct literals returned by this function, whose fields can be set to
+    /// their defaults.
+    returned_structs: Vec<Span>,
 }
 
 impl<'o> DiscoveryVisitor<'o> {
-    fn collect_fn_mutants(&mut self, return_type: &ReturnType, span: &proc_macro2::Span) {
-        let full_function_name = Arc::new(self.namespace_stack.join("::"));
-        let return_type_str = Arc::new(return_type_to_string(return_type));
-        let mut new_mutants = return_type_replacements(return_type, self.error_exprs)
+    fn new(
+        root: &Utf8Path,
+        source_file: Arc<SourceFile>,
+        syn_file: &syn::File,
+        options: &'o Options,
+        error_exprs: &'o [Expr],
+    ) -> DiscoveryVisitor<'o> {
+        DiscoveryVisitor {
+            error_exprs,
+            fn_stack: Vec::new(),
+            more_files: Vec::new(),
+            mutants: Vec::new(),
+            namespace_stack: Vec::new(),
+            options,
+            root: root.to_owned(),
+            source_file,
+            assoc_types: Vec::new(),
+            operator_impl_type: None,
+            types_without_default: types_without_default(syn_file),
+            anyhow_result_imported: syn_file.items.iter().any(|item| match item {
+                syn::Item::Use(item_use) => use_tree_imports_anyhow_result(&item_use.tree, false),
+                _ => false,
+            }),
+            array_length_names: array_length_names(syn_file),
+            dumped_items: None,
+            in_tight_operand: false,
+        }
+    }
+
+    /// The error values to insert into a function with this return type.
+    ///
+    /// If no error values are configured and the function returns an `anyhow::Result`,
+    /// an error from `anyhow!` is used.
+    fn error_exprs_for(&self, return_type: &ReturnType) -> Cow<'o, [Expr]> {
+        if self.error_exprs.is_empty()
+            && returns_anyhow_result(return_type, self.anyhow_result_imported)
+        {
+            Cow::Owned(vec![syn::parse_quote! { ::anyhow::anyhow!("mutated") }])
+        } else {
+            Cow::Borrowed(self.error_exprs)
+        }
+    }
+
+    fn collect_fn_mutants(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        if !self.options.genre_enabled(Genre::FnValue) {
+            return;
+        }
+        let FnContext {
+            full_name: full_function_name,
+            return_type: return_type_str,
+            span: function_span,
+            ..
+        } = self.fn_stack.last().expect("inside a function");
+        let return_type = resolve_assoc_types(&sig.output, &self.assoc_types);
+        if self.operator_output_has_no_default(&return_type) {
+            debug!(
+                ?full_function_name,
+                "Skip operator whose output type can't be constructed by default"
+            );
+            return;
+        }
+        // A body that's just the same value as a replacement would make a mutant that
+        // does nothing.
+        let only_value = match &block.stmts[..] {
+            [syn::Stmt::Expr(expr, None)] => Some(tokens_to_pretty_string(expr)),
+            _ => None,
+        };
+        let span = block.brace_token.span.join();
+        let mut new_mutants = generic_return_replacements(&return_type, &sig.generics)
+            .unwrap_or_else(|| {
+                return_type_replacements(
+                    &return_type,
+                    &self.error_exprs_for(&return_type),
+                    &self.options.no_default_types,
+                )
+            })
             .into_iter()
-            .map(|rep| Mutant {
+            .filter(|rep| sig.constness.is_none() || tokens_are_const(rep))
+            .map(tokens_to_pretty_string)
+            .filter(|replacement| {
+                if only_value.as_ref() == Some(replacement) {
+                    debug!(?replacement, "Skip replacement equal to the function body");
+                    false
+                } else {
+                    true
+                }
+            })
+            .map(|replacement| Mutant {
                 source_file: Arc::clone(&self.source_file),
-                function_name: Arc::clone(&full_function_name),
-                return_type: Arc::clone(&return_type_str),
-                replacement: tokens_to_pretty_string(&rep),
+                function_name: Arc::clone(full_function_name),
+                return_type: Arc::clone(return_type_str),
+                replacement,
+                function_span: *function_span,
                 span: span.into(),
                 genre: Genre::FnValue,
+                swapped_patterns: None,
+                id: String::new(),
             })
             .collect_vec();
         if new_mutants.is_empty() {
@@ -161,730 +702,5175 @@ impl<'o> DiscoveryVisitor<'o> {
                 ?return_type_str,
                 "No mutants generated for this return type"
             );
+        } else if !self.options.mutate_diverging_fns && block_diverges(block) {
+            debug!(
+                ?full_function_name,
+                "Skip replacing the body of a function that always diverges"
+            );
         } else {
             self.mutants.append(&mut new_mutants);
         }
     }
 
-    /// Call a function with a namespace pushed onto the stack.
+    /// True if this is an operator method like `Add::add`, and its resolved return type
+    /// is defined in this file without a `Default` impl, so the only replacement,
+    /// `Default::default()`, wouldn't build.
+    fn operator_output_has_no_default(&self, return_type: &ReturnType) -> bool {
+        let (Some(impl_type), ReturnType::Type(_, output)) =
+            (&self.operator_impl_type, return_type)
+        else {
+            return false;
+        };
+        let output = if type_is_self(output) {
+            impl_type
+        } else {
+            output
+        };
+        match output {
+            Type::Path(syn::TypePath { qself: None, path }) => {
+                path.segments.last().map_or(false, |s| {
+                    self.types_without_default.contains(&s.ident.to_string())
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Collect mutants that replace some expression within the current function.
     ///
-    /// This is used when recursively descending into a namespace.
-    fn in_namespace<F, T>(&mut self, name: &str, f: F) -> T
-    where
-        F: FnOnce(&mut Self) -> T,
-    {
-        self.namespace_stack.push(name.to_owned());
-        let r = f(self);
-        assert_eq!(self.namespace_stack.pop().unwrap(), name);
-        r
+    /// Code outside of any function, such as in a `const` initializer, is not mutated.
+    fn collect_expr_mutants(
+        &mut self,
+        span: proc_macro2::Span,
+        replacements: Vec<TokenStream>,
+        genre: Genre,
+    ) {
+        for rep in replacements {
+            self.collect_text_mutant(span.into(), tokens_to_pretty_string(&rep), genre);
+        }
     }
-}
 
-impl<'ast> Visit<'ast> for DiscoveryVisitor<'_> {
-    /// Visit top-level `fn foo()`.
-    fn visit_item_fn(&mut self, i: &'ast ItemFn) {
-        let function_name = tokens_to_pretty_string(&i.sig.ident);
-        let _span = trace_span!(
-            "fn",
-            line = i.sig.fn_token.span.start().line,
-            name = function_name
-        )
-        .entered();
-        if fn_sig_excluded(&i.sig) || attrs_excluded(&i.attrs) || block_is_empty(&i.block) {
-            return;
+    /// Collect a mutant that replaces a region within the current function by some text,
+    /// which need not be a complete expression or balanced tokens.
+    fn collect_text_mutant(
+        &mut self,
+        span: Span,
+        replacement: String,
+        genre: Genre,
+    ) -> Option<&mut Mutant> {
+        if !self.options.genre_enabled(genre) {
+            return None;
         }
-        self.in_namespace(&function_name, |self_| {
-            self_.collect_fn_mutants(&i.sig.output, &i.block.brace_token.span.join());
-            syn::visit::visit_item_fn(self_, i);
+        let FnContext {
+            full_name,
+            return_type,
+            span: function_span,
+            ..
+        } = self.fn_stack.last()?;
+        self.mutants.push(Mutant {
+            source_file: Arc::clone(&self.source_file),
+            function_name: Arc::clone(full_name),
+            return_type: Arc::clone(return_type),
+            replacement,
+            function_span: *function_span,
+            span,
+            genre,
+            swapped_patterns: None,
+            id: String::new(),
         });
+        self.mutants.last_mut()
     }
 
-    /// Visit `fn foo()` within an `impl`.
-    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
-        // Don't look inside constructors (called "new") because there's often no good
-        // alternative.
-        let function_name = tokens_to_pretty_string(&i.sig.ident);
-        let _span = trace_span!(
-            "fn",
-            line = i.sig.fn_token.span.start().line,
-            name = function_name
-        )
-        .entered();
-        if fn_sig_excluded(&i.sig)
-            || attrs_excluded(&i.attrs)
-            || i.sig.ident == "new"
-            || block_is_empty(&i.block)
+    /// True if an item with these attributes, such as a function, impl, or module,
+    /// should not be mutated.
+    fn item_attrs_excluded(&self, attrs: &[Attribute]) -> bool {
+        self.item_attrs_skip_reason(attrs).is_some()
+    }
+
+    /// Say why an item with these attributes should not be mutated, if it shouldn't.
+    ///
+    /// This is like [attrs_skip_reason], but also skips `#[doc(hidden)]` items if the
+    /// options say so, and items whose `cfg` is false in the profile being built.
+    fn item_attrs_skip_reason(&self, attrs: &[Attribute]) -> Option<SkipReason> {
+        if let Some(reason) = attrs_skip_reason(attrs) {
+            Some(reason)
+        } else if self.options.exclude_doc_hidden && attrs.iter().any(attr_is_doc_hidden) {
+            Some(SkipReason::DocHidden)
+        } else if attrs
+            .iter()
+            .any(|attr| attr_is_cfg_false(attr, self.options.debug_assertions()))
         {
-            return;
+            Some(SkipReason::CfgFalse)
+        } else {
+            None
         }
-        self.in_namespace(&function_name, |self_| {
-            self_.collect_fn_mutants(&i.sig.output, &i.block.brace_token.span.join());
-            syn::visit::visit_impl_item_fn(self_, i)
+    }
+
+    /// Record an item for `--dump-ast`, if that was requested, returning its index so
+    /// that the mutants generated within it can be counted by [Self::count_dumped_mutants].
+    fn dump_item(
+        &mut self,
+        kind: &'static str,
+        name: &str,
+        span: proc_macro2::Span,
+        attrs: &[Attribute],
+        return_type: Option<&ReturnType>,
+        skipped: Option<SkipReason>,
+    ) -> Option<usize> {
+        let first_mutant = self.mutants.len();
+        let full_name = self
+            .namespace_stack
+            .iter()
+            .map(String::as_str)
+            .chain([name])
+            .join("::");
+        let items = self.dumped_items.as_mut()?;
+        items.push(DumpedItem {
+            kind,
+            name: full_name,
+            line: span.start().line,
+            return_type: return_type
+                .map(return_type_to_string)
+                .filter(|s| !s.is_empty()),
+            attrs: attrs
+                .iter()
+                .filter(|attr| !attr.path().is_ident("doc") || attr_is_doc_hidden(attr))
+                .map(tokens_to_pretty_string)
+                .collect(),
+            skipped,
+            first_mutant,
+            mutants: 0,
         });
+        Some(items.len() - 1)
     }
 
-    /// Visit `impl Foo { ...}` or `impl Debug for Foo { ... }`.
-    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
-        if attrs_excluded(&i.attrs) {
+    /// Count the mutants generated since an item was recorded by [Self::dump_item].
+    fn count_dumped_mutants(&mut self, index: Option<usize>) {
+        if let (Some(items), Some(index)) = (self.dumped_items.as_mut(), index) {
+            let item = &mut items[index];
+            item.mutants = self.mutants.len() - item.first_mutant;
+        }
+    }
+
+    /// Collect mutants that flip a final `Ok(x)` or `Err(e)` in a function returning
+    /// `Result`, or make an early `return Err(e)` return `Ok` instead.
+    ///
+    /// `Ok(x)` is replaced by `Err` with each configured error value, so there are no
+    /// such mutants unless error values are configured.
+    fn collect_result_flip_mutants(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let return_type = &sig.output;
+        let ReturnType::Type(_, type_) = return_type else {
             return;
+        };
+        match &**type_ {
+            Type::Path(syn::TypePath { path, .. }) if path_ends_with(path, "Result") => (),
+            _ => return,
         }
-        let type_name = tokens_to_pretty_string(&i.self_ty);
-        let name = if let Some((_, trait_path, _)) = &i.trait_ {
-            let trait_name = &trait_path.segments.last().unwrap().ident;
-            if trait_name == "Default" {
-                // Can't think of how to generate a viable different default.
-                return;
+        if let Some(syn::Stmt::Expr(tail, None)) = block.stmts.last() {
+            let mut replacements = match result_variant(tail) {
+                Some("Ok") => self
+                    .error_exprs_for(return_type)
+                    .iter()
+                    .map(|error_expr| quote! { Err(#error_expr) })
+                    .collect_vec(),
+                Some("Err") => vec![quote! { Ok(Default::default()) }],
+                _ => Vec::new(),
+            };
+            if sig.constness.is_some() {
+                replacements.retain(tokens_are_const);
             }
-            format!("<impl {trait_name} for {type_name}>")
-        } else {
-            type_name
-        };
-        self.in_namespace(&name, |v| syn::visit::visit_item_impl(v, i));
+            self.collect_expr_mutants(tail.span(), replacements, Genre::ResultFlip);
+        }
+        // Early returns of errors, often from guards that reject bad input, are made to
+        // succeed instead.
+        if sig.constness.is_none() {
+            for early_return in early_error_returns(block) {
+                self.collect_expr_mutants(
+                    early_return.span(),
+                    vec![quote! { return Ok(Default::default()) }],
+                    Genre::ResultFlip,
+                );
+            }
+        }
     }
 
-    /// Visit `mod foo { ... }` or `mod foo;`.
-    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
-        let mod_name = &node.ident.unraw().to_string();
-        let _span = trace_span!(
-            "mod",
-            line = node.mod_token.span.start().line,
-            name = mod_name
-        )
-        .entered();
-        if attrs_excluded(&node.attrs) {
-            trace!("mod {:?} excluded by attrs", node.ident,);
+    /// Remember the struct literals returned by the current function, so that their
+    /// fields can be mutated when they're visited.
+    ///
+    /// `Default::default()` can't be called in a `const fn`, so nothing is noted there.
+    fn note_returned_structs(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        if sig.constness.is_some() || !self.options.genre_enabled(Genre::StructFieldDefault) {
             return;
         }
-        // If there's no content in braces, then this is a `mod foo;`
-        // statement referring to an external file. We find the file name
-        // then remember to visit it later.
-        //
-        // Both the current module and the included sub-module can be in
-        // either style: `.../foo.rs` or `.../foo/mod.rs`.
-        //
-        // If the current file ends with `/mod.rs`, then sub-modules
-        // will be in the same directory as this file. Otherwise, this is
-        // `/foo.rs` and sub-modules will be in `foo/`.
-        //
-        // Having determined the directory then we can look for either
-        // `foo.rs` or `foo/mod.rs`.
-        if node.content.is_none() {
-            let my_path: &Utf8Path = self.source_file.tree_relative_path().as_ref();
-            // Maybe matching on the name here is no the right approach and
-            // we should instead remember how this file was found?
-            let dir = if my_path.ends_with("mod.rs")
-                || my_path.ends_with("lib.rs")
-                || my_path.ends_with("main.rs")
-            {
-                my_path.parent().expect("mod path has no parent").to_owned()
-            } else {
-                my_path.with_extension("")
-            };
-            let mut found = false;
-            let mut tried_paths = Vec::new();
-            for &ext in &[".rs", "/mod.rs"] {
-                let relative_path = TreeRelativePathBuf::new(dir.join(format!("{mod_name}{ext}")));
-                let full_path = relative_path.within(&self.root);
-                if full_path.is_file() {
-                    trace!("found submodule in {full_path}");
-                    self.more_files.push(relative_path);
-                    found = true;
-                    break;
-                } else {
-                    tried_paths.push(full_path);
-                }
+        let returned_structs = returned_struct_literals(block)
+            .into_iter()
+            .map(|expr| expr.span().into())
+            .collect();
+        self.fn_stack
+            .last_mut()
+            .expect("inside a function")
+            .returned_structs = returned_structs;
+    }
+
+    /// Collect mutants that each delete one statement from the body of a function
+    /// returning `()`, to show which of its side effects are not tested.
+    ///
+    /// `let` statements and items aren't deleted, because later code probably depends
+    /// on them, and neither is a final expression with no semicolon. If there's only
+    /// one statement, deleting it is the same as replacing the body with `()`, so
+    /// nothing is generated.
+    fn collect_statement_deletions(&mut self, return_type: &ReturnType, block: &syn::Block) {
+        if !self.options.genre_enabled(Genre::StatementDelete) {
+            return;
+        }
+        match return_type {
+            ReturnType::Default => (),
+            ReturnType::Type(_, type_) => match &**type_ {
+                Type::Tuple(TypeTuple { elems, .. }) if elems.is_empty() => (),
+                _ => return,
+            },
+        }
+        if block.stmts.len() < 2 {
+            return;
+        }
+        let last_index = block.stmts.len() - 1;
+        let deletable = block
+            .stmts
+            .iter()
+            .enumerate()
+            .filter(|(i, stmt)| match stmt {
+                syn::Stmt::Expr(_, semi) => semi.is_some() || *i != last_index,
+                syn::Stmt::Macro(stmt_macro) => !attrs_excluded(&stmt_macro.attrs),
+                syn::Stmt::Local(_) | syn::Stmt::Item(_) => false,
+            })
+            .map(|(_, stmt)| stmt)
+            .collect_vec();
+        if deletable.len() > MAX_STATEMENT_DELETIONS {
+            debug!(
+                n_statements = deletable.len(),
+                "Only deleting the first {MAX_STATEMENT_DELETIONS} statements"
+            );
+        }
+        for stmt in deletable.into_iter().take(MAX_STATEMENT_DELETIONS) {
+            self.collect_expr_mutants(stmt.span(), vec![quote! {}], Genre::StatementDelete);
+        }
+    }
+
+    /// Collect a mutant that deletes a statement adding to a collection, like `v.push(x);`,
+    /// to show whether anything checks the contents of the collection.
+    fn collect_push_deletion(&mut self, stmt: &syn::Stmt) {
+        if !self.options.genre_enabled(Genre::CollectionPush) {
+            return;
+        }
+        let Some(fn_context) = self.fn_stack.last_mut() else {
+            return;
+        };
+        if fn_context.push_deletions >= MAX_PUSH_DELETIONS {
+            debug!("Only deleting the first {MAX_PUSH_DELETIONS} collection pushes");
+            return;
+        }
+        fn_context.push_deletions += 1;
+        self.collect_expr_mutants(stmt.span(), vec![quote! {}], Genre::CollectionPush);
+    }
+
+    /// Collect mutants that swap the patterns of two adjacent arms of a `match`, keeping
+    /// their guards and bodies in place, to check that each case is mapped to the
+    /// right result.
+    ///
+    /// Only literal and unit patterns like `3`, `None`, or `Color::Red` are swapped,
+    /// because they bind no variables, and arms whose bodies are identical are
+    /// skipped because swapping them changes nothing.
+    fn collect_match_arm_swaps(&mut self, i: &syn::ExprMatch) {
+        if !self.options.genre_enabled(Genre::MatchArmSwap) {
+            return;
+        }
+        let swaps = i
+            .arms
+            .iter()
+            .tuple_windows()
+            .filter(|(a, b)| {
+                pattern_is_swappable(&a.pat)
+                    && pattern_is_swappable(&b.pat)
+                    && !attrs_excluded(&a.attrs)
+                    && !attrs_excluded(&b.attrs)
+                    && a.body.to_token_stream().to_string() != b.body.to_token_stream().to_string()
+            })
+            .collect_vec();
+        if swaps.len() > MAX_MATCH_ARM_SWAPS {
+            debug!(
+                n_swaps = swaps.len(),
+                "Only swapping the first {MAX_MATCH_ARM_SWAPS} pairs of match arms"
+            );
+        }
+        for (a, b) in swaps.into_iter().take(MAX_MATCH_ARM_SWAPS) {
+            let a_span = Span::from(a.pat.span());
+            let b_span = Span::from(b.pat.span());
+            // The text between the patterns, including the first arm's guard and body,
+            // is kept as written.
+            let code = &self.source_file.code;
+            let a_pattern = extract_region(code, &a_span.start, &a_span.end);
+            let b_pattern = extract_region(code, &b_span.start, &b_span.end);
+            let replacement = [
+                b_pattern.as_str(),
+                &extract_region(code, &a_span.end, &b_span.start),
+                &a_pattern,
+            ]
+            .concat();
+            let span = Span {
+                start: a_span.start,
+                end: b_span.end,
+            };
+            let one_line = |pattern: &str| pattern.lines().map(str::trim).join(" ");
+            if let Some(mutant) = self.collect_text_mutant(span, replacement, Genre::MatchArmSwap) {
+                mutant.swapped_patterns = Some((one_line(&a_pattern), one_line(&b_pattern)));
             }
-            if !found {
-                warn!(
-                    "{path}:{line}: referent of mod {mod_name:#?} not found: tried {tried_paths:?}",
-                    path = self.source_file.tree_relative_path,
-                    line = node.mod_token.span.start().line,
-                );
+        }
+    }
+
+    /// Collect a mutant that replaces the body of a catch-all `_ =>` arm with
+    /// `unreachable!()`, to check that some test reaches the default case.
+    ///
+    /// Arms with a guard aren't really catch-all, and bodies that already diverge, like
+    /// `_ => panic!()`, are left alone.
+    fn collect_match_wildcard_mutant(&mut self, i: &syn::ExprMatch) {
+        let Some((index, arm)) = i
+            .arms
+            .iter()
+            .enumerate()
+            .find(|(_, arm)| matches!(arm.pat, syn::Pat::Wild(_)) && arm.guard.is_none())
+        else {
+            return;
+        };
+        if attrs_excluded(&arm.attrs) {
+            return;
+        }
+        let body = &arm.body;
+        let body_block: syn::Block = syn::parse_quote! { { #body } };
+        if block_diverges(&body_block) {
+            return;
+        }
+        // A block body needs no comma after it, but a macro call does if more arms follow.
+        let replacement = if arm.comma.is_none() && index + 1 < i.arms.len() {
+            "{ unreachable!() }"
+        } else {
+            "unreachable!()"
+        };
+        self.collect_text_mutant(
+            body.span().into(),
+            replacement.to_owned(),
+            Genre::MatchArmSwap,
+        );
+    }
+
+    /// Collect a mutant that changes the operator of a binary expression between `&&`
+    /// and `&`, or `||` and `|`, keeping the operands as written.
+    ///
+    /// Parentheses are added where precedence requires: for example `a == 1 && f(b)`
+    /// becomes `(a == 1) & f(b)`, and `x == a & f(b)` becomes `x == (a && f(b))`.
+    fn collect_short_circuit_swap(
+        &mut self,
+        i: &syn::ExprBinary,
+        new_op: &str,
+        in_tight_operand: bool,
+    ) {
+        let code = &self.source_file.code;
+        let operand = |expr: &Expr| {
+            let span = Span::from(expr.span());
+            let text = extract_region(code, &span.start, &span.end);
+            if new_op.len() == 1 && operand_needs_parens(expr) {
+                format!("({text})")
+            } else {
+                text
             }
+        };
+        let mut replacement = format!("{} {new_op} {}", operand(&i.left), operand(&i.right));
+        if new_op.len() == 2 && in_tight_operand {
+            replacement = format!("({replacement})");
         }
-        self.in_namespace(mod_name, |v| syn::visit::visit_item_mod(v, node));
+        self.collect_text_mutant(i.span().into(), replacement, Genre::BinaryOperator);
     }
-}
 
-/// Generate replacement text for a function based on its return type.
-fn return_type_replacements(return_type: &ReturnType, error_exprs: &[Expr]) -> Vec<TokenStream> {
-    match return_type {
-        ReturnType::Default => vec![quote! { () }],
-        ReturnType::Type(_rarrow, type_) => type_replacements(type_, error_exprs),
+    /// Collect mutants that make a `while let` loop stop before running its body, or
+    /// after running it once, to check that the effects of later iterations are tested.
+    ///
+    /// The loop's condition is still evaluated once, so for example one item is taken
+    /// from an iterator in `while let Some(x) = iter.next()`.
+    fn collect_while_let_breaks(&mut self, i: &syn::ExprWhile) {
+        self.collect_expr_mutants(
+            i.body.span(),
+            vec![quote! { { break } }],
+            Genre::WhileLetBreak,
+        );
+        // A final expression with no semicolon needs one before the added `break`.
+        let needs_semicolon = match i.body.stmts.last() {
+            Some(syn::Stmt::Expr(_, semi)) => semi.is_none(),
+            Some(syn::Stmt::Macro(stmt_macro)) => stmt_macro.semi_token.is_none(),
+            _ => false,
+        };
+        let replacement = if needs_semicolon {
+            "; break }"
+        } else {
+            "break }"
+        };
+        self.collect_text_mutant(
+            i.body.brace_token.span.close().into(),
+            replacement.to_owned(),
+            Genre::WhileLetBreak,
+        );
     }
-}
 
-/// Generate some values that we hope are reasonable replacements for a type.
-///
-/// This is really the heart of cargo-mutants.
-fn type_replacements(type_: &Type, error_exprs: &[Expr]) -> Vec<TokenStream> {
-    let mut reps = Vec::new();
-    match type_ {
-        Type::Path(syn::TypePath { path, .. }) => {
-            // dbg!(&path);
-            if path.is_ident("bool") {
-                reps.push(quote! { true });
-                reps.push(quote! { false });
-            } else if path.is_ident("String") {
-                reps.push(quote! { String::new() });
-                reps.push(quote! { "xyzzy".into() });
-            } else if path_is_unsigned(path) {
-                reps.push(quote! { 0 });
-                reps.push(quote! { 1 });
-            } else if path_is_signed(path) {
-                reps.push(quote! { 0 });
-                reps.push(quote! { 1 });
-                reps.push(quote! { -1 });
-            } else if path_is_nonzero_signed(path) {
-                reps.extend([quote! { 1 }, quote! { -1 }]);
-            } else if path_is_nonzero_unsigned(path) {
-                reps.push(quote! { 1 });
-            } else if path_is_float(path) {
-                reps.push(quote! { 0.0 });
-                reps.push(quote! { 1.0 });
-                reps.push(quote! { -1.0 });
-            } else if path_ends_with(path, "Result") {
-                if let Some(ok_type) = result_ok_type(path) {
-                    reps.extend(
-                        type_replacements(ok_type, error_exprs)
-                            .into_iter()
-                            .map(|rep| {
-                                quote! { Ok(#rep) }
-                            }),
-                    );
-                } else {
-                    // A result but with no type arguments, like `fmt::Result`; hopefully
-                    // the Ok value can be constructed with Default.
-                    reps.push(quote! { Ok(Default::default()) });
-                }
-                reps.extend(error_exprs.iter().map(|error_expr| {
-                    quote! { Err(#error_expr) }
-                }));
-            } else if let Some(boxed_type) = match_first_type_arg(path, "Box") {
-                reps.extend(
-                    type_replacements(boxed_type, error_exprs)
-                        .into_iter()
-                        .map(|rep| {
-                            quote! { Box::new(#rep) }
-                        }),
-                )
-            } else if let Some(some_type) = match_first_type_arg(path, "Option") {
-                reps.push(quote! { None });
-                reps.extend(
-                    type_replacements(some_type, error_exprs)
-                        .into_iter()
-                        .map(|rep| {
-                            quote! { Some(#rep) }
-                        }),
+    /// Collect mutants that change the literals in the initializer of a lazily
+    /// initialized static.
+    ///
+    /// The static is treated like a function for the purposes of naming the mutants.
+    fn collect_static_initializer_mutants(
+        &mut self,
+        ident: &syn::Ident,
+        init: &Expr,
+        span: proc_macro2::Span,
+    ) {
+        if !self.options.genre_enabled(Genre::LazyStatic) {
+            return;
+        }
+        let name = ident.unraw().to_string();
+        self.in_function(&name, &ReturnType::Default, span, |self_| {
+            for lit in expr_literals(init) {
+                self_.collect_expr_mutants(
+                    lit.span(),
+                    literal_replacements(&lit),
+                    Genre::LazyStatic,
                 );
-            } else if let Some(boxed_type) = match_first_type_arg(path, "Vec") {
-                // Generate an empty Vec, and then a one-element vec for every recursive
-                // value.
-                reps.push(quote! { vec![] });
-                reps.extend(
-                    type_replacements(boxed_type, error_exprs)
-                        .into_iter()
-                        .map(|rep| {
-                            quote! { vec![#rep] }
-                        }),
-                )
-            } else if let Some(inner_type) = match_first_type_arg(path, "Arc") {
-                // TODO: Ideally we should use the path without relying on it being
-                // imported, but we must strip or rewrite the arguments, so that
-                // `std::sync::Arc<String>` becomes either `std::sync::Arc::<String>::new`
-                // or at least `std::sync::Arc::new`. Similarly for other types.
-                reps.extend(
-                    type_replacements(inner_type, error_exprs)
-                        .into_iter()
-                        .map(|rep| {
-                            quote! { Arc::new(#rep) }
-                        }),
-                )
-            } else {
-                reps.push(quote! { Default::default() });
             }
+        });
+    }
+
+    /// Call a function with a function context pushed on the stack, and with its name
+    /// pushed onto the namespace stack.
+    fn in_function<F, T>(
+        &mut self,
+        name: &str,
+        return_type: &ReturnType,
+        span: proc_macro2::Span,
+        f: F,
+    ) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        self.in_namespace(name, |self_| {
+            self_.fn_stack.push(FnContext {
+                full_name: Arc::new(self_.namespace_stack.join("::")),
+                return_type: Arc::new(return_type_to_string(return_type)),
+                span: span.into(),
+                push_deletions: 0,
+                returned_structs: Vec::new(),
+            });
+            let r = f(self_);
+            self_
+                .fn_stack
+                .pop()
+                .expect("function context is on the stack");
+            r
+        })
+    }
+
+    /// Call a function with a namespace pushed onto the stack.
+    ///
+    /// This is used when recursively descending into a namespace.
+    fn in_namespace<F, T>(&mut self, name: &str, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        self.namespace_stack.push(name.to_owned());
+        let r = f(self);
+        assert_eq!(self.namespace_stack.pop().unwrap(), name);
+        r
+    }
+}
+
+impl<'ast> Visit<'ast> for DiscoveryVisitor<'_> {
+    /// Visit top-level `fn foo()`.
+    fn visit_item_fn(&mut self, i: &'ast ItemFn) {
+        let function_name = tokens_to_pretty_string(&i.sig.ident);
+        let _span = trace_span!(
+            "fn",
+            line = i.sig.fn_token.span.start().line,
+            name = function_name
+        )
+        .entered();
+        let skipped = self
+            .item_attrs_skip_reason(&i.attrs)
+            .or_else(|| fn_sig_skip_reason(&i.sig, self.options))
+            .or_else(|| block_is_empty(&i.block).then_some(SkipReason::EmptyBody));
+        let dumped = self.dump_item(
+            "fn",
+            &function_name,
+            i.sig.fn_token.span,
+            &i.attrs,
+            Some(&i.sig.output),
+            skipped,
+        );
+        if skipped.is_some() {
+            return;
         }
-        Type::Array(TypeArray { elem, len, .. }) => reps.extend(
-            // Generate arrays that repeat each replacement value however many times.
-            // In principle we could generate combinations, but that might get very
-            // large, and values like "all zeros" and "all ones" seem likely to catch
-            // lots of things.
-            type_replacements(elem, error_exprs)
+        self.in_function(&function_name, &i.sig.output, i.span(), |self_| {
+            self_.collect_fn_mutants(&i.sig, &i.block);
+            self_.collect_result_flip_mutants(&i.sig, &i.block);
+            self_.collect_statement_deletions(&i.sig.output, &i.block);
+            self_.note_returned_structs(&i.sig, &i.block);
+            syn::visit::visit_item_fn(self_, i);
+        });
+        self.count_dumped_mutants(dumped);
+    }
+
+    /// Visit `fn foo()` within an `impl`.
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        // Don't look inside constructors (called "new") because there's often no good
+        // alternative.
+        let function_name = tokens_to_pretty_string(&i.sig.ident);
+        let _span = trace_span!(
+            "fn",
+            line = i.sig.fn_token.span.start().line,
+            name = function_name
+        )
+        .entered();
+        let skipped = self
+            .item_attrs_skip_reason(&i.attrs)
+            .or_else(|| fn_sig_skip_reason(&i.sig, self.options))
+            .or_else(|| (i.sig.ident == "new").then_some(SkipReason::Constructor))
+            .or_else(|| block_is_empty(&i.block).then_some(SkipReason::EmptyBody));
+        let dumped = self.dump_item(
+            "fn",
+            &function_name,
+            i.sig.fn_token.span,
+            &i.attrs,
+            Some(&i.sig.output),
+            skipped,
+        );
+        if skipped.is_some() {
+            return;
+        }
+        self.in_function(&function_name, &i.sig.output, i.span(), |self_| {
+            self_.collect_fn_mutants(&i.sig, &i.block);
+            self_.collect_result_flip_mutants(&i.sig, &i.block);
+            self_.collect_statement_deletions(&i.sig.output, &i.block);
+            self_.note_returned_structs(&i.sig, &i.block);
+            syn::visit::visit_impl_item_fn(self_, i)
+        });
+        self.count_dumped_mutants(dumped);
+    }
+
+    /// Visit `const MAX: usize = 100;` within an `impl`.
+    fn visit_impl_item_const(&mut self, i: &'ast syn::ImplItemConst) {
+        let name = i.ident.unraw().to_string();
+        let skipped = self.item_attrs_skip_reason(&i.attrs);
+        let dumped = self.dump_item("const", &name, i.ident.span(), &i.attrs, None, skipped);
+        if skipped.is_some() {
+            return;
+        }
+        let used_as_length = self.array_length_names.contains(&name);
+        let reps = const_value_replacements(&i.ty, &i.expr, used_as_length);
+        if !reps.is_empty() {
+            self.in_function(&name, &ReturnType::Default, i.span(), |self_| {
+                self_.collect_expr_mutants(i.expr.span(), reps, Genre::AssocConst);
+            });
+        }
+        syn::visit::visit_impl_item_const(self, i);
+        self.count_dumped_mutants(dumped);
+    }
+
+    /// Visit a method call like `opt.unwrap_or(0)`.
+    fn visit_expr_method_call(&mut self, i: &'ast ExprMethodCall) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        if let Some(default_lit) = literal_default_argument(i) {
+            let reps = literal_replacements(default_lit);
+            self.collect_expr_mutants(default_lit.span(), reps, Genre::DefaultValue);
+        }
+        if let Some(swapped) = swapped_overflow_method(&i.method) {
+            self.collect_expr_mutants(
+                i.method.span(),
+                vec![swapped.into_token_stream()],
+                Genre::OverflowMethod,
+            );
+        }
+        if let Some(value) = bool_then_value(i) {
+            self.collect_expr_mutants(
+                i.span(),
+                vec![quote! { Some(#value) }, quote! { None }],
+                Genre::BoolThen,
+            );
+        }
+        if let Some(body) = predicate_closure_body(i) {
+            // A closure that already returns a constant isn't replaced by the same value.
+            let original = body.to_token_stream().to_string();
+            let reps = [quote! { true }, quote! { false }]
                 .into_iter()
-                .map(|r| quote! { [ #r; #len ] }),
-        ),
-        Type::Reference(syn::TypeReference {
-            mutability: None,
-            elem,
-            ..
-        }) => match &**elem {
-            Type::Path(path) if path.path.is_ident("str") => {
-                reps.push(quote! { "" });
-                reps.push(quote! { "xyzzy" });
+                .filter(|rep| rep.to_string() != original)
+                .collect();
+            self.collect_expr_mutants(body.span(), reps, Genre::PredicateClosure);
+        }
+        if i.args.is_empty() && i.turbofish.is_none() {
+            let receiver = &i.receiver;
+            let swapped = match i.method.to_string().as_str() {
+                "ok" => Some(quote! { #receiver.err() }),
+                "err" => Some(quote! { #receiver.ok() }),
+                _ => None,
+            };
+            if let Some(swapped) = swapped {
+                self.collect_expr_mutants(
+                    i.span(),
+                    vec![swapped, quote! { None }],
+                    Genre::ResultToOption,
+                );
+            }
+        }
+        if let Some((target, replacement)) = collect_target_replacement(i) {
+            self.collect_expr_mutants(target.span(), vec![replacement], Genre::CollectTarget);
+        }
+        syn::visit::visit_expr_method_call(self, i);
+    }
+
+    /// Visit a statement, deleting it if it's a call like `mem::swap(&mut a, &mut b);`
+    /// or `v.push(x);`.
+    fn visit_stmt(&mut self, i: &'ast syn::Stmt) {
+        if let syn::Stmt::Expr(Expr::MethodCall(call), Some(_semi)) = i {
+            if !attrs_excluded(&call.attrs) && is_collection_push(call) {
+                self.collect_push_deletion(i);
+            }
+        }
+        if let syn::Stmt::Expr(Expr::Call(call), Some(_semi)) = i {
+            if !attrs_excluded(&call.attrs) && mem_function(call).is_some() {
+                self.collect_expr_mutants(i.span(), vec![quote! {}], Genre::MemCall);
+                // Visit the arguments, but don't also replace the call by a value.
+                syn::visit::visit_expr_call(self, call);
+                return;
+            }
+        }
+        syn::visit::visit_stmt(self, i);
+    }
+
+    /// Visit a binary operation like `a && b.is_empty()`.
+    fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        let in_tight_operand = std::mem::take(&mut self.in_tight_operand);
+        if let Some(new_op) = short_circuit_swap(i) {
+            self.collect_short_circuit_swap(i, new_op, in_tight_operand);
+        }
+        let tight =
+            binary_precedence(&i.op) > binary_precedence(&syn::BinOp::And(Default::default()));
+        self.in_tight_operand = tight && matches!(*i.left, Expr::Binary(_));
+        self.visit_expr(&i.left);
+        self.in_tight_operand = tight && matches!(*i.right, Expr::Binary(_));
+        self.visit_expr(&i.right);
+        self.in_tight_operand = false;
+    }
+
+    /// Visit a function call like `mem::take(&mut self.buf)`.
+    fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        // Replace the call by a value of the same type that leaves the destination
+        // unchanged.
+        let replacement = match (mem_function(i), i.args.len()) {
+            (Some("take"), 1) => Some(quote! { Default::default() }),
+            (Some("replace"), 2) => Some(i.args[1].to_token_stream()),
+            (Some("swap"), 2) => Some(quote! { () }),
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            self.collect_expr_mutants(i.span(), vec![replacement], Genre::MemCall);
+        }
+        if let Some(lit) = duration_literal_argument(i) {
+            self.collect_expr_mutants(
+                lit.span(),
+                duration_literal_replacements(lit),
+                Genre::DurationLiteral,
+            );
+        }
+        syn::visit::visit_expr_call(self, i);
+    }
+
+    /// Visit `if cond { a } else { b }`.
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        let reps = if_branch_replacements(i);
+        if !reps.is_empty() {
+            self.collect_expr_mutants(i.span(), reps, Genre::IfBranch);
+        }
+        if is_return_guard(i) {
+            // Make the guard always return early, or never.
+            let reps = [quote! { true }, quote! { false }]
+                .into_iter()
+                .filter(|rep| rep.to_string() != i.cond.to_token_stream().to_string())
+                .collect();
+            self.collect_expr_mutants(i.cond.span(), reps, Genre::IfBranch);
+        } else if let Some(field) = condition_field(&i.cond) {
+            self.collect_expr_mutants(
+                field.span(),
+                vec![quote! { true }, quote! { false }],
+                Genre::IfBranch,
+            );
+        }
+        syn::visit::visit_expr_if(self, i);
+    }
+
+    /// Visit `while cond { ... }`, including `while let`.
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        if matches!(*i.cond, Expr::Let(_)) {
+            self.collect_while_let_breaks(i);
+        } else if let Some(field) = condition_field(&i.cond) {
+            self.collect_expr_mutants(
+                field.span(),
+                vec![quote! { true }, quote! { false }],
+                Genre::IfBranch,
+            );
+        }
+        syn::visit::visit_expr_while(self, i);
+    }
+
+    /// Visit `match x { ... }`.
+    fn visit_expr_match(&mut self, i: &'ast syn::ExprMatch) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        self.collect_match_arm_swaps(i);
+        self.collect_match_wildcard_mutant(i);
+        syn::visit::visit_expr_match(self, i);
+    }
+
+    /// Visit a struct expression like `Foo { a: 1, ..Default::default() }`.
+    fn visit_expr_struct(&mut self, i: &'ast syn::ExprStruct) {
+        if attrs_excluded(&i.attrs) {
+            return;
+        }
+        if i.rest.as_deref().map_or(false, expr_is_default_call) {
+            // Each field is followed by a comma, because the base comes after them all.
+            // Deleting the field and its comma leaves it to be filled from the base.
+            for pair in i.fields.pairs() {
+                self.collect_expr_mutants(pair.span(), vec![quote! {}], Genre::StructUpdate);
+            }
+        } else if self
+            .fn_stack
+            .last()
+            .map_or(false, |f| f.returned_structs.contains(&i.span().into()))
+        {
+            // The whole field is replaced, so that shorthand fields like `Foo { a }` get
+            // a name.
+            for field in &i.fields {
+                if !expr_is_default_call(&field.expr) {
+                    let member = tokens_to_pretty_string(&field.member);
+                    self.collect_text_mutant(
+                        field.span().into(),
+                        format!("{member}: Default::default()"),
+                        Genre::StructFieldDefault,
+                    );
+                }
+            }
+        }
+        syn::visit::visit_expr_struct(self, i);
+    }
+
+    /// Visit a macro invocation like `format!("...")`.
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if let Some(format_lit) = plain_format_string(i) {
+            self.collect_expr_mutants(
+                format_lit.span(),
+                vec![quote! { "xyzzy" }],
+                Genre::FormatString,
+            );
+        }
+        if path_ends_with(&i.path, "lazy_static") && self.options.genre_enabled(Genre::LazyStatic) {
+            match i.parse_body_with(parse_lazy_statics) {
+                Ok(statics) => {
+                    for lazy_static in statics {
+                        self.collect_static_initializer_mutants(
+                            &lazy_static.ident,
+                            &lazy_static.expr,
+                            lazy_static.span,
+                        );
+                    }
+                }
+                Err(err) => debug!(?err, "failed to parse lazy_static! body"),
+            }
+        }
+        syn::visit::visit_macro(self, i);
+    }
+
+    /// Visit `static NAME: T = ...;`.
+    fn visit_item_static(&mut self, i: &'ast syn::ItemStatic) {
+        if self.item_attrs_excluded(&i.attrs) {
+            return;
+        }
+        if type_is_lazy(&i.ty) {
+            self.collect_static_initializer_mutants(&i.ident, &i.expr, i.span());
+        }
+        syn::visit::visit_item_static(self, i);
+    }
+
+    /// Visit `impl Foo { ...}` or `impl Debug for Foo { ... }`.
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        let type_name = tokens_to_pretty_string(&i.self_ty);
+        let mut skipped = self.item_attrs_skip_reason(&i.attrs);
+        let name = if let Some((_, trait_path, _)) = &i.trait_ {
+            let trait_name = &trait_path.segments.last().unwrap().ident;
+            if trait_name == "Default" {
+                // Can't think of how to generate a viable different default.
+                skipped = skipped.or(Some(SkipReason::DefaultImpl));
+            }
+            if self.options.trait_skipped(&trait_name.to_string()) {
+                trace!(%trait_name, "skip impl of skipped trait");
+                skipped = skipped.or(Some(SkipReason::SkippedTrait));
+            }
+            format!("<impl {trait_name} for {type_name}>")
+        } else {
+            type_name
+        };
+        let dumped = self.dump_item("impl", &name, i.impl_token.span, &i.attrs, None, skipped);
+        if skipped.is_some() {
+            return;
+        }
+        let operator_impl_type = i
+            .trait_
+            .as_ref()
+            .filter(|(_, trait_path, _)| {
+                OPERATOR_TRAITS
+                    .iter()
+                    .any(|name| path_ends_with(trait_path, name))
+            })
+            .map(|_| (*i.self_ty).clone());
+        let assoc_types = i
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::ImplItem::Type(item_type) => {
+                    // An operator's `type Output = Self;` is the type it's implemented for.
+                    let ty = match &operator_impl_type {
+                        Some(impl_type) if type_is_self(&item_type.ty) => impl_type.clone(),
+                        _ => item_type.ty.clone(),
+                    };
+                    Some((item_type.ident.clone(), ty))
+                }
+                _ => None,
+            })
+            .collect();
+        let outer_assoc_types = std::mem::replace(&mut self.assoc_types, assoc_types);
+        let outer_operator_impl_type =
+            std::mem::replace(&mut self.operator_impl_type, operator_impl_type);
+        self.in_namespace(&name, |v| syn::visit::visit_item_impl(v, i));
+        self.assoc_types = outer_assoc_types;
+        self.operator_impl_type = outer_operator_impl_type;
+        self.count_dumped_mutants(dumped);
+    }
+
+    /// Visit `mod foo { ... }` or `mod foo;`.
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        let mod_name = &node.ident.unraw().to_string();
+        let _span = trace_span!(
+            "mod",
+            line = node.mod_token.span.start().line,
+            name = mod_name
+        )
+        .entered();
+        let skipped = self.item_attrs_skip_reason(&node.attrs);
+        let dumped = self.dump_item(
+            "mod",
+            mod_name,
+            node.mod_token.span,
+            &node.attrs,
+            None,
+            skipped,
+        );
+        if skipped.is_some() {
+            trace!("mod {:?} excluded by attrs", node.ident,);
+            return;
+        }
+        // If there's no content in braces, then this is a `mod foo;`
+        // statement referring to an external file. We find the file name
+        // then remember to visit it later.
+        //
+        // Both the current module and the included sub-module can be in
+        // either style: `.../foo.rs` or `.../foo/mod.rs`.
+        //
+        // If the current file ends with `/mod.rs`, then sub-modules
+        // will be in the same directory as this file. Otherwise, this is
+        // `/foo.rs` and sub-modules will be in `foo/`.
+        //
+        // Having determined the directory then we can look for either
+        // `foo.rs` or `foo/mod.rs`.
+        if node.content.is_none() {
+            let my_path: &Utf8Path = self.source_file.tree_relative_path().as_ref();
+            // Maybe matching on the name here is no the right approach and
+            // we should instead remember how this file was found?
+            let dir = if my_path.ends_with("mod.rs")
+                || my_path.ends_with("lib.rs")
+                || my_path.ends_with("main.rs")
+            {
+                my_path.parent().expect("mod path has no parent").to_owned()
+            } else {
+                my_path.with_extension("")
+            };
+            let mut found = false;
+            let mut tried_paths = Vec::new();
+            for &ext in &[".rs", "/mod.rs"] {
+                let relative_path = TreeRelativePathBuf::new(dir.join(format!("{mod_name}{ext}")));
+                let full_path = relative_path.within(&self.root);
+                if full_path.is_file() {
+                    trace!("found submodule in {full_path}");
+                    self.more_files.push(relative_path);
+                    found = true;
+                    break;
+                } else {
+                    tried_paths.push(full_path);
+                }
+            }
+            if !found {
+                warn!(
+                    "{path}:{line}: referent of mod {mod_name:#?} not found: tried {tried_paths:?}",
+                    path = self.source_file.tree_relative_path,
+                    line = node.mod_token.span.start().line,
+                );
+            }
+        }
+        self.in_namespace(mod_name, |v| syn::visit::visit_item_mod(v, node));
+        self.count_dumped_mutants(dumped);
+    }
+}
+
+/// Generate replacement text for a function based on its return type.
+fn return_type_replacements(
+    return_type: &ReturnType,
+    error_exprs: &[Expr],
+    no_default_types: &[String],
+) -> Vec<TokenStream> {
+    match return_type {
+        ReturnType::Default => vec![quote! { () }],
+        ReturnType::Type(_rarrow, type_) => type_replacements(type_, error_exprs, no_default_types),
+    }
+}
+
+/// Generate replacements for a function that returns one of its own type parameters,
+/// like `fn f<T: Default>() -> T`.
+///
+/// If the parameter is bounded by `Default` this is `<T as Default>::default()`, which
+/// doesn't depend on inference. With no such bound there's no way to construct a value,
+/// so nothing is generated.
+///
+/// Returns `None` if the return type isn't a type parameter of the function.
+fn generic_return_replacements(
+    return_type: &ReturnType,
+    generics: &Generics,
+) -> Option<Vec<TokenStream>> {
+    let ReturnType::Type(_rarrow, type_) = return_type else {
+        return None;
+    };
+    let Type::Path(syn::TypePath { qself: None, path }) = &**type_ else {
+        return None;
+    };
+    let ident = path.get_ident()?;
+    let param = generics.type_params().find(|param| param.ident == *ident)?;
+    let where_bounds = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| &where_clause.predicates)
+        .filter_map(|predicate| match predicate {
+            syn::WherePredicate::Type(syn::PredicateType {
+                bounded_ty: Type::Path(bounded),
+                bounds,
+                ..
+            }) if bounded.qself.is_none() && bounded.path.is_ident(ident) => Some(bounds),
+            _ => None,
+        })
+        .flatten();
+    let has_default_bound = param.bounds.iter().chain(where_bounds).any(|bound| {
+        matches!(bound, syn::TypeParamBound::Trait(trait_bound)
+            if path_ends_with(&trait_bound.path, "Default"))
+    });
+    if has_default_bound {
+        Some(vec![quote! { <#ident as Default>::default() }])
+    } else {
+        trace!(
+            ?ident,
+            "Return type is a type parameter with no Default bound"
+        );
+        Some(Vec::new())
+    }
+}
+
+/// True if a replacement expression can be evaluated in a `const fn`.
+///
+/// This allows literals, paths like `None`, and `Some`, `Ok`, `Err`, references,
+/// tuples, and arrays built from them, plus `String::new()`. Anything else, like
+/// `Default::default()` or `"xyzzy".into()`, is assumed not to be const.
+fn tokens_are_const(tokens: &TokenStream) -> bool {
+    fn expr_is_const(expr: &Expr) -> bool {
+        match expr {
+            Expr::Lit(_) | Expr::Path(_) => true,
+            Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            })
+            | Expr::Paren(syn::ExprParen { expr, .. })
+            | Expr::Repeat(syn::ExprRepeat { expr, .. })
+            | Expr::Reference(syn::ExprReference {
+                mutability: None,
+                expr,
+                ..
+            }) => expr_is_const(expr),
+            Expr::Tuple(syn::ExprTuple { elems, .. })
+            | Expr::Array(syn::ExprArray { elems, .. }) => elems.iter().all(expr_is_const),
+            Expr::Call(syn::ExprCall { func, args, .. }) => match &**func {
+                Expr::Path(syn::ExprPath { path, .. }) => {
+                    (["Some", "Ok", "Err"].iter().any(|name| path.is_ident(name))
+                        && args.iter().all(expr_is_const))
+                        || (path_ends_with(path, "new")
+                            && path.segments.len() == 2
+                            && path.segments[0].ident == "String"
+                            && args.is_empty())
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    syn::parse2::<Expr>(tokens.clone()).map_or(false, |expr| expr_is_const(&expr))
+}
+
+/// True if the return type is probably an `anyhow::Result`.
+///
+/// This is either written as `anyhow::Result<T>`, or as `Result<T>` with one type
+/// argument in a file that imports `anyhow::Result`.
+fn returns_anyhow_result(return_type: &ReturnType, anyhow_result_imported: bool) -> bool {
+    let ReturnType::Type(_rarrow, type_) = return_type else {
+        return false;
+    };
+    let Type::Path(syn::TypePath { qself: None, path }) = &**type_ else {
+        return false;
+    };
+    let segments = path.segments.iter().collect_vec();
+    let Some(last) = segments.last() else {
+        return false;
+    };
+    let n_args = match &last.arguments {
+        PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) => args.len(),
+        _ => 0,
+    };
+    if last.ident != "Result" || n_args > 1 {
+        return false;
+    }
+    match segments[..] {
+        [.., anyhow, _] => anyhow.ident == "anyhow",
+        [_] => anyhow_result_imported && n_args == 1,
+        [] => false,
+    }
+}
+
+/// True if this `use` tree imports `Result` from `anyhow`.
+fn use_tree_imports_anyhow_result(tree: &syn::UseTree, in_anyhow: bool) -> bool {
+    match tree {
+        syn::UseTree::Path(path) => {
+            use_tree_imports_anyhow_result(&path.tree, path.ident == "anyhow")
+        }
+        syn::UseTree::Name(name) => in_anyhow && name.ident == "Result",
+        syn::UseTree::Glob(_) => in_anyhow,
+        syn::UseTree::Group(group) => group
+            .items
+            .iter()
+            .any(|tree| use_tree_imports_anyhow_result(tree, in_anyhow)),
+        syn::UseTree::Rename(_) => false,
+    }
+}
+
+/// Replace associated types like `Self::Item` in a return type with their definitions
+/// from the enclosing impl block.
+///
+/// Associated types that aren't defined there, or that are written as
+/// `<Self as Trait>::Item`, are left alone.
+fn resolve_assoc_types(return_type: &ReturnType, assoc_types: &[(syn::Ident, Type)]) -> ReturnType {
+    struct Resolver<'a>(&'a [(syn::Ident, Type)]);
+
+    impl VisitMut for Resolver<'_> {
+        fn visit_type_mut(&mut self, type_: &mut Type) {
+            // Like `<Self as Mul<i32>>::Output`.
+            if let Type::Path(syn::TypePath {
+                qself: Some(qself),
+                path,
+            }) = type_
+            {
+                if let Some(assoc_segment) = path.segments.last() {
+                    if type_is_self(&qself.ty)
+                        && qself.position + 1 == path.segments.len()
+                        && assoc_segment.arguments.is_none()
+                    {
+                        if let Some((_, resolved)) =
+                            self.0.iter().find(|(name, _)| *name == assoc_segment.ident)
+                        {
+                            *type_ = resolved.clone();
+                            return;
+                        }
+                    }
+                }
+            }
+            if let Type::Path(syn::TypePath { qself: None, path }) = type_ {
+                if let [self_segment, assoc_segment] = path.segments.iter().collect_vec()[..] {
+                    if self_segment.ident == "Self"
+                        && self_segment.arguments.is_none()
+                        && assoc_segment.arguments.is_none()
+                    {
+                        if let Some((_, resolved)) =
+                            self.0.iter().find(|(name, _)| *name == assoc_segment.ident)
+                        {
+                            *type_ = resolved.clone();
+                            return;
+                        }
+                    }
+                }
+            }
+            syn::visit_mut::visit_type_mut(self, type_);
+        }
+    }
+
+    let mut return_type = return_type.clone();
+    if !assoc_types.is_empty() {
+        Resolver(assoc_types).visit_return_type_mut(&mut return_type);
+    }
+    return_type
+}
+
+/// Generate some values that we hope are reasonable replacements for a type.
+///
+/// This is really the heart of cargo-mutants.
+fn type_replacements(
+    type_: &Type,
+    error_exprs: &[Expr],
+    no_default_types: &[String],
+) -> Vec<TokenStream> {
+    let mut reps = Vec::new();
+    match type_ {
+        Type::Path(syn::TypePath { path, .. }) => {
+            // dbg!(&path);
+            if path.is_ident("bool") {
+                reps.push(quote! { true });
+                reps.push(quote! { false });
+            } else if path.is_ident("String") {
+                reps.push(quote! { String::new() });
+                reps.push(quote! { "xyzzy".into() });
+            } else if path_is_unsigned(path) {
+                reps.push(quote! { 0 });
+                reps.push(quote! { 1 });
+            } else if path_is_signed(path) {
+                reps.push(quote! { 0 });
+                reps.push(quote! { 1 });
+                reps.push(quote! { -1 });
+            } else if path_is_nonzero_signed(path) {
+                reps.extend([quote! { 1 }, quote! { -1 }]);
+            } else if path_is_nonzero_unsigned(path) {
+                reps.push(quote! { 1 });
+            } else if path_is_float(path) {
+                reps.push(quote! { 0.0 });
+                reps.push(quote! { 1.0 });
+                reps.push(quote! { -1.0 });
+            } else if path_ends_with(path, "Result") {
+                if let Some(ok_type) = result_ok_type(path) {
+                    reps.extend(
+                        type_replacements(ok_type, error_exprs, no_default_types)
+                            .into_iter()
+                            .map(|rep| {
+                                quote! { Ok(#rep) }
+                            }),
+                    );
+                } else {
+                    // A result but with no type arguments, like `fmt::Result`; hopefully
+                    // the Ok value can be constructed with Default.
+                    reps.push(quote! { Ok(Default::default()) });
+                }
+                reps.extend(error_exprs.iter().map(|error_expr| {
+                    quote! { Err(#error_expr) }
+                }));
+            } else if let Some(boxed_type) = match_first_type_arg(path, "Box") {
+                reps.extend(
+                    type_replacements(boxed_type, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { Box::new(#rep) }
+                        }),
+                )
+            } else if let Some(some_type) = match_first_type_arg(path, "Option") {
+                reps.push(quote! { None });
+                reps.extend(
+                    type_replacements(some_type, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { Some(#rep) }
+                        }),
+                );
+            } else if let Some(boxed_type) = match_first_type_arg(path, "Vec") {
+                // Generate an empty Vec, and then a one-element vec for every recursive
+                // value.
+                reps.push(quote! { vec![] });
+                reps.extend(
+                    type_replacements(boxed_type, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { vec![#rep] }
+                        }),
+                )
+            } else if let Some((map_path, key_type, value_type)) = map_type_args(path) {
+                // Generate an empty map, and then, like for a Vec, a one-entry map for
+                // each value replacement, with the first key replacement, and for each
+                // other key replacement, with the first value. Types we know nothing
+                // about might not implement `Default`, and keys must be `Hash` and `Ord`,
+                // so otherwise there's only the empty map.
+                reps.push(quote! { Default::default() });
+                let default = quote! { Default::default() }.to_string();
+                let constructible_reps = |type_| {
+                    type_replacements(type_, error_exprs, no_default_types)
+                        .into_iter()
+                        .filter(|rep| rep.to_string() != default)
+                        .collect_vec()
+                };
+                let key_reps = if type_is_map_key(key_type) {
+                    constructible_reps(key_type)
+                } else {
+                    Vec::new()
+                };
+                let value_reps = constructible_reps(value_type);
+                if let (Some(first_key), Some(first_value)) = (key_reps.first(), value_reps.first())
+                {
+                    reps.extend(
+                        value_reps
+                            .iter()
+                            .map(|value| (first_key, value))
+                            .chain(key_reps[1..].iter().map(|key| (key, first_value)))
+                            .map(|(key, value)| quote! { #map_path::from([(#key, #value)]) }),
+                    );
+                }
+            } else if let Some(inner_type) = match_first_type_arg(path, "Arc") {
+                // TODO: Ideally we should use the path without relying on it being
+                // imported, but we must strip or rewrite the arguments, so that
+                // `std::sync::Arc<String>` becomes either `std::sync::Arc::<String>::new`
+                // or at least `std::sync::Arc::new`. Similarly for other types.
+                reps.extend(
+                    type_replacements(inner_type, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { Arc::new(#rep) }
+                        }),
+                )
+            } else if path.segments.last().map_or(false, |segment| {
+                no_default_types
+                    .iter()
+                    .any(|name| segment.ident == name.as_str())
+            }) {
+                trace!(?type_, "Skip Default::default() for configured type");
+            } else {
+                reps.push(quote! { Default::default() });
+            }
+        }
+        Type::Array(TypeArray { elem, len, .. }) => reps.extend(
+            // Generate arrays that repeat each replacement value however many times.
+            // In principle we could generate combinations, but that might get very
+            // large, and values like "all zeros" and "all ones" seem likely to catch
+            // lots of things.
+            type_replacements(elem, error_exprs, no_default_types)
+                .into_iter()
+                .map(|r| quote! { [ #r; #len ] }),
+        ),
+        Type::Reference(syn::TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) => match &**elem {
+            Type::Path(path) if path.path.is_ident("str") => {
+                reps.push(quote! { "" });
+                reps.push(quote! { "xyzzy" });
+            }
+            _ => {
+                reps.extend(
+                    referenced_type_replacements(elem, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { &#rep }
+                        }),
+                );
+            }
+        },
+        Type::Reference(syn::TypeReference {
+            mutability: Some(_),
+            elem,
+            ..
+        }) => {
+            // Make &mut with static lifetime by leaking them on the heap.
+            reps.extend(
+                referenced_type_replacements(elem, error_exprs, no_default_types)
+                    .into_iter()
+                    .map(|rep| {
+                        quote! { Box::leak(Box::new(#rep)) }
+                    }),
+            );
+        }
+        Type::Tuple(TypeTuple { elems, .. }) if elems.is_empty() => {
+            reps.push(quote! { () });
+            // TODO: Also recurse into non-empty tuples.
+        }
+        Type::Never(_) => {
+            // In theory we could mutate this to a function that just
+            // loops or sleeps, but it seems unlikely to be useful,
+            // so generate nothing.
+        }
+        Type::ImplTrait(syn::TypeImplTrait { bounds, .. }) => {
+            // An opaque type can't be constructed with `Default::default()`, so only
+            // generate mutants for traits with well-known implementations.
+            if let Some(item_type) = iterator_item_type(bounds) {
+                reps.push(quote! { std::iter::empty() });
+                reps.extend(
+                    type_replacements(item_type, error_exprs, no_default_types)
+                        .into_iter()
+                        .map(|rep| {
+                            quote! { std::iter::once(#rep) }
+                        }),
+                );
+            } else if let Some((n_inputs, output)) = fn_trait_signature(bounds) {
+                // Return a closure that ignores its arguments and returns each
+                // replacement for the output type.
+                let output_reps = match output {
+                    ReturnType::Default => vec![quote! { () }],
+                    ReturnType::Type(_, output_type) => {
+                        type_replacements(output_type, error_exprs, no_default_types)
+                    }
+                };
+                let params = std::iter::repeat(quote! { _ }).take(n_inputs).collect_vec();
+                reps.extend(output_reps.into_iter().map(|rep| {
+                    if params.is_empty() {
+                        quote! { || #rep }
+                    } else {
+                        quote! { |#(#params),*| #rep }
+                    }
+                }));
+            } else {
+                trace!(?type_, "Skip unrecognized impl Trait return type");
+            }
+        }
+        Type::Paren(syn::TypeParen { elem, .. }) => {
+            reps.extend(type_replacements(elem, error_exprs, no_default_types));
+        }
+        Type::TraitObject(_) => {
+            // A trait object, like `Box<dyn Trait>` or `&dyn Trait`, can't be made
+            // with `Default::default()`, and we don't know any concrete type to box.
+            trace!(?type_, "Skip dyn Trait return type");
+        }
+        _ => {
+            trace!(?type_, "Return type is not recognized, trying Default");
+            reps.push(quote! { Default::default() });
+        }
+    }
+    reps
+}
+
+/// Generate values to be referenced by a replacement of a reference type.
+///
+/// Getters often return references to fields of types that we don't know how to
+/// construct, like `fn config(&self) -> &Config`. The only value for them would be
+/// `Default::default()`, which probably doesn't exist or can't be borrowed for long
+/// enough, so generate nothing.
+fn referenced_type_replacements(
+    elem: &Type,
+    error_exprs: &[Expr],
+    no_default_types: &[String],
+) -> Vec<TokenStream> {
+    let reps = type_replacements(elem, error_exprs, no_default_types);
+    if matches!(elem, Type::Path(_))
+        && reps.len() == 1
+        && reps[0].to_string() == quote! { Default::default() }.to_string()
+    {
+        trace!(?elem, "Skip reference to a type with no known construction");
+        Vec::new()
+    } else {
+        reps
+    }
+}
+
+/// If this is a call to `unwrap_or`, `map_or`, or `unwrap_or_else` with a literal default
+/// value, return that literal.
+///
+/// For `unwrap_or_else` the default must be a closure that simply returns a literal,
+/// like `|| 0`.
+fn literal_default_argument(call: &ExprMethodCall) -> Option<&Lit> {
+    let method = call.method.to_string();
+    let default_arg = match method.as_str() {
+        "unwrap_or" | "map_or" => call.args.first()?,
+        "unwrap_or_else" => match call.args.first()? {
+            Expr::Closure(closure) => &closure.body,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match default_arg {
+        Expr::Lit(syn::ExprLit { lit, .. }) => Some(lit),
+        _ => None,
+    }
+}
+
+/// Generate some values to replace a literal, as close as possible to the original
+/// so that they're likely to be valid in the same place.
+///
+/// Booleans are flipped, and integers are nudged up and down by one, keeping any suffix.
+/// Other literals generate no replacements.
+fn literal_replacements(lit: &Lit) -> Vec<TokenStream> {
+    match lit {
+        Lit::Bool(b) => {
+            let flipped = !b.value;
+            vec![quote! { #flipped }]
+        }
+        Lit::Int(int) => {
+            let Ok(value) = int.base10_parse::<u128>() else {
+                return Vec::new();
+            };
+            let suffix = int.suffix();
+            let mut values = Vec::new();
+            if value > 0 {
+                values.push(value - 1);
+            }
+            if let Some(incremented) = value.checked_add(1) {
+                values.push(incremented);
+            }
+            values
+                .into_iter()
+                .map(|v| {
+                    let new_lit = syn::LitInt::new(&format!("{v}{suffix}"), int.span());
+                    quote! { #new_lit }
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Generate replacements for the value of a constant, if it's a literal of a primitive type.
+///
+/// Bools are flipped, and integers are replaced by zero, and by one less and one more,
+/// staying within the range of the type so that the mutants are viable.
+///
+/// If the constant is used as an array length, zero is never generated, because
+/// expressions like `SIZE - 1` or `buf[0]` would then fail to compile.
+fn const_value_replacements(type_: &Type, value: &Expr, used_as_length: bool) -> Vec<TokenStream> {
+    let (Type::Path(syn::TypePath { qself: None, path }), Expr::Lit(expr_lit)) = (type_, value)
+    else {
+        return Vec::new();
+    };
+    match &expr_lit.lit {
+        lit @ Lit::Bool(_) if path.is_ident("bool") => literal_replacements(lit),
+        Lit::Int(int) => {
+            let (Some(max), Ok(value)) = (integer_type_max(path), int.base10_parse::<u128>())
+            else {
+                return Vec::new();
+            };
+            let mut values = Vec::new();
+            if value > 1 && !used_as_length {
+                values.push(0);
+            }
+            if value > 1 || (value == 1 && !used_as_length) {
+                values.push(value - 1);
+            }
+            if value < max {
+                values.push(value + 1);
+            }
+            values
+                .into_iter()
+                .map(|v| {
+                    let new_lit = syn::LitInt::new(&format!("{v}{}", int.suffix()), int.span());
+                    quote! { #new_lit }
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The largest value of a primitive integer type, or None if this is not one.
+fn integer_type_max(path: &Path) -> Option<u128> {
+    let max = match path.get_ident()?.to_string().as_str() {
+        "u8" => u8::MAX as u128,
+        "u16" => u16::MAX as u128,
+        "u32" => u32::MAX as u128,
+        "u64" => u64::MAX as u128,
+        "u128" => u128::MAX,
+        "usize" => usize::MAX as u128,
+        "i8" => i8::MAX as u128,
+        "i16" => i16::MAX as u128,
+        "i32" => i32::MAX as u128,
+        "i64" => i64::MAX as u128,
+        "i128" => i128::MAX as u128,
+        "isize" => isize::MAX as u128,
+        _ => return None,
+    };
+    Some(max)
+}
+
+/// True if a match arm's pattern can be swapped with another: it's a literal, or a
+/// path to a unit variant or constant, so it binds no variables.
+///
+/// A bare capitalized identifier like `None` is parsed as a binding, but by convention
+/// it's a unit variant or constant.
+fn pattern_is_swappable(pat: &syn::Pat) -> bool {
+    let swappable = match pat {
+        syn::Pat::Lit(_) | syn::Pat::Path(_) => true,
+        syn::Pat::Ident(pat_ident) => {
+            pat_ident.by_ref.is_none()
+                && pat_ident.mutability.is_none()
+                && pat_ident.subpat.is_none()
+                && pat_ident
+                    .ident
+                    .to_string()
+                    .starts_with(|c: char| c.is_ascii_uppercase())
+        }
+        _ => false,
+    };
+    // Descriptions of the mutant find the pattern before the first `=>`.
+    swappable && !pat.to_token_stream().to_string().contains("=>")
+}
+
+/// Generate replacements for an `if`/`else` expression that produces a value: the
+/// then-branch block, and separately the else-branch block.
+///
+/// This is only done when the branches consist of a single expression with no statements,
+/// so that the replacement has the same type and the mutant can be briefly described.
+///
+/// For an `else if` chain only the then branch is used here: the nested `if` gets its
+/// own mutants. `if let` is skipped because the branches may use bindings from the pattern.
+fn if_branch_replacements(expr_if: &syn::ExprIf) -> Vec<TokenStream> {
+    let Some((_else, else_branch)) = &expr_if.else_branch else {
+        return Vec::new();
+    };
+    if matches!(*expr_if.cond, Expr::Let(_)) || !block_is_single_expr(&expr_if.then_branch) {
+        return Vec::new();
+    }
+    let then_branch = &expr_if.then_branch;
+    match else_branch.as_ref() {
+        Expr::Block(syn::ExprBlock { block, .. }) if block_is_single_expr(block) => {
+            vec![quote! { #then_branch }, quote! { #block }]
+        }
+        Expr::If(_) => vec![quote! { #then_branch }],
+        _ => Vec::new(),
+    }
+}
+
+/// True if this is a guard like `if cond { return x; }`, with no `else`, whose block
+/// does nothing but return.
+fn is_return_guard(expr_if: &syn::ExprIf) -> bool {
+    expr_if.else_branch.is_none()
+        && !matches!(*expr_if.cond, Expr::Let(_))
+        && matches!(
+            expr_if.then_branch.stmts.as_slice(),
+            [syn::Stmt::Expr(Expr::Return(_), _)]
+        )
+}
+
+/// If an `if` or `while` condition is a field access like `self.enabled`, possibly
+/// negated or in parens, return the field access.
+fn condition_field(cond: &Expr) -> Option<&syn::ExprField> {
+    match cond {
+        Expr::Field(field) => Some(field),
+        Expr::Paren(syn::ExprParen { expr, .. }) => condition_field(expr),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Not(_),
+            expr,
+            ..
+        }) => condition_field(expr),
+        _ => None,
+    }
+}
+
+/// True if the block contains only a single expression that is its value, like `{ a + 1 }`.
+fn block_is_single_expr(block: &syn::Block) -> bool {
+    matches!(block.stmts.as_slice(), [syn::Stmt::Expr(_, None)])
+}
+
+/// If this is a `format!`, `write!`, or `writeln!` macro whose format string is a plain
+/// literal with no placeholders and no further arguments, return the literal.
+///
+/// Format strings with placeholders are not mutated, because replacing them would leave
+/// the arguments unused, or lose captured identifiers.
+fn plain_format_string(mac: &syn::Macro) -> Option<syn::LitStr> {
+    let macro_name = mac.path.segments.last()?.ident.to_string();
+    let format_arg_index = match macro_name.as_str() {
+        "format" => 0,
+        "write" | "writeln" => 1,
+        _ => return None,
+    };
+    let args = mac
+        .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        .ok()?;
+    if args.len() != format_arg_index + 1 {
+        return None;
+    }
+    match &args[format_arg_index] {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(lit_str),
+            ..
+        }) if !lit_str.value().contains(['{', '}']) && lit_str.value() != "xyzzy" => {
+            Some(lit_str.clone())
+        }
+        _ => None,
+    }
+}
+
+/// If this is `cond.then_some(x)` or `cond.then(|| x)`, return the expression for the
+/// value, `x`.
+///
+/// The type of the receiver isn't known, so `then` is only matched when its argument
+/// is a closure with no parameters, which distinguishes it from methods like
+/// `Ordering::then`.
+fn bool_then_value(call: &ExprMethodCall) -> Option<&Expr> {
+    if call.args.len() != 1 {
+        return None;
+    }
+    let arg = call.args.first()?;
+    match (call.method.to_string().as_str(), arg) {
+        ("then_some", value) => Some(value),
+        ("then", Expr::Closure(closure)) if closure.inputs.is_empty() => Some(&closure.body),
+        _ => None,
+    }
+}
+
+/// Iterator adapters whose argument is a predicate returning `bool`.
+const PREDICATE_METHODS: &[&str] = &["filter", "take_while", "skip_while", "find"];
+
+/// If this is a call to an iterator adapter like `filter` with a closure as its
+/// predicate, return the body of the closure.
+fn predicate_closure_body(call: &ExprMethodCall) -> Option<&Expr> {
+    if call.args.len() != 1 || !PREDICATE_METHODS.contains(&call.method.to_string().as_str()) {
+        return None;
+    }
+    match call.args.first()? {
+        Expr::Closure(closure) => Some(&closure.body),
+        _ => None,
+    }
+}
+
+/// True if this is a call to a method that adds to a collection, like `v.push(x)`.
+///
+/// The methods are matched by name, since the type of the receiver isn't known.
+fn is_collection_push(call: &ExprMethodCall) -> bool {
+    !call.args.is_empty()
+        && ["push", "insert", "extend", "append"]
+            .iter()
+            .any(|name| call.method == name)
+}
+
+/// If this calls `mem::swap`, `mem::replace`, or `mem::take`, return the function name.
+///
+/// The function is recognized by a path ending in `mem::swap` and so on, so a function
+/// imported under its own name, like `swap(&mut a, &mut b)`, isn't seen.
+fn mem_function(call: &syn::ExprCall) -> Option<&'static str> {
+    let Expr::Path(syn::ExprPath {
+        qself: None, path, ..
+    }) = &*call.func
+    else {
+        return None;
+    };
+    let mut segments = path.segments.iter().rev();
+    let name = segments.next()?;
+    if segments.next()?.ident != "mem" || !name.arguments.is_none() {
+        return None;
+    }
+    ["swap", "replace", "take"]
+        .into_iter()
+        .find(|function| name.ident == function)
+}
+
+/// The precedence of a binary operator: higher numbers bind more tightly.
+fn binary_precedence(op: &syn::BinOp) -> u8 {
+    use syn::BinOp::*;
+    match op {
+        Mul(_) | Div(_) | Rem(_) => 10,
+        Add(_) | Sub(_) => 9,
+        Shl(_) | Shr(_) => 8,
+        BitAnd(_) => 7,
+        BitXor(_) => 6,
+        BitOr(_) => 5,
+        Eq(_) | Lt(_) | Le(_) | Ne(_) | Ge(_) | Gt(_) => 4,
+        And(_) => 3,
+        Or(_) => 2,
+        // Compound assignments, like `+=`.
+        _ => 1,
+    }
+}
+
+/// If this is a short-circuiting `a && b` or `a || b`, return the non-short-circuiting
+/// `&` or `|`, or the reverse.
+///
+/// The two only behave differently if evaluating the right operand has some effect,
+/// such as panicking, so nothing is returned unless it calls a function or method,
+/// invokes a macro, or indexes. `&` and `|` are only swapped if one of the operands looks
+/// boolean, since on integers they're bitwise operators.
+fn short_circuit_swap(expr: &syn::ExprBinary) -> Option<&'static str> {
+    use syn::BinOp;
+    if !expr_may_have_effects(&expr.right) {
+        return None;
+    }
+    match expr.op {
+        BinOp::And(_) | BinOp::Or(_)
+            if matches!(*expr.left, Expr::Let(_)) || matches!(*expr.right, Expr::Let(_)) =>
+        {
+            None
+        }
+        BinOp::And(_) => Some("&"),
+        BinOp::Or(_) => Some("|"),
+        BinOp::BitAnd(_) | BinOp::BitOr(_)
+            if !expr_looks_boolean(&expr.left) && !expr_looks_boolean(&expr.right) =>
+        {
+            None
+        }
+        BinOp::BitAnd(_) => Some("&&"),
+        BinOp::BitOr(_) => Some("||"),
+        _ => None,
+    }
+}
+
+/// True if this operand of a `&&` or `||` needs parentheses when the operator is
+/// changed to `&` or `|`, which bind more tightly, like `a == 1` in `a == 1 && f(b)`.
+fn operand_needs_parens(operand: &Expr) -> bool {
+    match operand {
+        Expr::Binary(binary) => {
+            binary_precedence(&binary.op)
+                <= binary_precedence(&syn::BinOp::BitAnd(Default::default()))
+        }
+        Expr::Range(_) | Expr::Closure(_) | Expr::Assign(_) => true,
+        _ => false,
+    }
+}
+
+/// True if evaluating this expression might have some effect beyond giving a value, or
+/// might panic: that is, if it contains a call, method call, macro, or index.
+fn expr_may_have_effects(expr: &Expr) -> bool {
+    #[derive(Default)]
+    struct EffectVisitor(bool);
+    impl<'ast> Visit<'ast> for EffectVisitor {
+        fn visit_expr_call(&mut self, _: &'ast syn::ExprCall) {
+            self.0 = true;
+        }
+        fn visit_expr_method_call(&mut self, _: &'ast ExprMethodCall) {
+            self.0 = true;
+        }
+        fn visit_macro(&mut self, _: &'ast syn::Macro) {
+            self.0 = true;
+        }
+        fn visit_expr_index(&mut self, _: &'ast syn::ExprIndex) {
+            self.0 = true;
+        }
+    }
+    let mut visitor = EffectVisitor::default();
+    visitor.visit_expr(expr);
+    visitor.0
+}
+
+/// True if this expression is evidently a `bool`: a `true` or `false` literal, a
+/// comparison, a `&&` or `||`, the negation of one of those, or a call to a method
+/// with a name like `is_empty`, `has_key`, or `contains`.
+fn expr_looks_boolean(expr: &Expr) -> bool {
+    use syn::BinOp::*;
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Bool(_), ..
+        }) => true,
+        Expr::Binary(binary) => matches!(
+            binary.op,
+            Eq(_) | Lt(_) | Le(_) | Ne(_) | Ge(_) | Gt(_) | And(_) | Or(_)
+        ),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Not(_),
+            expr,
+            ..
+        })
+        | Expr::Paren(syn::ExprParen { expr, .. }) => expr_looks_boolean(expr),
+        Expr::MethodCall(call) => {
+            let method = call.method.to_string();
+            method.starts_with("is_")
+                || method.starts_with("has_")
+                || [
+                    "contains",
+                    "contains_key",
+                    "starts_with",
+                    "ends_with",
+                    "any",
+                    "all",
+                    "eq",
+                    "ne",
+                ]
+                .contains(&method.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// If this is a call to a `Duration` constructor like `Duration::from_secs(5)` or
+/// `std::time::Duration::from_millis(250)` with a literal argument, return the literal.
+fn duration_literal_argument(call: &syn::ExprCall) -> Option<&Lit> {
+    let Expr::Path(syn::ExprPath {
+        qself: None, path, ..
+    }) = &*call.func
+    else {
+        return None;
+    };
+    let mut segments = path.segments.iter().rev();
+    let name = segments.next()?;
+    if segments.next()?.ident != "Duration"
+        || ![
+            "from_secs",
+            "from_millis",
+            "from_micros",
+            "from_nanos",
+            "from_secs_f32",
+            "from_secs_f64",
+        ]
+        .iter()
+        .any(|constructor| name.ident == constructor)
+    {
+        return None;
+    }
+    match call.args.iter().collect_vec().as_slice() {
+        [Expr::Lit(syn::ExprLit { lit, .. })] => Some(lit),
+        _ => None,
+    }
+}
+
+/// Generate replacements for the literal argument of a `Duration` constructor: zero,
+/// and twice the original value, keeping any suffix.
+///
+/// Small changes like one more or less are unlikely to make any difference to code that
+/// waits or times out, so the value is changed by more than [literal_replacements] would.
+fn duration_literal_replacements(lit: &Lit) -> Vec<TokenStream> {
+    match lit {
+        Lit::Int(int) => {
+            let Ok(value) = int.base10_parse::<u64>() else {
+                return Vec::new();
+            };
+            let suffix = int.suffix();
+            [0, value.saturating_mul(2).max(1)]
+                .into_iter()
+                .filter(|v| *v != value)
+                .map(|v| {
+                    let new_lit = syn::LitInt::new(&format!("{v}{suffix}"), int.span());
+                    quote! { #new_lit }
+                })
+                .collect()
+        }
+        Lit::Float(float) => {
+            let Ok(value) = float.base10_parse::<f64>() else {
+                return Vec::new();
+            };
+            let suffix = float.suffix();
+            let doubled = if value == 0.0 { 1.0 } else { value * 2.0 };
+            // Doubling a very large literal can overflow to infinity, which can't be
+            // written as a literal.
+            let representable = |v: f64| {
+                if suffix == "f32" {
+                    (v as f32).is_finite()
+                } else {
+                    v.is_finite()
+                }
+            };
+            [0.0, doubled]
+                .into_iter()
+                .filter(|v| *v != value && representable(*v))
+                .map(|v| {
+                    let new_lit = syn::LitFloat::new(&format!("{v:?}{suffix}"), float.span());
+                    quote! { #new_lit }
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// If this is `collect::<Vec<T>>()` or `collect::<HashSet<T>>()`, return the type in the
+/// turbofish and the other collection of the same elements.
+///
+/// Vecs of floats aren't changed, since floats can't be put in a `HashSet`.
+fn collect_target_replacement(call: &ExprMethodCall) -> Option<(&Type, TokenStream)> {
+    if call.method != "collect" || !call.args.is_empty() {
+        return None;
+    }
+    let turbofish = call.turbofish.as_ref()?;
+    let [syn::GenericArgument::Type(target)] = turbofish.args.iter().collect_vec()[..] else {
+        return None;
+    };
+    let Type::Path(syn::TypePath { qself: None, path }) = target else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let [syn::GenericArgument::Type(elem)] = args.args.iter().collect_vec()[..] else {
+        return None;
+    };
+    if segment.ident == "Vec" {
+        match elem {
+            Type::Path(elem_path) if path_is_float(&elem_path.path) => None,
+            _ => Some((target, quote! { std::collections::HashSet<#elem> })),
+        }
+    } else if segment.ident == "HashSet" {
+        Some((target, quote! { Vec<#elem> }))
+    } else {
+        None
+    }
+}
+
+/// Find the `return Err(...)` expressions in a function body, not including those
+/// in closures, async blocks, or nested items, which return from something else.
+fn early_error_returns(block: &syn::Block) -> Vec<&syn::ExprReturn> {
+    struct ReturnVisitor<'ast>(Vec<&'ast syn::ExprReturn>);
+    impl<'ast> Visit<'ast> for ReturnVisitor<'ast> {
+        fn visit_expr_return(&mut self, i: &'ast syn::ExprReturn) {
+            if i.expr.as_deref().and_then(result_variant) == Some("Err") {
+                self.0.push(i);
+            }
+            syn::visit::visit_expr_return(self, i);
+        }
+
+        fn visit_expr_closure(&mut self, _i: &'ast syn::ExprClosure) {}
+
+        fn visit_expr_async(&mut self, _i: &'ast syn::ExprAsync) {}
+
+        fn visit_item(&mut self, _i: &'ast syn::Item) {}
+    }
+    let mut visitor = ReturnVisitor(Vec::new());
+    visitor.visit_block(block);
+    visitor.0
+}
+
+/// Find the struct literals returned by a function body, as its final expression or
+/// from a `return`, possibly wrapped in `Ok(...)` or `Some(...)`.
+///
+/// Returns in closures, async blocks, or nested items return from something else, and
+/// aren't included.
+fn returned_struct_literals(block: &syn::Block) -> Vec<&syn::ExprStruct> {
+    struct ReturnVisitor<'ast>(Vec<&'ast syn::ExprStruct>);
+    impl<'ast> Visit<'ast> for ReturnVisitor<'ast> {
+        fn visit_expr_return(&mut self, i: &'ast syn::ExprReturn) {
+            if let Some(expr_struct) = i.expr.as_deref().and_then(returned_struct_literal) {
+                self.0.push(expr_struct);
+            }
+            syn::visit::visit_expr_return(self, i);
+        }
+
+        fn visit_expr_closure(&mut self, _i: &'ast syn::ExprClosure) {}
+
+        fn visit_expr_async(&mut self, _i: &'ast syn::ExprAsync) {}
+
+        fn visit_item(&mut self, _i: &'ast syn::Item) {}
+    }
+    let mut visitor = ReturnVisitor(Vec::new());
+    visitor.visit_block(block);
+    if let Some(syn::Stmt::Expr(tail, None)) = block.stmts.last() {
+        visitor.0.extend(returned_struct_literal(tail));
+    }
+    visitor.0
+}
+
+/// If this returned expression is a struct literal, possibly in parens or wrapped in
+/// `Ok(...)` or `Some(...)`, return the literal.
+fn returned_struct_literal(expr: &Expr) -> Option<&syn::ExprStruct> {
+    match expr {
+        Expr::Struct(expr_struct) => Some(expr_struct),
+        Expr::Paren(paren) => returned_struct_literal(&paren.expr),
+        Expr::Call(syn::ExprCall { func, args, .. }) if args.len() == 1 => match &**func {
+            Expr::Path(syn::ExprPath { path, .. })
+                if path.is_ident("Ok") || path.is_ident("Some") =>
+            {
+                returned_struct_literal(&args[0])
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// If this expression is a call to `Ok(...)` or `Err(...)`, return which.
+fn result_variant(expr: &Expr) -> Option<&'static str> {
+    let Expr::Call(syn::ExprCall { func, args, .. }) = expr else {
+        return None;
+    };
+    let Expr::Path(syn::ExprPath { path, .. }) = &**func else {
+        return None;
+    };
+    if args.len() != 1 {
+        None
+    } else if path_ends_with(path, "Ok") {
+        Some("Ok")
+    } else if path_ends_with(path, "Err") {
+        Some("Err")
+    } else {
+        None
+    }
+}
+
+/// One `static ref NAME: T = EXPR;` item inside `lazy_static!`.
+struct LazyStaticItem {
+    ident: syn::Ident,
+    expr: Expr,
+    span: proc_macro2::Span,
+}
+
+/// Parse the body of a `lazy_static!` macro.
+fn parse_lazy_statics(input: syn::parse::ParseStream) -> syn::Result<Vec<LazyStaticItem>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        input.call(Attribute::parse_outer)?;
+        input.parse::<syn::Visibility>()?;
+        let static_token: Token![static] = input.parse()?;
+        input.parse::<Token![ref]>()?;
+        let ident: syn::Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        input.parse::<Type>()?;
+        input.parse::<Token![=]>()?;
+        let expr: Expr = input.parse()?;
+        let semi: Token![;] = input.parse()?;
+        let span = static_token
+            .span
+            .join(semi.span)
+            .unwrap_or(static_token.span);
+        items.push(LazyStaticItem { ident, expr, span });
+    }
+    Ok(items)
+}
+
+/// True if this is the type of a lazily initialized static, like `Lazy<T>` from
+/// `once_cell` or `LazyLock<T>` from the standard library.
+fn type_is_lazy(type_: &Type) -> bool {
+    match type_ {
+        Type::Path(syn::TypePath { path, .. }) => {
+            path_ends_with(path, "Lazy") || path_ends_with(path, "LazyLock")
+        }
+        _ => false,
+    }
+}
+
+/// Return all the literals in an expression, in order.
+fn expr_literals(expr: &Expr) -> Vec<Lit> {
+    struct LiteralCollector(Vec<Lit>);
+
+    impl<'ast> Visit<'ast> for LiteralCollector {
+        fn visit_lit(&mut self, lit: &'ast Lit) {
+            self.0.push(lit.clone());
+        }
+    }
+
+    let mut collector = LiteralCollector(Vec::new());
+    collector.visit_expr(expr);
+    collector.0
+}
+
+/// Arithmetic operations that have both `saturating_` and `wrapping_` methods on the
+/// primitive integer types.
+const OVERFLOW_METHOD_OPS: &[&str] = &["add", "sub", "mul", "div", "pow"];
+
+/// If this is a saturating or wrapping arithmetic method like `saturating_add`, return
+/// the method with the other overflow behavior.
+///
+/// `checked_` methods are never produced or replaced because they return an `Option`,
+/// so swapping them would not compile.
+fn swapped_overflow_method(method: &syn::Ident) -> Option<syn::Ident> {
+    let name = method.to_string();
+    let (prefix, op) = name.split_once('_')?;
+    if !OVERFLOW_METHOD_OPS.contains(&op) {
+        return None;
+    }
+    let new_prefix = match prefix {
+        "saturating" => "wrapping",
+        "wrapping" => "saturating",
+        _ => return None,
+    };
+    Some(syn::Ident::new(
+        &format!("{new_prefix}_{op}"),
+        method.span(),
+    ))
+}
+
+fn return_type_to_string(return_type: &ReturnType) -> String {
+    match return_type {
+        ReturnType::Default => String::new(),
+        ReturnType::Type(arrow, typ) => {
+            format!(
+                "{} {}",
+                arrow.to_token_stream(),
+                tokens_to_pretty_string(typ)
+            )
+        }
+    }
+}
+
+/// True if the type is `Self`.
+fn type_is_self(type_: &Type) -> bool {
+    matches!(type_, Type::Path(syn::TypePath { qself: None, path }) if path.is_ident("Self"))
+}
+
+fn path_ends_with(path: &Path, ident: &str) -> bool {
+    path.segments.last().map_or(false, |s| s.ident == ident)
+}
+
+fn path_is_float(path: &Path) -> bool {
+    ["f32", "f64"].iter().any(|s| path.is_ident(s))
+}
+
+fn path_is_unsigned(path: &Path) -> bool {
+    ["u8", "u16", "u32", "u64", "u128", "usize"]
+        .iter()
+        .any(|s| path.is_ident(s))
+}
+
+fn path_is_signed(path: &Path) -> bool {
+    ["i8", "i16", "i32", "i64", "i128", "isize"]
+        .iter()
+        .any(|s| path.is_ident(s))
+}
+
+fn path_is_nonzero_signed(path: &Path) -> bool {
+    if let Some(l) = path.segments.last().map(|p| p.ident.to_string()) {
+        matches!(
+            l.as_str(),
+            "NonZeroIsize"
+                | "NonZeroI8"
+                | "NonZeroI16"
+                | "NonZeroI32"
+                | "NonZeroI64"
+                | "NonZeroI128",
+        )
+    } else {
+        false
+    }
+}
+
+fn path_is_nonzero_unsigned(path: &Path) -> bool {
+    if let Some(l) = path.segments.last().map(|p| p.ident.to_string()) {
+        matches!(
+            l.as_str(),
+            "NonZeroUsize"
+                | "NonZeroU8"
+                | "NonZeroU16"
+                | "NonZeroU32"
+                | "NonZeroU64"
+                | "NonZeroU128",
+        )
+    } else {
+        false
+    }
+}
+
+/// Convert a TokenStream representing some code to a reasonably formatted
+/// string of Rust code.
+///
+/// [TokenStream] has a `to_string`, but it adds spaces in places that don't
+/// look idiomatic, so this reimplements it in a way that looks better.
+///
+/// This is probably not correctly formatted for all Rust syntax, and only tries
+/// to cover cases that can emerge from the code we generate.
+fn tokens_to_pretty_string<T: ToTokens>(t: T) -> String {
+    use TokenTree::*;
+    let mut b = String::with_capacity(200);
+    let mut ts = t.to_token_stream().into_iter().peekable();
+    // True if the operator being emitted, which may have several punctuation characters,
+    // follows an operand and a space: then it's a binary operator like `a * 2` and should
+    // have a space after it too.
+    let mut spaced_operator = false;
+    let mut in_operator = false;
+    let mut after_operand = false;
+    // True between the pipes around the parameters of a closure, like `|a, b|`.
+    let mut in_closure_params = false;
+    while let Some(tt) = ts.next() {
+        match tt {
+            Punct(p)
+                if p.as_char() == '|' && (in_closure_params || !(after_operand || in_operator)) =>
+            {
+                // A pipe that doesn't follow an operand starts a closure, and the
+                // closure's body follows the closing pipe after a space.
+                b.push('|');
+                if in_closure_params {
+                    in_closure_params = false;
+                } else if p.spacing() == Spacing::Joint
+                    && matches!(ts.peek(), Some(Punct(next)) if next.as_char() == '|')
+                {
+                    ts.next();
+                    b.push('|');
+                } else {
+                    in_closure_params = true;
+                    continue;
+                }
+                in_operator = false;
+                after_operand = false;
+                if ts.peek().is_some() {
+                    b.push(' ');
+                }
+            }
+            Punct(p) => {
+                let pc = p.as_char();
+                if !in_operator {
+                    spaced_operator = after_operand && b.ends_with(' ');
+                }
+                after_operand = false;
+                in_operator = p.spacing() == Spacing::Joint;
+                b.push(pc);
+                if ts.peek().is_some()
+                    && (b.ends_with("->")
+                        || pc == ','
+                        || pc == ';'
+                        || (spaced_operator && !in_operator))
+                {
+                    b.push(' ');
+                }
+            }
+            Ident(_) | Literal(_) => {
+                in_operator = false;
+                after_operand = true;
+                match tt {
+                    Literal(l) => b.push_str(&l.to_string()),
+                    Ident(i) => b.push_str(&i.to_string()),
+                    _ => unreachable!(),
+                };
+                if let Some(next) = ts.peek() {
+                    match next {
+                        Ident(_) | Literal(_) => b.push(' '),
+                        Punct(p) => match p.as_char() {
+                            ',' | ';' | '<' | '>' | ':' | '.' | '!' => (),
+                            '|' if in_closure_params => (),
+                            _ => b.push(' '),
+                        },
+                        Group(g) if g.delimiter() == Delimiter::Brace => b.push(' '),
+                        Group(_) => (),
+                    }
+                }
+            }
+            Group(g) => {
+                in_operator = false;
+                after_operand = true;
+                // Non-empty braces are padded with spaces, like `{ a }`.
+                let pad = if g.delimiter() == Delimiter::Brace && !g.stream().is_empty() {
+                    " "
+                } else {
+                    ""
+                };
+                match g.delimiter() {
+                    Delimiter::Brace => b.push('{'),
+                    Delimiter::Bracket => b.push('['),
+                    Delimiter::Parenthesis => b.push('('),
+                    Delimiter::None => (),
+                }
+                b.push_str(pad);
+                b.push_str(&tokens_to_pretty_string(g.stream()));
+                b.push_str(pad);
+                match g.delimiter() {
+                    Delimiter::Brace => b.push('}'),
+                    Delimiter::Bracket => b.push(']'),
+                    Delimiter::Parenthesis => b.push(')'),
+                    Delimiter::None => (),
+                }
+                if g.delimiter() == Delimiter::Brace && matches!(ts.peek(), Some(Ident(_))) {
+                    b.push(' ');
+                }
+            }
+        }
+    }
+    debug_assert!(
+        !b.ends_with(' '),
+        "generated a trailing space: ts={ts:?}, b={b:?}",
+        ts = t.to_token_stream(),
+    );
+    b
+}
+
+/// If this looks like `Result<T, E>` (optionally with `Result` in some module), return `T`.
+fn result_ok_type(path: &Path) -> Option<&Type> {
+    match_first_type_arg(path, "Result")
+}
+
+/// If this is a path ending in `expected_ident`, return the first type argument.
+fn match_first_type_arg<'p>(path: &'p Path, expected_ident: &str) -> Option<&'p Type> {
+    let last = path.segments.last()?;
+    if last.ident == expected_ident {
+        if let PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) =
+            &last.arguments
+        {
+            if let Some(GenericArgument::Type(ok_type)) = args.first() {
+                return Some(ok_type);
+            }
+        }
+    }
+    None
+}
+
+/// True if this is a type whose replacements are known to implement `Hash` and `Ord`, so
+/// that they can be used as map keys.
+fn type_is_map_key(type_: &Type) -> bool {
+    match type_ {
+        Type::Path(syn::TypePath { qself: None, path }) => {
+            path.is_ident("bool")
+                || path.is_ident("String")
+                || path.is_ident("char")
+                || path_is_unsigned(path)
+                || path_is_signed(path)
+                || path_is_nonzero_signed(path)
+                || path_is_nonzero_unsigned(path)
+                || ["Option", "Box", "Vec"]
+                    .iter()
+                    .any(|name| match_first_type_arg(path, name).map_or(false, type_is_map_key))
+        }
+        Type::Reference(syn::TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) => matches!(&**elem, Type::Path(path) if path.path.is_ident("str")),
+        _ => false,
+    }
+}
+
+/// If this is a `HashMap<K, V>` or `BTreeMap<K, V>`, return the path without its type
+/// arguments, like `std::collections::HashMap`, and the key and value types.
+///
+/// Maps with a custom hasher are not matched, since they can't be made with `from`.
+fn map_type_args(path: &Path) -> Option<(Path, &Type, &Type)> {
+    let last = path.segments.last()?;
+    if last.ident != "HashMap" && last.ident != "BTreeMap" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) =
+        &last.arguments
+    else {
+        return None;
+    };
+    let (GenericArgument::Type(key_type), GenericArgument::Type(value_type)) =
+        args.iter().collect_tuple()?
+    else {
+        return None;
+    };
+    let mut map_path = path.clone();
+    map_path.segments.last_mut().unwrap().arguments = PathArguments::None;
+    Some((map_path, key_type, value_type))
+}
+
+/// If these are the bounds of `impl Iterator<Item = T>`, return `T`.
+fn iterator_item_type(bounds: &Punctuated<syn::TypeParamBound, Token![+]>) -> Option<&Type> {
+    bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(trait_bound) = bound else {
+            return None;
+        };
+        let last = trait_bound.path.segments.last()?;
+        if last.ident != "Iterator" {
+            return None;
+        }
+        let PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) =
+            &last.arguments
+        else {
+            return None;
+        };
+        args.iter().find_map(|arg| match arg {
+            GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(&assoc.ty),
+            _ => None,
+        })
+    })
+}
+
+/// If these are the bounds of `impl Fn(A, B) -> T`, or `FnMut` or `FnOnce`, return
+/// the number of arguments and the return type.
+///
+/// Bounds with higher-ranked lifetimes, like `for<'a> Fn(&'a str)`, are skipped.
+fn fn_trait_signature(
+    bounds: &Punctuated<syn::TypeParamBound, Token![+]>,
+) -> Option<(usize, &ReturnType)> {
+    bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(trait_bound) = bound else {
+            return None;
+        };
+        if trait_bound.lifetimes.is_some() {
+            return None;
+        }
+        let last = trait_bound.path.segments.last()?;
+        if !["Fn", "FnMut", "FnOnce"]
+            .iter()
+            .any(|name| last.ident == name)
+        {
+            return None;
+        }
+        let PathArguments::Parenthesized(args) = &last.arguments else {
+            return None;
+        };
+        Some((args.inputs.len(), &args.output))
+    })
+}
+
+/// Say why a function should be excluded because of its signature, if it should.
+///
+/// Functions with type parameters are excluded if `--exclude-generics` is given, since
+/// their mutants are often unviable for lack of bounds like `T: Default`.
+fn fn_sig_skip_reason(sig: &syn::Signature, options: &Options) -> Option<SkipReason> {
+    if sig.unsafety.is_some() {
+        trace!("Skip unsafe fn");
+        Some(SkipReason::Unsafe)
+    } else if options.exclude_generics && sig.generics.type_params().next().is_some() {
+        trace!("Skip generic fn");
+        Some(SkipReason::Generic)
+    } else {
+        None
+    }
+}
+
+/// True if the expression is a call to a `default` function with no arguments, like
+/// `Default::default()` or `Self::default()`.
+fn expr_is_default_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(call) => {
+            call.args.is_empty()
+                && matches!(&*call.func, Expr::Path(func) if path_ends_with(&func.path, "default"))
+        }
+        _ => false,
+    }
+}
+
+/// True if any of the attrs indicate that we should skip this node and everything inside it.
+fn attrs_excluded(attrs: &[Attribute]) -> bool {
+    attrs_skip_reason(attrs).is_some()
+}
+
+/// Say why the attrs indicate that we should skip this node, if they do.
+fn attrs_skip_reason(attrs: &[Attribute]) -> Option<SkipReason> {
+    if attrs
+        .iter()
+        .any(|attr| attr_is_cfg_test(attr) || attr_is_test(attr) || attr_is_bench(attr))
+    {
+        Some(SkipReason::Test)
+    } else if attrs.iter().any(attr_is_mutants_skip) {
+        Some(SkipReason::MutantsSkip)
+    } else {
+        None
+    }
+}
+
+/// True if the block (e.g. the contents of a function) is empty.
+fn block_is_empty(block: &syn::Block) -> bool {
+    block.stmts.is_empty()
+}
+
+/// True if the block always diverges: it ends in a call to a macro like `panic!` or
+/// `unreachable!`, a call to `process::exit`, or a `loop` with no `break`, and has no
+/// `return` or `?` that could leave it earlier.
+///
+/// Replacing the body of such a function just makes it return where it never did
+/// before, which few callers can observe, so these mutants are mostly noise.
+fn block_diverges(block: &syn::Block) -> bool {
+    fn contains_token(tokens: TokenStream, pred: &dyn Fn(&TokenTree) -> bool) -> bool {
+        tokens.into_iter().any(|token| match &token {
+            TokenTree::Group(group) => contains_token(group.stream(), pred),
+            _ => pred(&token),
+        })
+    }
+    let macro_diverges = |mac: &syn::Macro| {
+        ["panic", "unreachable", "todo", "unimplemented"]
+            .iter()
+            .any(|name| path_ends_with(&mac.path, name))
+    };
+    let last_diverges = match block.stmts.last() {
+        Some(syn::Stmt::Macro(stmt_macro)) => macro_diverges(&stmt_macro.mac),
+        Some(syn::Stmt::Expr(expr, _)) => match expr {
+            Expr::Macro(expr_macro) => macro_diverges(&expr_macro.mac),
+            Expr::Loop(expr_loop) => !contains_token(
+                expr_loop.body.to_token_stream(),
+                &|t| matches!(t, TokenTree::Ident(ident) if ident == "break"),
+            ),
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(syn::ExprPath { path, .. }) => {
+                    path_is(path, &["process", "exit"])
+                        || path_is(path, &["std", "process", "exit"])
+                }
+                _ => false,
+            },
+            _ => false,
+        },
+        _ => false,
+    };
+    last_diverges
+        && !contains_token(block.to_token_stream(), &|t| match t {
+            TokenTree::Ident(ident) => ident == "return",
+            TokenTree::Punct(punct) => punct.as_char() == '?',
+            _ => false,
+        })
+}
+
+/// True if the attribute looks like `#[cfg(test)]`, or has "test"
+/// anywhere in it.
+fn attr_is_cfg_test(attr: &Attribute) -> bool {
+    if !path_is(attr.path(), &["cfg"]) {
+        return false;
+    }
+    let mut contains_test = false;
+    if let Err(err) = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("test") {
+            contains_test = true;
+        }
+        Ok(())
+    }) {
+        debug!(
+            ?err,
+            ?attr,
+            "Attribute is not in conventional form; skipped"
+        );
+        return false;
+    }
+    contains_test
+}
+
+/// True if the attribute is a `cfg` that's known to be false in the build, so that the
+/// item won't be compiled, like `#[cfg(not(debug_assertions))]` in the dev profile.
+fn attr_is_cfg_false(attr: &Attribute, debug_assertions: Option<bool>) -> bool {
+    if !path_is(attr.path(), &["cfg"]) {
+        return false;
+    }
+    match attr.parse_args::<syn::Meta>() {
+        Ok(meta) => eval_cfg(&meta, debug_assertions) == Some(false),
+        Err(err) => {
+            debug!(?err, ?attr, "Can't parse cfg attribute");
+            false
+        }
+    }
+}
+
+/// Evaluate a cfg predicate, or return None if its value isn't known.
+///
+/// Only `debug_assertions` has a known value, possibly combined with `not`, `all`,
+/// and `any`. Features and other cfgs are unknown.
+fn eval_cfg(meta: &syn::Meta, debug_assertions: Option<bool>) -> Option<bool> {
+    let syn::Meta::List(list) = meta else {
+        return match meta {
+            syn::Meta::Path(path) if path.is_ident("debug_assertions") => debug_assertions,
+            _ => None,
+        };
+    };
+    let values = list
+        .parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated)
+        .ok()?
+        .iter()
+        .map(|meta| eval_cfg(meta, debug_assertions))
+        .collect_vec();
+    // `all` is false if any part is false, and `any` is true if any part is true, even
+    // if other parts are unknown.
+    let combine = |decisive: bool| {
+        if values.contains(&Some(decisive)) {
+            Some(decisive)
+        } else if values.iter().all(Option::is_some) {
+            Some(!decisive)
+        } else {
+            None
+        }
+    };
+    if list.path.is_ident("not") && values.len() == 1 {
+        values[0].map(|value| !value)
+    } else if list.path.is_ident("all") {
+        combine(false)
+    } else if list.path.is_ident("any") {
+        combine(true)
+    } else {
+        None
+    }
+}
+
+/// True if the attribute is `#[doc(hidden)]`, possibly along with other doc attributes.
+fn attr_is_doc_hidden(attr: &Attribute) -> bool {
+    if !path_is(attr.path(), &["doc"]) {
+        return false;
+    }
+    let mut hidden = false;
+    // Doc comments are `#[doc = "..."]`, which isn't a list and so isn't hidden.
+    let _ = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("hidden") {
+            hidden = true;
+        } else if meta.input.peek(Token![=]) {
+            // Like `alias = "name"`.
+            meta.value()?.parse::<Expr>()?;
+        } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+            // Like `cfg(feature = "x")`.
+            meta.input.parse::<proc_macro2::TokenTree>()?;
+        }
+        Ok(())
+    });
+    hidden
+}
+
+/// True if the attribute is `#[derive(...)]` including `Default`.
+fn attr_derives_default(attr: &Attribute) -> bool {
+    if !attr.path().is_ident("derive") {
+        return false;
+    }
+    let mut default = false;
+    let _ = attr.parse_nested_meta(|meta| {
+        if path_ends_with(&meta.path, "Default") {
+            default = true;
+        }
+        Ok(())
+    });
+    default
+}
+
+/// True if the attribute is `#[test]`.
+fn attr_is_test(attr: &Attribute) -> bool {
+    attr.path().is_ident("test")
+}
+
+/// True if the attribute marks a benchmark: `#[bench]`, `#[divan::bench]`, or
+/// `#[criterion]` from `criterion-macro`.
+fn attr_is_bench(attr: &Attribute) -> bool {
+    let path = attr.path();
+    path.is_ident("bench") || path.is_ident("criterion") || path_is(path, &["divan", "bench"])
+}
+
+fn path_is(path: &syn::Path, idents: &[&str]) -> bool {
+    path.segments.iter().map(|ps| &ps.ident).eq(idents.iter())
+}
+
+/// True if the attribute contains `mutants::skip`.
+///
+/// This for example returns true for `#[mutants::skip] or `#[cfg_attr(test, mutants::skip)]`.
+fn attr_is_mutants_skip(attr: &Attribute) -> bool {
+    if path_is(attr.path(), &["mutants", "skip"]) {
+        return true;
+    }
+    if !path_is(attr.path(), &["cfg_attr"]) {
+        return false;
+    }
+    let mut skip = false;
+    if let Err(err) = attr.parse_nested_meta(|meta| {
+        if path_is(&meta.path, &["mutants", "skip"]) {
+            skip = true
+        }
+        Ok(())
+    }) {
+        debug!(
+            ?attr,
+            ?err,
+            "Attribute is not a path with attributes; skipping"
+        );
+        return false;
+    }
+    skip
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use camino::Utf8Path;
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::{parse_quote, Expr, Lit, ReturnType};
+
+    use super::{
+        cap_mutants_per_function, duration_literal_replacements, prioritize_untested,
+        return_type_replacements, tokens_to_pretty_string, walk_file,
+    };
+    use crate::source::{Package, SourceFile};
+    use crate::{Mutant, Options};
+
+    /// A tool that finds the same root files as Cargo, but returns them in reverse
+    /// order, as if they were listed from a filesystem with a different order.
+    #[derive(Debug)]
+    struct ReversedRootsTool(crate::CargoTool);
+
+    impl crate::Tool for ReversedRootsTool {
+        fn name(&self) -> &str {
+            "reversed"
+        }
+
+        fn find_root(&self, path: &Utf8Path) -> crate::Result<camino::Utf8PathBuf> {
+            self.0.find_root(path)
+        }
+
+        fn root_files(&self, path: &Utf8Path) -> crate::Result<Vec<Arc<SourceFile>>> {
+            let mut files = self.0.root_files(path)?;
+            files.reverse();
+            Ok(files)
+        }
+
+        fn compose_argv(
+            &self,
+            build_dir: &crate::BuildDir,
+            scenario: &crate::Scenario,
+            phase: crate::Phase,
+            options: &Options,
+        ) -> crate::Result<Vec<String>> {
+            self.0.compose_argv(build_dir, scenario, phase, options)
+        }
+
+        fn compose_env(
+            &self,
+            scenario: &crate::Scenario,
+            phase: crate::Phase,
+            options: &Options,
+        ) -> crate::Result<Vec<(String, String)>> {
+            self.0.compose_env(scenario, phase, options)
+        }
+
+        fn is_transient_failure(&self, output: &str) -> bool {
+            self.0.is_transient_failure(output)
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinked_module_is_mutated_once() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        use crate::Tool;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = Utf8Path::from_path(tmp.path()).unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"symlinked\"\nversion = \"0.0.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/lib.rs"), "mod shared;\nmod linked;\n").unwrap();
+        fs::write(
+            root.join("src/shared.rs"),
+            "pub fn one() -> u32 {\n    1\n}\n",
+        )
+        .unwrap();
+        symlink("shared.rs", root.join("src/linked.rs")).unwrap();
+
+        let tool = crate::CargoTool::new();
+        let source_tree = tool.find_root(root).unwrap();
+        let discovered = super::walk_tree(&tool, &source_tree, &Options::default()).unwrap();
+        assert_eq!(
+            discovered
+                .mutants
+                .iter()
+                .map(|m| m.to_string())
+                .collect_vec(),
+            ["src/shared.rs:1: replace one -> u32 with 0"]
+        );
+        assert_eq!(
+            discovered
+                .files
+                .iter()
+                .map(|f| f.tree_relative_slashes())
+                .collect_vec(),
+            ["src/lib.rs", "src/shared.rs"]
+        );
+    }
+
+    #[test]
+    fn discovery_order_does_not_depend_on_root_file_order() {
+        use crate::Tool;
+
+        let cargo_tool = crate::CargoTool::new();
+        let root = cargo_tool
+            .find_root(Utf8Path::new("testdata/tree/workspace"))
+            .unwrap();
+        let root_files = cargo_tool.root_files(&root).unwrap();
+        assert!(root_files.len() > 1);
+
+        let options = Options::default();
+        let describe = |discovered: super::Discovered| {
+            (
+                discovered
+                    .files
+                    .iter()
+                    .map(|f| f.tree_relative_slashes())
+                    .collect_vec(),
+                discovered
+                    .mutants
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect_vec(),
+            )
+        };
+        let forward = describe(super::walk_tree(&cargo_tool, &root, &options).unwrap());
+        let reversed = describe(
+            super::walk_tree(&ReversedRootsTool(crate::CargoTool::new()), &root, &options).unwrap(),
+        );
+        assert_eq!(forward, reversed);
+        assert!(forward.0.iter().tuple_windows().all(|(a, b)| a < b));
+    }
+
+    /// Find all the mutants in some source code, given as a string.
+    fn mutants_in_code(code: &str, options: &Options) -> Vec<Mutant> {
+        mutants_in_code_with_error_exprs(code, options, &[])
+    }
+
+    /// Find all the mutants in some source code, given as a string, using these error
+    /// values for functions returning `Result`.
+    fn mutants_in_code_with_error_exprs(
+        code: &str,
+        options: &Options,
+        error_exprs: &[Expr],
+    ) -> Vec<Mutant> {
+        let source_file = SourceFile {
+            package: Arc::new(Package {
+                name: "imaginary-package".to_owned(),
+                relative_manifest_path: "Cargo.toml".into(),
+                workspace_dependencies: Vec::new(),
+            }),
+            tree_relative_path: "src/lib.rs".parse().unwrap(),
+            code: Arc::new(code.to_owned()),
+        };
+        walk_file(
+            Utf8Path::new("/nonexistent"),
+            Arc::new(source_file),
+            options,
+            error_exprs,
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn mutant_ids_are_unique_and_stable_when_lines_move() {
+        let code = indoc::indoc! { r#"
+            fn f(a: Option<u32>, b: Option<u32>) -> u32 {
+                a.unwrap_or(0) + b.unwrap_or(0)
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default());
+        let ids = mutants.iter().map(|m| m.id.as_str()).collect_vec();
+        assert!(ids.iter().all_unique(), "{ids:?}");
+        let default_value_ids = mutants
+            .iter()
+            .filter(|m| m.genre == crate::Genre::DefaultValue)
+            .map(|m| m.id.as_str())
+            .collect_vec();
+        assert_eq!(default_value_ids.len(), 2);
+        assert_eq!(default_value_ids[0].len(), 12);
+        assert_eq!(default_value_ids[1], format!("{}-2", default_value_ids[0]));
+
+        let moved_code = format!("// A new comment\n\n{code}");
+        let moved_ids = mutants_in_code(&moved_code, &Options::default())
+            .into_iter()
+            .map(|m| m.id)
+            .collect_vec();
+        assert_eq!(ids, moved_ids);
+    }
+
+    /// Assert that these are the replacements for a return type, with no error values or
+    /// types excluded from `Default::default()`.
+    fn check_replacements(return_type: ReturnType, expected: &[&str]) {
+        let reps = return_type_replacements(&return_type, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    /// Find the mutants of a genre in some source code, when that genre is requested.
+    fn requested_mutants_of_genre(code: &str, genre: crate::Genre) -> Vec<Mutant> {
+        let options = Options {
+            opt_in_genres: vec![genre],
+            ..Default::default()
+        };
+        mutants_in_code(code, &options)
+            .into_iter()
+            .filter(|m| m.genre == genre)
+            .collect_vec()
+    }
+
+    /// Describe all the mutants of a given genre in some source code.
+    fn describe_mutants_of_genre(code: &str, genre: crate::Genre) -> Vec<String> {
+        mutants_in_code(code, &Options::default())
+            .into_iter()
+            .filter(|m| m.genre == genre)
+            .map(|m| m.to_string())
+            .collect_vec()
+    }
+
+    #[test]
+    fn only_genres_enabled_by_default_are_generated_unless_requested() {
+        let code = indoc::indoc! { r#"
+            use std::time::Duration;
+
+            lazy_static! {
+                static ref LIMIT: usize = 10;
+            }
+
+            struct Buffer;
+
+            impl Buffer {
+                const SIZE: usize = 100;
+            }
+
+            fn config(verbose: bool) -> Config {
+                Config {
+                    verbose: true,
+                    ..Default::default()
+                }
+            }
+
+            fn point(x: i32) -> Point {
+                Point { x: 1, y: x }
+            }
+
+            fn parse(s: &str) -> Result<usize, Error> {
+                if s.is_empty() {
+                    return Err(Error);
+                }
+                Ok(s.len())
+            }
+
+            fn update(items: &mut Vec<u32>, a: &mut u32, b: &mut u32, r: Result<u32, ()>, q: bool) {
+                let x = a.unwrap_or(0).saturating_add(1);
+                let y = if x > 3 { 1 } else { 2 };
+                let d = Duration::from_secs(5);
+                let s = format!("items");
+                let o = (x > 1).then_some(y);
+                let v = r.ok();
+                std::mem::swap(a, b);
+                items.push(y);
+                let evens = items.iter().filter(|i| *i % 2 == 0).collect::<Vec<_>>();
+                let both = q && items.is_empty();
+                match x {
+                    1 => println!("one"),
+                    2 => println!("two"),
+                    _ => println!("many"),
+                }
+                while let Some(i) = items.pop() {
+                    println!("{i}");
+                }
+            }
+        "# };
+        let default_genres = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .map(|m| m.genre)
+            .collect::<HashSet<_>>();
+        for genre in crate::Genre::all() {
+            assert_eq!(
+                default_genres.contains(&genre),
+                genre.enabled_by_default(),
+                "{genre:?}"
+            );
+            assert!(
+                !requested_mutants_of_genre(code, genre).is_empty(),
+                "{genre:?} is not generated when requested"
+            );
+        }
+    }
+
+    #[test]
+    fn delete_fields_before_default_struct_base() {
+        let code = indoc::indoc! { r#"
+            fn config() -> Config {
+                Config {
+                    verbose: true,
+                    name,
+                    limit: compute_limit(
+                        3,
+                    ),
+                    ..Default::default()
+                }
+            }
+
+            fn other(base: Config) -> Config {
+                Config { verbose: true, ..base }
+            }
+
+            fn complete() -> Point {
+                Point { x: 1, y: 2 }
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .filter(|m| m.genre == crate::Genre::StructUpdate)
+            .collect_vec();
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:3: delete field verbose: true in config",
+                "src/lib.rs:4: delete field name in config",
+                "src/lib.rs:5: delete field limit: compute_limit( 3, ) in config",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "        verbose: true,\n",
+                "         /* ~ changed by cargo-mutants ~ */\n"
+            )
+        );
+    }
+
+    #[test]
+    fn set_fields_of_returned_struct_literals_to_default() {
+        let code = indoc::indoc! { r#"
+            fn point(y: i32) -> Point {
+                Point { x: 1, y }
+            }
+
+            fn parse(s: &str) -> Result<Config> {
+                if s.is_empty() {
+                    return Ok(Config { verbose: true, limit: Default::default() });
+                }
+                let unused = Config { verbose: false, limit: 3 };
+                Ok(Config {
+                    verbose: false,
+                    limit: s.len(),
+                })
+            }
+
+            fn update(base: Config) -> Config {
+                Config { verbose: true, ..Default::default() }
+            }
+
+            const fn origin() -> Point {
+                Point { x: 0, y: 0 }
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::StructFieldDefault);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace x: 1 with x: Default::default() in point",
+                "src/lib.rs:2: replace y with y: Default::default() in point",
+                "src/lib.rs:7: replace verbose: true with verbose: Default::default() in parse",
+                "src/lib.rs:11: replace verbose: false with verbose: Default::default() in parse",
+                "src/lib.rs:12: replace limit: s.len() with limit: Default::default() in parse",
+            ]
+        );
+        assert_eq!(
+            mutants[1].mutated_code(),
+            code.replace(
+                "Point { x: 1, y }",
+                "Point { x: 1, y: Default::default() /* ~ changed by cargo-mutants ~ */ }"
+            )
+        );
+    }
+
+    #[test]
+    fn associated_consts_of_primitive_types_are_mutated() {
+        let code = indoc::indoc! { r#"
+            struct Buffer;
+
+            impl Buffer {
+                const SIZE: usize = 100;
+                const SMALL: u8 = 255;
+                const ONE: i32 = 1;
+                const VERBOSE: bool = false;
+                const NAME: &'static str = "buffer";
+                const LIMIT: Limit = Limit(3);
+                const COMPUTED: u32 = 2 * 3;
+            }
+
+            impl Limits for Buffer {
+                const MAX: u64 = 1u64;
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::AssocConst),
+            [
+                "src/lib.rs:4: replace 100 with 0 in Buffer::SIZE",
+                "src/lib.rs:4: replace 100 with 99 in Buffer::SIZE",
+                "src/lib.rs:4: replace 100 with 101 in Buffer::SIZE",
+                "src/lib.rs:5: replace 255 with 0 in Buffer::SMALL",
+                "src/lib.rs:5: replace 255 with 254 in Buffer::SMALL",
+                "src/lib.rs:6: replace 1 with 0 in Buffer::ONE",
+                "src/lib.rs:6: replace 1 with 2 in Buffer::ONE",
+                "src/lib.rs:7: replace false with true in Buffer::VERBOSE",
+                "src/lib.rs:14: replace 1u64 with 0u64 in <impl Limits for Buffer>::MAX",
+                "src/lib.rs:14: replace 1u64 with 2u64 in <impl Limits for Buffer>::MAX",
+            ]
+        );
+    }
+
+    #[test]
+    fn consts_used_as_array_lengths_are_not_made_zero() {
+        let code = indoc::indoc! { r#"
+            struct Buffer {
+                data: [u8; Buffer::SIZE],
+            }
+
+            impl Buffer {
+                const SIZE: usize = 16;
+                const ONE: usize = 1;
+                const LIMIT: usize = 4;
+
+                fn new() -> Buffer {
+                    let _pad = [0u8; Self::ONE];
+                    Buffer { data: [0; Self::SIZE] }
+                }
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::AssocConst),
+            [
+                "src/lib.rs:6: replace 16 with 15 in Buffer::SIZE",
+                "src/lib.rs:6: replace 16 with 17 in Buffer::SIZE",
+                "src/lib.rs:7: replace 1 with 2 in Buffer::ONE",
+                "src/lib.rs:8: replace 4 with 0 in Buffer::LIMIT",
+                "src/lib.rs:8: replace 4 with 3 in Buffer::LIMIT",
+                "src/lib.rs:8: replace 4 with 5 in Buffer::LIMIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn diverging_functions_are_not_replaced() {
+        let code = indoc::indoc! { r#"
+            fn stub() -> u32 {
+                todo!()
+            }
+
+            fn fail(code: i32) -> String {
+                eprintln!("failed");
+                std::process::exit(code)
+            }
+
+            fn spin() -> bool {
+                loop {
+                    std::hint::spin_loop();
+                }
+            }
+
+            fn checked(a: u32) -> u32 {
+                if a > 3 {
+                    return a;
+                }
+                panic!("too small");
+            }
+
+            fn until_done() -> u32 {
+                loop {
+                    break 3;
+                }
+            }
+        "# };
+        assert_eq!(
+            mutants_in_code(code, &Options::default())
+                .iter()
+                .filter(|m| m.genre == crate::Genre::FnValue)
+                .map(|m| m.to_string())
+                .collect_vec(),
+            [
+                "src/lib.rs:16: replace checked -> u32 with 0",
+                "src/lib.rs:16: replace checked -> u32 with 1",
+                "src/lib.rs:23: replace until_done -> u32 with 0",
+                "src/lib.rs:23: replace until_done -> u32 with 1",
+            ]
+        );
+        let options = Options {
+            mutate_diverging_fns: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            mutants_in_code(code, &options)
+                .iter()
+                .filter(|m| m.genre == crate::Genre::FnValue)
+                .count(),
+            10
+        );
+    }
+
+    #[test]
+    fn benchmark_functions_are_skipped() {
+        let code = indoc::indoc! { r#"
+            #[bench]
+            fn bench_parse(b: &mut Bencher) -> u32 {
+                b.iter(|| parse("1"));
+                1
+            }
+
+            #[divan::bench]
+            fn divan_parse() -> u32 {
+                parse("2")
+            }
+
+            #[criterion(config())]
+            fn criterion_parse(c: &mut Criterion) -> u32 {
+                c.bench_function("parse", |b| b.iter(|| parse("3")));
+                3
+            }
+
+            fn parse(s: &str) -> u32 {
+                s.parse().unwrap()
+            }
+        "# };
+        let names = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .map(|m| m.function_name().to_owned())
+            .unique()
+            .collect_vec();
+        assert_eq!(names, ["parse"]);
+    }
+
+    #[test]
+    fn delete_statements_from_unit_functions() {
+        let code = indoc::indoc! { r#"
+            fn save(&mut self) {
+                let path = self.path();
+                self.flush();
+                println!("saving {path}");
+                if self.dirty {
+                    write(path);
+                }
+                self.dirty = false
+            }
+
+            fn one(&self) -> () {
+                self.flush();
+            }
+
+            fn count(&self) -> usize {
+                self.flush();
+                self.n
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::StatementDelete);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:3: delete self.flush(); in save",
+                "src/lib.rs:4: delete println!(\"saving {path}\"); in save",
+                "src/lib.rs:5: delete if self.dirty { write(path); } in save",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "    self.flush();\n    println",
+                "     /* ~ changed by cargo-mutants ~ */\n    println"
+            )
+        );
+    }
+
+    #[test]
+    fn break_from_while_let_loops() {
+        let code = indoc::indoc! { r#"
+            fn total(items: &mut Vec<u32>) {
+                let mut sum = 0;
+                while let Some(x) = items.pop() {
+                    sum += x
+                }
+                while sum > 10 {
+                    sum -= 1;
+                }
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .filter(|m| m.genre == crate::Genre::WhileLetBreak)
+            .collect_vec();
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:3: replace while let loop body with break in total",
+                "src/lib.rs:5: break after the first iteration of while let loop in total",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "{\n        sum += x\n    }",
+                "{ break } /* ~ changed by cargo-mutants ~ */"
+            )
+        );
+        assert_eq!(
+            mutants[1].mutated_code(),
+            code.replace(
+                "sum += x\n    }",
+                "sum += x\n    ; break } /* ~ changed by cargo-mutants ~ */"
+            )
+        );
+    }
+
+    #[test]
+    fn replace_predicate_closure_bodies() {
+        let code = indoc::indoc! { r#"
+            fn positive(v: &[i32]) -> Vec<i32> {
+                let first = v.iter().find(|x| **x > 100);
+                let all = v.iter().skip_while(|_| true).count();
+                v.iter().filter(|x| **x > 0).copied().collect()
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .filter(|m| m.genre == crate::Genre::PredicateClosure)
+            .collect_vec();
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace **x > 100 with true in positive",
+                "src/lib.rs:2: replace **x > 100 with false in positive",
+                "src/lib.rs:3: replace true with false in positive",
+                "src/lib.rs:4: replace **x > 0 with true in positive",
+                "src/lib.rs:4: replace **x > 0 with false in positive",
+            ]
+        );
+        assert_eq!(
+            mutants[4].mutated_code(),
+            code.replace(
+                "filter(|x| **x > 0)",
+                "filter(|x| false /* ~ changed by cargo-mutants ~ */)"
+            )
+        );
+    }
+
+    #[test]
+    fn cap_mutants_in_each_function() {
+        let code = indoc::indoc! { r#"
+            fn sign(a: i32) -> i32 {
+                if a < 0 { -1 } else if a > 0 { 1 } else { 0 }
+            }
+
+            fn yes() -> bool {
+                false
+            }
+        "# };
+        let mut mutants = mutants_in_code(code, &Options::default());
+        cap_mutants_per_function(&mut mutants, 2);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:1: replace sign -> i32 with 0",
+                "src/lib.rs:1: replace sign -> i32 with 1",
+                "src/lib.rs:5: replace yes -> bool with true",
+            ]
+        );
+    }
+
+    #[test]
+    fn swap_result_ok_and_err() {
+        let code = indoc::indoc! { r#"
+            fn parse(s: &str) -> Option<u32> {
+                let e = s.parse::<u8>().err();
+                s.parse().ok()
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::ResultToOption);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace s.parse::<u8>().err() with s.parse::<u8>().ok() in parse",
+                "src/lib.rs:2: replace s.parse::<u8>().err() with None in parse",
+                "src/lib.rs:3: replace s.parse().ok() with s.parse().err() in parse",
+                "src/lib.rs:3: replace s.parse().ok() with None in parse",
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_or_replace_mem_calls() {
+        let code = indoc::indoc! { r#"
+            use std::mem;
+
+            fn rotate(a: &mut Vec<u8>, b: &mut Vec<u8>) -> Vec<u8> {
+                mem::swap(a, b);
+                let old = std::mem::replace(a, vec![1]);
+                swap(a, b);
+                mem::take(b)
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::MemCall);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:4: delete mem::swap(a, b); in rotate",
+                "src/lib.rs:5: replace std::mem::replace(a, vec![1]) with vec![1] in rotate",
+                "src/lib.rs:7: replace mem::take(b) with Default::default() in rotate",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "    mem::swap(a, b);\n",
+                "     /* ~ changed by cargo-mutants ~ */\n"
+            )
+        );
+    }
+
+    #[test]
+    fn swap_short_circuit_operators() {
+        let code = indoc::indoc! { r#"
+            fn check(a: bool, v: &[u32], n: u32) -> bool {
+                let x = a && v[0] > 1;
+                let y = n == 1 || v.is_empty();
+                let z = a || b;
+                let w = v.is_empty() & check(a, v, n);
+                let mask = n & f(n);
+                x == a | v.contains(&n)
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::BinaryOperator);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace a && v[0] > 1 with a & (v[0] > 1) in check",
+                "src/lib.rs:3: replace n == 1 || v.is_empty() with (n == 1) | v.is_empty() in check",
+                "src/lib.rs:5: replace v.is_empty() & check(a, v, n) with v.is_empty() && check(a, v, n) in check",
+                "src/lib.rs:7: replace a | v.contains(&n) with (a || v.contains(&n)) in check",
+            ]
+        );
+    }
+
+    #[test]
+    fn change_duration_literals() {
+        let code = indoc::indoc! { r#"
+            use std::time::Duration;
+
+            fn timeouts(n: u64) -> [Duration; 6] {
+                [
+                    Duration::from_secs(5),
+                    std::time::Duration::from_millis(0),
+                    Duration::from_secs_f64(1.5),
+                    Duration::from_micros(10_u64),
+                    Duration::from_secs(n),
+                    Duration::new(5, 0),
+                ]
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::DurationLiteral)
+            .iter()
+            .map(|m| m.to_string())
+            .collect_vec();
+        assert_eq!(
+            mutants,
+            [
+                "src/lib.rs:5: replace 5 with 0 in timeouts",
+                "src/lib.rs:5: replace 5 with 10 in timeouts",
+                "src/lib.rs:6: replace 0 with 1 in timeouts",
+                "src/lib.rs:7: replace 1.5 with 0.0 in timeouts",
+                "src/lib.rs:7: replace 1.5 with 3.0 in timeouts",
+                "src/lib.rs:8: replace 10_u64 with 0u64 in timeouts",
+                "src/lib.rs:8: replace 10_u64 with 20u64 in timeouts",
+            ]
+        );
+    }
+
+    #[test]
+    fn duration_literals_are_not_doubled_to_infinity() {
+        let replacements = |lit: Lit| {
+            duration_literal_replacements(&lit)
+                .iter()
+                .map(tokens_to_pretty_string)
+                .collect_vec()
+        };
+        assert_eq!(replacements(parse_quote! { 1e308 }), ["0.0"]);
+        assert_eq!(replacements(parse_quote! { 3e38f32 }), ["0.0f32"]);
+        assert_eq!(
+            replacements(parse_quote! { 1e38f32 }),
+            ["0.0f32", "2e38f32"]
+        );
+    }
+
+    #[test]
+    fn delete_collection_pushes() {
+        let code = indoc::indoc! { r#"
+            fn evens(n: u32) -> Vec<u32> {
+                let mut v = Vec::new();
+                let mut seen = HashSet::new();
+                for i in 0..n {
+                    if i % 2 == 0 {
+                        v.push(i);
+                    }
+                    let new = seen.insert(i);
+                }
+                v.extend([100, 102]);
+                v.sort();
+                v
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::CollectionPush);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:6: delete v.push(i); in evens",
+                "src/lib.rs:10: delete v.extend([100, 102]); in evens",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "            v.push(i);\n",
+                "             /* ~ changed by cargo-mutants ~ */\n"
+            )
+        );
+    }
+
+    #[test]
+    fn collection_push_deletions_are_capped_per_function() {
+        let pushes = (0..30)
+            .map(|i| format!("    v.push({i});\n"))
+            .collect::<String>();
+        let code = format!("fn many(v: &mut Vec<u32>) {{\n{pushes}}}\n\nfn one(v: &mut Vec<u32>) {{\n    v.push(1);\n}}\n");
+        let counts = requested_mutants_of_genre(&code, crate::Genre::CollectionPush)
+            .iter()
+            .counts_by(|m| m.function_name().to_owned());
+        assert_eq!(counts["many"], 20);
+        assert_eq!(counts["one"], 1);
+    }
+
+    #[test]
+    fn original_tail_expression_of_fn_value_mutants() {
+        let code = indoc::indoc! { r#"
+            fn total(v: &[u32]) -> u32 {
+                let n = v.len() as u32;
+                v.iter()
+                    .sum::<u32>()
+                    + n
+            }
+
+            fn log(s: &str) {
+                println!("{s}");
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default());
+        assert_eq!(
+            mutants
+                .iter()
+                .map(|m| (m.to_string(), m.original_tail_expression()))
+                .collect_vec(),
+            [
+                (
+                    "src/lib.rs:1: replace total -> u32 with 0".to_owned(),
+                    Some("v.iter() .sum::<u32>() + n".to_owned())
+                ),
+                (
+                    "src/lib.rs:1: replace total -> u32 with 1".to_owned(),
+                    Some("v.iter() .sum::<u32>() + n".to_owned())
+                ),
+                ("src/lib.rs:8: replace log with ()".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn change_collect_target() {
+        let code = indoc::indoc! { r#"
+            fn names(v: &[&str], x: &[f64]) -> usize {
+                let all = v.iter().collect::<Vec<_>>();
+                let unique = v.iter().copied().collect::<HashSet<&str>>();
+                let floats = x.iter().collect::<Vec<f64>>();
+                let hashed = v.iter().collect::<HashSet<_, MyHasher>>();
+                let map = v.iter().map(|s| (s, 1)).collect::<HashMap<_, _>>();
+                all.len() + unique.len()
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::CollectTarget);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace Vec<_> with std::collections::HashSet<_> in names",
+                "src/lib.rs:3: replace HashSet<&str> with Vec<&str> in names",
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "collect::<Vec<_>>()",
+                "collect::<std::collections::HashSet<_> /* ~ changed by cargo-mutants ~ */>()"
+            )
+        );
+    }
+
+    #[test]
+    fn swap_match_arm_patterns() {
+        let code = indoc::indoc! { r#"
+            fn name(n: u8, color: Option<Color>) -> &'static str {
+                let _ = match color {
+                    None => "none",
+                    Some(_) => "some",
+                };
+                match n {
+                    0 => "zero",
+                    1 if color.is_some() => "one",
+                    2 | 3 => "few",
+                    4 => "four",
+                    5 => "four",
+                    _ => "many",
+                }
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::MatchArmSwap);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:7: swap match arm patterns 0 and 1 in name",
+                "src/lib.rs:12: replace \"many\" with unreachable!() in name",
+            ]
+        );
+        assert_eq!(
+            mutants[0].swapped_patterns,
+            Some(("0".to_owned(), "1".to_owned()))
+        );
+        assert_eq!(mutants[1].swapped_patterns, None);
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "0 => \"zero\",\n        1 if",
+                "1 => \"zero\",\n        0 /* ~ changed by cargo-mutants ~ */ if"
+            )
+        );
+    }
+
+    #[test]
+    fn make_match_wildcard_arms_unreachable() {
+        let code = indoc::indoc! { r#"
+            fn describe(n: u8, shape: Shape) -> u32 {
+                let sides = match shape {
+                    Shape::Triangle => 3,
+                    _ => {
+                        log("other");
+                        4
+                    }
+                    Shape::Circle => 0,
+                };
+                let guarded = match n {
+                    0 => 1,
+                    _ if n > 10 => 2,
+                    other => other.into(),
+                };
+                let strict = match n {
+                    0 => 1,
+                    _ => unreachable!("only zero"),
+                };
+                match n {
+                    0 => sides,
+                    _ => sides * 2,
+                }
+            }
+        "# };
+        let mutants = requested_mutants_of_genre(code, crate::Genre::MatchArmSwap)
+            .into_iter()
+            .filter(|m| m.swapped_patterns.is_none())
+            .collect_vec();
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:4: replace { log(\"other\"); 4 } with { unreachable!() } in describe",
+                "src/lib.rs:21: replace sides * 2 with unreachable!() in describe",
+            ]
+        );
+        assert_eq!(
+            mutants[1].mutated_code(),
+            code.replace(
+                "_ => sides * 2,",
+                "_ => unreachable!() /* ~ changed by cargo-mutants ~ */,"
+            )
+        );
+    }
+
+    #[test]
+    fn replacement_equal_to_body_is_skipped() {
+        let code = indoc::indoc! { r#"
+            fn always() -> bool {
+                true
+            }
+
+            fn none() -> Option<u32> { None }
+
+            fn logged() -> bool {
+                log("hi");
+                true
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::FnValue),
+            [
+                "src/lib.rs:1: replace always -> bool with false",
+                "src/lib.rs:5: replace none -> Option<u32> with Some(0)",
+                "src/lib.rs:5: replace none -> Option<u32> with Some(1)",
+                "src/lib.rs:7: replace logged -> bool with true",
+                "src/lib.rs:7: replace logged -> bool with false",
+            ]
+        );
+    }
+
+    #[test]
+    fn anyhow_results_get_anyhow_errors_by_default() {
+        let code = indoc::indoc! { r#"
+            use anyhow::{Context, Result};
+
+            fn parse(s: &str) -> Result<u32> {
+                s.parse().context("parse")
+            }
+
+            fn explicit() -> anyhow::Result<bool> {
+                Ok(check())
+            }
+
+            fn io() -> std::io::Result<()> {
+                write()
+            }
+        "# };
+        assert_eq!(
+            mutants_in_code(code, &Options::default())
+                .iter()
+                .map(|m| m.to_string())
+                .collect_vec(),
+            [
+                "src/lib.rs:3: replace parse -> Result<u32> with Ok(0)",
+                "src/lib.rs:3: replace parse -> Result<u32> with Ok(1)",
+                "src/lib.rs:3: replace parse -> Result<u32> with Err(::anyhow::anyhow!(\"mutated\"))",
+                "src/lib.rs:7: replace explicit -> anyhow::Result<bool> with Ok(true)",
+                "src/lib.rs:7: replace explicit -> anyhow::Result<bool> with Ok(false)",
+                "src/lib.rs:7: replace explicit -> anyhow::Result<bool> with Err(::anyhow::anyhow!(\"mutated\"))",
+                "src/lib.rs:8: replace Ok(check()) with Err(::anyhow::anyhow!(\"mutated\")) in explicit",
+                "src/lib.rs:11: replace io -> std::io::Result<()> with Ok(())",
+            ]
+        );
+        // A plain `Result` is not assumed to be from anyhow unless it's imported.
+        let code = "fn parse(s: &str) -> Result<u32> { s.parse().context(\"parse\") }";
+        assert!(mutants_in_code(code, &Options::default())
+            .iter()
+            .all(|m| !m.replacement.contains("anyhow")));
+    }
+
+    #[test]
+    fn const_fn_replacements_are_const() {
+        let code = indoc::indoc! { r#"
+            const fn label() -> String {
+                make_label()
+            }
+
+            const fn origin() -> Point {
+                Point { x: 0, y: 0 }
+            }
+
+            const fn check(n: u32) -> Result<u32, Error> {
+                Ok(n)
+            }
+
+            fn not_const() -> Point {
+                Point { x: 0, y: 0 }
+            }
+        "# };
+        let mutants = mutants_in_code_with_error_exprs(
+            code,
+            &Options::default(),
+            &[parse_quote! { Error::Mutated }],
+        );
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:1: replace label -> String with String::new()",
+                "src/lib.rs:9: replace check -> Result<u32, Error> with Ok(0)",
+                "src/lib.rs:9: replace check -> Result<u32, Error> with Ok(1)",
+                "src/lib.rs:9: replace check -> Result<u32, Error> with Err(Error::Mutated)",
+                "src/lib.rs:10: replace Ok(n) with Err(Error::Mutated) in check",
+                "src/lib.rs:13: replace not_const -> Point with Default::default()",
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_return_types_use_qualified_default() {
+        let code = indoc::indoc! { r#"
+            fn make<T: Default>() -> T {
+                T::default()
+            }
+
+            fn make_where<T>(x: u32) -> T where T: Clone + std::default::Default {
+                build(x)
+            }
+
+            fn first<T: Clone>(v: &[T]) -> T {
+                v[0].clone()
+            }
+
+            fn wrap<T: Default>() -> Option<T> {
+                Some(T::default())
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::FnValue),
+            [
+                "src/lib.rs:1: replace make -> T with <T as Default>::default()",
+                "src/lib.rs:5: replace make_where -> T with <T as Default>::default()",
+                "src/lib.rs:13: replace wrap -> Option<T> with None",
+                "src/lib.rs:13: replace wrap -> Option<T> with Some(Default::default())",
+            ]
+        );
+    }
+
+    #[test]
+    fn path_is_result() {
+        let path: syn::Path = syn::parse_quote! { Result<(), ()> };
+        assert!(super::result_ok_type(&path).is_some());
+    }
+
+    #[test]
+    fn pretty_format() {
+        assert_eq!(
+            tokens_to_pretty_string(quote! {
+                <impl Iterator for MergeTrees < AE , BE , AIT , BIT > > :: next
+                -> Option < Self ::  Item >
+            }),
+            "<impl Iterator for MergeTrees<AE, BE, AIT, BIT>>::next -> Option<Self::Item>"
+        );
+        assert_eq!(
+            tokens_to_pretty_string(quote! { Lex < 'buf >::take }),
+            "Lex<'buf>::take"
+        );
+        assert_eq!(
+            tokens_to_pretty_string(quote! { Some(a * 2 + -b) }),
+            "Some(a * 2 + -b)"
+        );
+        assert_eq!(
+            tokens_to_pretty_string(quote! { Some(|a, _| a || b | c) }),
+            "Some(|a, _| a || b | c)"
+        );
+    }
+
+    #[test]
+    fn recurse_into_result_bool() {
+        let return_type: syn::ReturnType = parse_quote! {-> std::result::Result<bool> };
+        let reps = return_type_replacements(&return_type, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["Ok(true)", "Ok(false)",]
+        );
+    }
+
+    #[test]
+    fn recurse_into_result_result_bool() {
+        let return_type: syn::ReturnType = parse_quote! {-> std::result::Result<Result<bool>> };
+        let error_expr: syn::Expr = parse_quote! { anyhow!("mutated") };
+        let reps = return_type_replacements(&return_type, &[error_expr], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &[
+                "Ok(Ok(true))",
+                "Ok(Ok(false))",
+                "Ok(Err(anyhow!(\"mutated\")))",
+                "Err(anyhow!(\"mutated\"))"
+            ]
+        );
+    }
+
+    #[test]
+    fn recurse_into_result_string_and_unknown_types() {
+        check_replacements(
+            parse_quote! { -> Result<bool, io::Error> },
+            &["Ok(true)", "Ok(false)"],
+        );
+        check_replacements(
+            parse_quote! { -> Result<String, io::Error> },
+            &["Ok(String::new())", "Ok(\"xyzzy\".into())"],
+        );
+        check_replacements(
+            parse_quote! { -> Result<SomeStruct, io::Error> },
+            &["Ok(Default::default())"],
+        );
+    }
+
+    #[test]
+    fn u16_replacements() {
+        let reps = return_type_replacements(&parse_quote! { -> u16 }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["0", "1",]
+        );
+    }
+
+    #[test]
+    fn isize_replacements() {
+        let reps = return_type_replacements(&parse_quote! { -> isize }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["0", "1", "-1"]
+        );
+    }
+
+    #[test]
+    fn nonzero_integer_replacements() {
+        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroIsize }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["1", "-1"]
+        );
+
+        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroUsize }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["1"]
+        );
+
+        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroU32 }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["1"]
+        );
+    }
+
+    #[test]
+    fn unit_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> () }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["()"]
+        );
+    }
+
+    #[test]
+    fn result_unit_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> Result<(), Error> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["Ok(())"]
+        );
+
+        let reps = return_type_replacements(&parse_quote! { -> Result<()> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["Ok(())"]
+        );
+    }
+
+    #[test]
+    fn option_usize_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> Option<usize> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["None", "Some(0)", "Some(1)"]
+        );
+    }
+
+    #[test]
+    fn option_of_ref_and_box_replacements() {
+        check_replacements(
+            parse_quote! { -> Option<&str> },
+            &["None", "Some(\"\")", "Some(\"xyzzy\")"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<&'static str> },
+            &["None", "Some(\"\")", "Some(\"xyzzy\")"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<u32> },
+            &["None", "Some(0)", "Some(1)"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<Box<u8>> },
+            &["None", "Some(Box::new(0))", "Some(Box::new(1))"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<&Option<Box<u8>>> },
+            &[
+                "None",
+                "Some(&None)",
+                "Some(&Some(Box::new(0)))",
+                "Some(&Some(Box::new(1)))",
+            ],
+        );
+        check_replacements(parse_quote! { -> Option<&MyObject> }, &["None"]);
+    }
+
+    #[test]
+    fn option_of_bool_string_and_unknown_replacements() {
+        check_replacements(
+            parse_quote! { -> Option<bool> },
+            &["None", "Some(true)", "Some(false)"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<String> },
+            &["None", "Some(String::new())", "Some(\"xyzzy\".into())"],
+        );
+        check_replacements(
+            parse_quote! { -> Option<SomeStruct> },
+            &["None", "Some(Default::default())"],
+        );
+    }
+
+    #[test]
+    fn box_usize_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> Box<usize> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["Box::new(0)", "Box::new(1)"]
+        );
+    }
+
+    #[test]
+    fn box_unrecognized_type_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> Box<MyObject> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["Box::new(Default::default())"]
+        );
+    }
+
+    #[test]
+    fn vec_string_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> std::vec::Vec<String> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["vec![]", "vec![String::new()]", "vec![\"xyzzy\".into()]"]
+        );
+    }
+
+    #[test]
+    fn map_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> HashMap<String, bool> }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &[
+                "Default::default()",
+                "HashMap::from([(String::new(), true)])",
+                "HashMap::from([(String::new(), false)])",
+                "HashMap::from([(\"xyzzy\".into(), true)])",
+            ]
+        );
+        let reps = return_type_replacements(
+            &parse_quote! { -> std::collections::BTreeMap<u8, Vec<u8>> },
+            &[],
+            &[],
+        );
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &[
+                "Default::default()",
+                "std::collections::BTreeMap::from([(0, vec![])])",
+                "std::collections::BTreeMap::from([(0, vec![0])])",
+                "std::collections::BTreeMap::from([(0, vec![1])])",
+                "std::collections::BTreeMap::from([(1, vec![])])",
+            ]
+        );
+        // Nested maps grow with the number of replacements, not their product: the
+        // inner map has 5 non-empty replacements, and the outer keys 3.
+        let reps = return_type_replacements(
+            &parse_quote! { -> HashMap<i32, HashMap<i32, i32>> },
+            &[],
+            &[],
+        );
+        assert_eq!(reps.len(), 1 + 5 + 2);
+        // Unknown types, keys that can't be hashed or ordered, or a custom hasher, give
+        // only the empty map.
+        for type_ in [
+            parse_quote! { -> HashMap<Key, u32> },
+            parse_quote! { -> HashMap<f64, u32> },
+            parse_quote! { -> BTreeMap<Vec<f32>, u32> },
+            parse_quote! { -> HashMap<u32, u32, MyHasher> },
+        ] {
+            let reps = return_type_replacements(&type_, &[], &[]);
+            assert_eq!(
+                reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+                &["Default::default()"]
+            );
+        }
+    }
+
+    #[test]
+    fn float_replacement() {
+        let reps = return_type_replacements(&parse_quote! { -> f32 }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["0.0", "1.0", "-1.0"]
+        );
+    }
+
+    #[test]
+    fn ref_replacement_recurses() {
+        let reps = return_type_replacements(&parse_quote! { -> &bool }, &[], &[]);
+        assert_eq!(
+            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
+            &["&true", "&false"]
+        );
+    }
+
+    #[test]
+    fn ref_to_unrecognized_type_is_skipped() {
+        assert_eq!(
+            replace(&parse_quote! { -> &Config }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> &mut Config }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> &Option<Config> }, &[]),
+            ["&None", "&Some(Default::default())"]
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> &str }, &[]),
+            ["\"\"", "\"xyzzy\""]
+        );
+    }
+
+    #[test]
+    fn dyn_trait_is_skipped() {
+        assert_eq!(
+            replace(&parse_quote! { -> Box<dyn Trait> }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> &dyn Trait }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> &mut (dyn Trait + Send) }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> Arc<dyn Trait + Send + Sync> }, &[]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> Option<Box<dyn Trait>> }, &[]),
+            ["None"]
+        );
+    }
+
+    #[test]
+    fn array_replacement() {
+        assert_eq!(
+            replace(&parse_quote! { -> [u8; 256] }, &[]),
+            &["[0; 256]", "[1; 256]"]
+        );
+    }
+
+    #[test]
+    fn arc_replacement() {
+        // Also checks that it matches the path, even using an atypical path.
+        // TODO: Ideally this would be fully qualified like `alloc::sync::Arc::new(String::new())`.
+        assert_eq!(
+            replace(&parse_quote! { -> alloc::sync::Arc<String> }, &[]),
+            &["Arc::new(String::new())", "Arc::new(\"xyzzy\".into())"]
+        );
+    }
+
+    #[test]
+    fn impl_fn_replacement() {
+        assert_eq!(
+            replace(&parse_quote! { -> impl Fn() -> bool }, &[]),
+            &["|| true", "|| false"]
+        );
+        assert_eq!(
+            replace(
+                &parse_quote! { -> impl FnMut(u32, &str) -> Option<u8> + '_ },
+                &[]
+            ),
+            &["|_, _| None", "|_, _| Some(0)", "|_, _| Some(1)"]
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> impl FnOnce(u8) }, &[]),
+            &["|_| ()"]
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> impl Fn(&str) -> MyThing }, &[]),
+            &["|_| Default::default()"]
+        );
+        assert_eq!(
+            replace(
+                &parse_quote! { -> impl for<'a> Fn(&'a str) -> &'a str },
+                &[]
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> impl Fn() -> impl Fn() -> u8 }, &[]),
+            &["|| || 0", "|| || 1"]
+        );
+    }
+
+    #[test]
+    fn impl_iterator_replacement() {
+        assert_eq!(
+            replace(&parse_quote! { -> impl Iterator<Item = u8> + '_ }, &[]),
+            &[
+                "std::iter::empty()",
+                "std::iter::once(0)",
+                "std::iter::once(1)"
+            ]
+        );
+        assert_eq!(
+            replace(&parse_quote! { -> impl Iterator<Item = MyItem> }, &[]),
+            &["std::iter::empty()", "std::iter::once(Default::default())"]
+        );
+    }
+
+    #[test]
+    fn other_impl_trait_has_no_replacements() {
+        assert!(replace(&parse_quote! { -> impl std::fmt::Display }, &[]).is_empty());
+        assert!(replace(&parse_quote! { -> impl Iterator }, &[]).is_empty());
+    }
+
+    // #[test]
+    // fn rc_replacement() {
+    //     // Also checks that it matches the path, even using an atypical path.
+    //     // TODO: Ideally this would be fully qualified like `alloc::sync::Rc::new(String::new())`.
+    //     assert_eq!(
+    //         replace(&parse_quote! { -> alloc::sync::Rc<String> }, &[]),
+    //         &["Rc::new(String::new())", "Rc::new(\"xyzzy\".into())"]
+    //     );
+    // }
+
+    #[test]
+    fn associated_types_are_resolved_from_the_impl() {
+        let code = indoc::indoc! { r#"
+            struct Counter(u32);
+
+            impl Iterator for Counter {
+                type Item = u32;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    Some(self.0)
+                }
+            }
+
+            impl std::str::FromStr for Counter {
+                type Err = String;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.parse().map(Counter).map_err(|_| s.to_owned())
+                }
+            }
+
+            impl Counter {
+                fn get(&self) -> Self::Unknown {
+                    self.0
+                }
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::FnValue),
+            [
+                "src/lib.rs:6: replace <impl Iterator for Counter>::next -> Option<Self::Item> with None",
+                "src/lib.rs:6: replace <impl Iterator for Counter>::next -> Option<Self::Item> with Some(0)",
+                "src/lib.rs:6: replace <impl Iterator for Counter>::next -> Option<Self::Item> with Some(1)",
+                "src/lib.rs:14: replace <impl FromStr for Counter>::from_str -> Result<Self, Self::Err> with Ok(Default::default())",
+                "src/lib.rs:20: replace Counter::get -> Self::Unknown with Default::default()",
+            ]
+        );
+    }
+
+    #[test]
+    fn operator_outputs_are_resolved_or_skipped() {
+        let code = indoc::indoc! { r#"
+            use std::ops::{Add, Div, Mul, Neg};
+
+            #[derive(Clone, Copy, Debug, Default)]
+            struct Point(i32, i32);
+
+            struct Meters(f64);
+
+            impl Add for Point {
+                type Output = Self;
+
+                fn add(self, other: Self) -> Self::Output {
+                    Point(self.0 + other.0, self.1 + other.1)
+                }
             }
-            _ => {
-                reps.extend(type_replacements(elem, error_exprs).into_iter().map(|rep| {
-                    quote! { &#rep }
-                }));
+
+            impl Mul<f64> for Meters {
+                type Output = Meters;
+
+                fn mul(self, k: f64) -> <Self as Mul<f64>>::Output {
+                    Meters(self.0 * k)
+                }
             }
-        },
-        Type::Reference(syn::TypeReference {
-            mutability: Some(_),
-            elem,
-            ..
-        }) => {
-            // Make &mut with static lifetime by leaking them on the heap.
-            reps.extend(type_replacements(elem, error_exprs).into_iter().map(|rep| {
-                quote! { Box::leak(Box::new(#rep)) }
-            }));
-        }
-        Type::Tuple(TypeTuple { elems, .. }) if elems.is_empty() => {
-            reps.push(quote! { () });
-            // TODO: Also recurse into non-empty tuples.
-        }
-        Type::Never(_) => {
-            // In theory we could mutate this to a function that just
-            // loops or sleeps, but it seems unlikely to be useful,
-            // so generate nothing.
-        }
-        _ => {
-            trace!(?type_, "Return type is not recognized, trying Default");
-            reps.push(quote! { Default::default() });
-        }
+
+            impl Div for Meters {
+                type Output = f64;
+
+                fn div(self, other: Meters) -> <Self as Div>::Output {
+                    self.0 / other.0
+                }
+            }
+
+            impl Neg for Meters {
+                type Output = Self;
+
+                fn neg(self) -> Self {
+                    Meters(-self.0)
+                }
+            }
+        "# };
+        assert_eq!(
+            describe_mutants_of_genre(code, crate::Genre::FnValue),
+            [
+                "src/lib.rs:11: replace <impl Add for Point>::add -> Self::Output with Default::default()",
+                "src/lib.rs:27: replace <impl Div for Meters>::div -> <Self as Div>::Output with 0.0",
+                "src/lib.rs:27: replace <impl Div for Meters>::div -> <Self as Div>::Output with 1.0",
+                "src/lib.rs:27: replace <impl Div for Meters>::div -> <Self as Div>::Output with -1.0",
+            ]
+        );
     }
-    reps
-}
 
-fn return_type_to_string(return_type: &ReturnType) -> String {
-    match return_type {
-        ReturnType::Default => String::new(),
-        ReturnType::Type(arrow, typ) => {
-            format!(
-                "{} {}",
-                arrow.to_token_stream(),
-                tokens_to_pretty_string(typ)
+    #[test]
+    fn fn_value_replaces_exactly_the_braced_body() {
+        let code = indoc::indoc! { r#"
+            /// Compute the answer.
+            #[inline]
+            fn f() -> u32 { compute() }
+
+            fn g() -> u32 {
+                #![allow(unused)]
+                // The answer.
+                compute()
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default());
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:3: replace f -> u32 with 0",
+                "src/lib.rs:3: replace f -> u32 with 1",
+                "src/lib.rs:5: replace g -> u32 with 0",
+                "src/lib.rs:5: replace g -> u32 with 1",
+            ]
+        );
+        assert_eq!(mutants[0].original_text(), "{ compute() }");
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                "{ compute() }",
+                "{\n0 /* ~ changed by cargo-mutants ~ */\n}"
             )
-        }
+        );
+        // Inner attributes and comments are within the braces, and so are replaced too,
+        // but the signature and outer attributes are kept.
+        assert_eq!(
+            mutants[3].original_text(),
+            "{\n    #![allow(unused)]\n    // The answer.\n    compute()\n}"
+        );
+        assert_eq!(
+            mutants[3].mutated_code(),
+            indoc::indoc! { r#"
+                /// Compute the answer.
+                #[inline]
+                fn f() -> u32 { compute() }
+
+                fn g() -> u32 {
+                1 /* ~ changed by cargo-mutants ~ */
+                }
+            "# }
+        );
     }
-}
 
-fn path_ends_with(path: &Path, ident: &str) -> bool {
-    path.segments.last().map_or(false, |s| s.ident == ident)
-}
+    #[test]
+    fn doc_hidden_items_are_excluded_only_if_requested() {
+        let code = indoc::indoc! { r#"
+            /// Visible.
+            pub fn visible() -> bool {
+                true
+            }
 
-fn path_is_float(path: &Path) -> bool {
-    ["f32", "f64"].iter().any(|s| path.is_ident(s))
-}
+            #[doc(hidden)]
+            pub fn hidden() -> bool {
+                true
+            }
 
-fn path_is_unsigned(path: &Path) -> bool {
-    ["u8", "u16", "u32", "u64", "u128", "usize"]
-        .iter()
-        .any(|s| path.is_ident(s))
-}
+            #[doc(alias = "secret", cfg(unix), hidden)]
+            pub fn aliased() -> bool {
+                true
+            }
 
-fn path_is_signed(path: &Path) -> bool {
-    ["i8", "i16", "i32", "i64", "i128", "isize"]
-        .iter()
-        .any(|s| path.is_ident(s))
-}
+            #[doc(hidden)]
+            impl Thing {
+                fn get(&self) -> bool {
+                    true
+                }
+            }
 
-fn path_is_nonzero_signed(path: &Path) -> bool {
-    if let Some(l) = path.segments.last().map(|p| p.ident.to_string()) {
-        matches!(
-            l.as_str(),
-            "NonZeroIsize"
-                | "NonZeroI8"
-                | "NonZeroI16"
-                | "NonZeroI32"
-                | "NonZeroI64"
-                | "NonZeroI128",
-        )
-    } else {
-        false
+            #[doc(hidden)]
+            mod internal {
+                fn inner() -> bool {
+                    true
+                }
+            }
+        "# };
+        let function_names = |options: &Options| {
+            mutants_in_code(code, options)
+                .iter()
+                .map(|m| m.function_name().to_owned())
+                .collect_vec()
+        };
+        assert_eq!(
+            function_names(&Options::default()),
+            [
+                "visible",
+                "hidden",
+                "aliased",
+                "Thing::get",
+                "internal::inner"
+            ]
+        );
+        let options = Options {
+            exclude_doc_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(function_names(&options), ["visible"]);
     }
-}
 
-fn path_is_nonzero_unsigned(path: &Path) -> bool {
-    if let Some(l) = path.segments.last().map(|p| p.ident.to_string()) {
-        matches!(
-            l.as_str(),
-            "NonZeroUsize"
-                | "NonZeroU8"
-                | "NonZeroU16"
-                | "NonZeroU32"
-                | "NonZeroU64"
-                | "NonZeroU128",
-        )
-    } else {
-        false
-    }
-}
+    #[test]
+    fn generic_functions_are_excluded_only_if_requested() {
+        let code = indoc::indoc! { r#"
+            fn plain(a: u32) -> u32 {
+                a + 1
+            }
 
-/// Convert a TokenStream representing some code to a reasonably formatted
-/// string of Rust code.
-///
-/// [TokenStream] has a `to_string`, but it adds spaces in places that don't
-/// look idiomatic, so this reimplements it in a way that looks better.
-///
-/// This is probably not correctly formatted for all Rust syntax, and only tries
-/// to cover cases that can emerge from the code we generate.
-fn tokens_to_pretty_string<T: ToTokens>(t: T) -> String {
-    use TokenTree::*;
-    let mut b = String::with_capacity(200);
-    let mut ts = t.to_token_stream().into_iter().peekable();
-    while let Some(tt) = ts.next() {
-        match tt {
-            Punct(p) => {
-                let pc = p.as_char();
-                b.push(pc);
-                if ts.peek().is_some() && (b.ends_with("->") || pc == ',' || pc == ';') {
-                    b.push(' ');
-                }
+            fn first<T: Clone>(v: &[T]) -> Option<T> {
+                v.first().cloned()
             }
-            Ident(_) | Literal(_) => {
-                match tt {
-                    Literal(l) => b.push_str(&l.to_string()),
-                    Ident(i) => b.push_str(&i.to_string()),
-                    _ => unreachable!(),
-                };
-                if let Some(next) = ts.peek() {
-                    match next {
-                        Ident(_) | Literal(_) => b.push(' '),
-                        Punct(p) => match p.as_char() {
-                            ',' | ';' | '<' | '>' | ':' | '.' | '!' => (),
-                            _ => b.push(' '),
-                        },
-                        Group(_) => (),
-                    }
-                }
+
+            fn longest<'a>(a: &'a str, b: &'a str) -> &'a str {
+                if a.len() > b.len() { a } else { b }
             }
-            Group(g) => {
-                match g.delimiter() {
-                    Delimiter::Brace => b.push('{'),
-                    Delimiter::Bracket => b.push('['),
-                    Delimiter::Parenthesis => b.push('('),
-                    Delimiter::None => (),
+
+            impl Stack {
+                fn push<I: IntoIterator<Item = u32>>(&mut self, items: I) -> usize {
+                    self.0.extend(items);
+                    self.0.len()
                 }
-                b.push_str(&tokens_to_pretty_string(g.stream()));
-                match g.delimiter() {
-                    Delimiter::Brace => b.push('}'),
-                    Delimiter::Bracket => b.push(']'),
-                    Delimiter::Parenthesis => b.push(')'),
-                    Delimiter::None => (),
+
+                fn len(&self) -> usize {
+                    self.0.len()
                 }
             }
-        }
+        "# };
+        let function_names = |options: &Options| {
+            mutants_in_code(code, options)
+                .iter()
+                .map(|m| m.function_name().to_owned())
+                .dedup()
+                .collect_vec()
+        };
+        assert_eq!(
+            function_names(&Options::default()),
+            ["plain", "first", "longest", "Stack::push", "Stack::len"]
+        );
+        let options = Options {
+            exclude_generics: true,
+            ..Default::default()
+        };
+        assert_eq!(function_names(&options), ["plain", "longest", "Stack::len"]);
     }
-    debug_assert!(
-        !b.ends_with(' '),
-        "generated a trailing space: ts={ts:?}, b={b:?}",
-        ts = t.to_token_stream(),
-    );
-    b
-}
 
-/// If this looks like `Result<T, E>` (optionally with `Result` in some module), return `T`.
-fn result_ok_type(path: &Path) -> Option<&Type> {
-    match_first_type_arg(path, "Result")
-}
+    #[test]
+    fn debug_assertions_cfg_depends_on_profile() {
+        let code = indoc::indoc! { r#"
+            #[cfg(debug_assertions)]
+            fn check_invariants() -> bool {
+                true
+            }
 
-/// If this is a path ending in `expected_ident`, return the first type argument.
-fn match_first_type_arg<'p>(path: &'p Path, expected_ident: &str) -> Option<&'p Type> {
-    let last = path.segments.last()?;
-    if last.ident == expected_ident {
-        if let PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) =
-            &last.arguments
-        {
-            if let Some(GenericArgument::Type(ok_type)) = args.first() {
-                return Some(ok_type);
+            #[cfg(not(debug_assertions))]
+            fn fast_path() -> bool {
+                true
             }
-        }
-    }
-    None
-}
 
-/// True if the signature of a function is such that it should be excluded.
-fn fn_sig_excluded(sig: &syn::Signature) -> bool {
-    if sig.unsafety.is_some() {
-        trace!("Skip unsafe fn");
-        true
-    } else {
-        false
+            #[cfg(all(debug_assertions, feature = "extra"))]
+            fn extra_checks() -> bool {
+                true
+            }
+
+            #[cfg(any(debug_assertions, feature = "extra"))]
+            fn maybe_checks() -> bool {
+                true
+            }
+
+            #[cfg(feature = "extra")]
+            fn extra() -> bool {
+                true
+            }
+        "# };
+        let function_names = |options: &Options| {
+            mutants_in_code(code, options)
+                .iter()
+                .map(|m| m.function_name().to_owned())
+                .collect_vec()
+        };
+        assert_eq!(
+            function_names(&Options::default()),
+            ["check_invariants", "extra_checks", "maybe_checks", "extra"]
+        );
+        let options = Options {
+            profile: Some("release".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            function_names(&options),
+            ["fast_path", "maybe_checks", "extra"]
+        );
+        let options = Options {
+            profile: Some("custom".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(function_names(&options).len(), 5);
     }
-}
 
-/// True if any of the attrs indicate that we should skip this node and everything inside it.
-fn attrs_excluded(attrs: &[Attribute]) -> bool {
-    attrs
-        .iter()
-        .any(|attr| attr_is_cfg_test(attr) || attr_is_test(attr) || attr_is_mutants_skip(attr))
-}
+    #[test]
+    fn benign_attributes_dont_affect_mutants() {
+        let code = indoc::indoc! { r#"
+            #[inline]
+            fn a() -> bool {
+                true
+            }
 
-/// True if the block (e.g. the contents of a function) is empty.
-fn block_is_empty(block: &syn::Block) -> bool {
-    block.stmts.is_empty()
-}
+            #[inline(always)]
+            #[must_use]
+            fn b() -> bool {
+                true
+            }
 
-/// True if the attribute looks like `#[cfg(test)]`, or has "test"
-/// anywhere in it.
-fn attr_is_cfg_test(attr: &Attribute) -> bool {
-    if !path_is(attr.path(), &["cfg"]) {
-        return false;
-    }
-    let mut contains_test = false;
-    if let Err(err) = attr.parse_nested_meta(|meta| {
-        if meta.path.is_ident("test") {
-            contains_test = true;
-        }
-        Ok(())
-    }) {
-        debug!(
-            ?err,
-            ?attr,
-            "Attribute is not in conventional form; skipped"
+            #[cold]
+            #[track_caller]
+            fn c() -> bool {
+                true
+            }
+
+            impl Thing {
+                #[inline]
+                #[allow(clippy::all)]
+                fn d(&self) -> bool {
+                    true
+                }
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default());
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace a -> bool with false",
+                "src/lib.rs:8: replace b -> bool with false",
+                "src/lib.rs:14: replace c -> bool with false",
+                "src/lib.rs:21: replace Thing::d -> bool with false",
+            ]
         );
-        return false;
+        // Only the body is replaced, so the attributes are kept.
+        for mutant in &mutants {
+            assert_eq!(
+                mutant.original_text().split_whitespace().collect_vec(),
+                ["{", "true", "}"]
+            );
+            let mutated = mutant.mutated_code();
+            for attr in ["#[inline]", "#[must_use]", "#[cold]", "#[track_caller]"] {
+                assert!(mutated.contains(attr), "{attr} missing from {mutated}");
+            }
+        }
     }
-    contains_test
-}
 
-/// True if the attribute is `#[test]`.
-fn attr_is_test(attr: &Attribute) -> bool {
-    attr.path().is_ident("test")
-}
+    #[test]
+    fn prioritize_functions_not_named_in_tests() {
+        let code = indoc::indoc! { r#"
+            fn double(a: u32) -> u32 {
+                a * 2
+            }
 
-fn path_is(path: &syn::Path, idents: &[&str]) -> bool {
-    path.segments.iter().map(|ps| &ps.ident).eq(idents.iter())
-}
+            fn triple(a: u32) -> u32 {
+                a * 3
+            }
 
-/// True if the attribute contains `mutants::skip`.
-///
-/// This for example returns true for `#[mutants::skip] or `#[cfg_attr(test, mutants::skip)]`.
-fn attr_is_mutants_skip(attr: &Attribute) -> bool {
-    if path_is(attr.path(), &["mutants", "skip"]) {
-        return true;
-    }
-    if !path_is(attr.path(), &["cfg_attr"]) {
-        return false;
-    }
-    let mut skip = false;
-    if let Err(err) = attr.parse_nested_meta(|meta| {
-        if path_is(&meta.path, &["mutants", "skip"]) {
-            skip = true
-        }
-        Ok(())
-    }) {
-        debug!(
-            ?attr,
-            ?err,
-            "Attribute is not a path with attributes; skipping"
+            impl Thing {
+                fn get(&self) -> bool {
+                    true
+                }
+            }
+
+            #[test]
+            fn test_double() {
+                assert_eq!(double(2), 4);
+            }
+
+            #[cfg(test)]
+            mod test {
+                #[test]
+                fn thing() {
+                    assert!(Thing.get());
+                }
+            }
+        "# };
+        let mut mutants = mutants_in_code(code, &Options::default());
+        prioritize_untested(&mut mutants);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:5: replace triple -> u32 with 0",
+                "src/lib.rs:5: replace triple -> u32 with 1",
+                "src/lib.rs:1: replace double -> u32 with 0",
+                "src/lib.rs:1: replace double -> u32 with 1",
+                "src/lib.rs:10: replace Thing::get -> bool with false",
+            ]
         );
-        return false;
     }
-    skip
-}
 
-#[cfg(test)]
-mod test {
-    use quote::quote;
-    use syn::{parse_quote, Expr, ReturnType};
+    #[test]
+    fn formatting_and_error_impls_are_skipped_by_default() {
+        let code = indoc::indoc! { r#"
+            impl fmt::Display for Error {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "error")
+                }
+            }
+
+            impl std::error::Error for Error {
+                fn description(&self) -> &str {
+                    "error"
+                }
+            }
 
-    use super::{return_type_replacements, tokens_to_pretty_string};
+            impl Error {
+                fn code(&self) -> u32 {
+                    1
+                }
+            }
+        "# };
+        let describe = |options: &Options| {
+            mutants_in_code(code, options)
+                .iter()
+                .map(|m| m.to_string())
+                .collect_vec()
+        };
+        assert_eq!(
+            describe(&Options::default()),
+            ["src/lib.rs:14: replace Error::code -> u32 with 0",]
+        );
+        let options = Options {
+            skip_traits: Some(vec!["Error".to_owned()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            describe(&options),
+            [
+                "src/lib.rs:2: replace <impl Display for Error>::fmt -> fmt::Result with Ok(Default::default())",
+                "src/lib.rs:14: replace Error::code -> u32 with 0",
+            ]
+        );
+    }
 
     #[test]
-    fn path_is_result() {
-        let path: syn::Path = syn::parse_quote! { Result<(), ()> };
-        assert!(super::result_ok_type(&path).is_some());
+    fn operator_trait_impls_are_skipped_by_default() {
+        let code = indoc::indoc! { r#"
+            impl Index<usize> for Stack {
+                type Output = u32;
+                fn index(&self, i: usize) -> &u32 {
+                    &self.items[i]
+                }
+            }
+
+            impl std::ops::Deref for Stack {
+                type Target = [u32];
+                fn deref(&self) -> &[u32] {
+                    &self.items
+                }
+            }
+        "# };
+        assert!(mutants_in_code(code, &Options::default()).is_empty());
+        let options = Options {
+            skip_traits: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert_eq!(
+            mutants_in_code(code, &options)
+                .iter()
+                .map(|m| m.to_string())
+                .collect_vec(),
+            [
+                "src/lib.rs:3: replace <impl Index for Stack>::index -> &u32 with &0",
+                "src/lib.rs:3: replace <impl Index for Stack>::index -> &u32 with &1",
+                "src/lib.rs:10: replace <impl Deref for Stack>::deref -> &[u32] with &Default::default()",
+            ]
+        );
     }
 
     #[test]
-    fn pretty_format() {
+    fn mutate_literal_default_arguments() {
+        let code = indoc::indoc! { r#"
+            fn f(a: Option<u32>, b: Option<bool>, c: Option<u8>) -> u32 {
+                if b.map_or(false, |x| !x) {
+                    return c.unwrap_or_else(|| 7u8) as u32;
+                }
+                a.unwrap_or(0)
+            }
+        "# };
         assert_eq!(
-            tokens_to_pretty_string(quote! {
-                <impl Iterator for MergeTrees < AE , BE , AIT , BIT > > :: next
-                -> Option < Self ::  Item >
-            }),
-            "<impl Iterator for MergeTrees<AE, BE, AIT, BIT>>::next -> Option<Self::Item>"
+            describe_mutants_of_genre(code, crate::Genre::DefaultValue),
+            [
+                "src/lib.rs:2: replace false with true in f",
+                "src/lib.rs:3: replace 7u8 with 6u8 in f",
+                "src/lib.rs:3: replace 7u8 with 8u8 in f",
+                "src/lib.rs:5: replace 0 with 1 in f",
+            ]
         );
+    }
+
+    #[test]
+    fn default_argument_mutant_replaces_only_the_literal() {
+        let code = "fn f(a: Option<u32>) -> u32 {\n    a.unwrap_or(0)\n}\n";
+        let mutants = mutants_in_code(code, &Options::default());
+        let mutant = mutants
+            .iter()
+            .find(|m| m.genre == crate::Genre::DefaultValue)
+            .unwrap();
+        assert_eq!(mutant.original_text(), "0");
         assert_eq!(
-            tokens_to_pretty_string(quote! { Lex < 'buf >::take }),
-            "Lex<'buf>::take"
+            mutant.mutated_code(),
+            "fn f(a: Option<u32>) -> u32 {\n    a.unwrap_or(1 /* ~ changed by cargo-mutants ~ */)\n}\n"
         );
     }
 
     #[test]
-    fn recurse_into_result_bool() {
-        let return_type: syn::ReturnType = parse_quote! {-> std::result::Result<bool> };
-        let reps = return_type_replacements(&return_type, &[]);
+    fn non_literal_default_arguments_are_not_mutated() {
+        let code = indoc::indoc! { r#"
+            fn f(a: Option<u32>, d: u32) -> u32 {
+                a.unwrap_or(d) + a.unwrap_or_else(|| d + 1) + a.unwrap_or_default()
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["Ok(true)", "Ok(false)",]
+            describe_mutants_of_genre(code, crate::Genre::DefaultValue),
+            Vec::<String>::new()
         );
     }
 
     #[test]
-    fn recurse_into_result_result_bool() {
-        let return_type: syn::ReturnType = parse_quote! {-> std::result::Result<Result<bool>> };
-        let error_expr: syn::Expr = parse_quote! { anyhow!("mutated") };
-        let reps = return_type_replacements(&return_type, &[error_expr]);
+    fn swap_saturating_and_wrapping_methods() {
+        let code = indoc::indoc! { r#"
+            fn f(a: u32, b: i64) -> u32 {
+                let c = b.wrapping_mul(2).saturating_pow(3);
+                a.saturating_add(1).wrapping_sub(c as u32)
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &[
-                "Ok(Ok(true))",
-                "Ok(Ok(false))",
-                "Ok(Err(anyhow!(\"mutated\")))",
-                "Err(anyhow!(\"mutated\"))"
+            describe_mutants_of_genre(code, crate::Genre::OverflowMethod),
+            [
+                "src/lib.rs:2: replace saturating_pow with wrapping_pow in f",
+                "src/lib.rs:2: replace wrapping_mul with saturating_mul in f",
+                "src/lib.rs:3: replace wrapping_sub with saturating_sub in f",
+                "src/lib.rs:3: replace saturating_add with wrapping_add in f",
             ]
         );
     }
 
     #[test]
-    fn u16_replacements() {
-        let reps = return_type_replacements(&parse_quote! { -> u16 }, &[]);
+    fn checked_and_other_methods_are_not_swapped() {
+        let code = indoc::indoc! { r#"
+            fn f(a: u32) -> Option<u32> {
+                a.checked_add(1)?.saturating_neg_ish();
+                a.overflowing_add(1).0.wrapping_frobnicate();
+                a.checked_mul(2)
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["0", "1",]
+            describe_mutants_of_genre(code, crate::Genre::OverflowMethod),
+            Vec::<String>::new()
         );
     }
 
     #[test]
-    fn isize_replacements() {
-        let reps = return_type_replacements(&parse_quote! { -> isize }, &[]);
+    fn overflow_method_mutant_replaces_only_the_method_name() {
+        let code = "fn f(a: u8) -> u8 {\n    a.saturating_add(1)\n}\n";
+        let mutants = mutants_in_code(code, &Options::default());
+        let mutant = mutants
+            .iter()
+            .find(|m| m.genre == crate::Genre::OverflowMethod)
+            .unwrap();
+        assert_eq!(mutant.original_text(), "saturating_add");
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["0", "1", "-1"]
+            mutant.mutated_code(),
+            "fn f(a: u8) -> u8 {\n    a.wrapping_add /* ~ changed by cargo-mutants ~ */(1)\n}\n"
         );
     }
 
     #[test]
-    fn nonzero_integer_replacements() {
-        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroIsize }, &[]);
+    fn format_string_mutants_are_opt_in() {
+        let code = indoc::indoc! { r#"
+            fn f() -> String {
+                format!("hello")
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["1", "-1"]
+            describe_mutants_of_genre(code, crate::Genre::FormatString),
+            Vec::<String>::new()
         );
-
-        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroUsize }, &[]);
+        let mutants = requested_mutants_of_genre(code, crate::Genre::FormatString);
+        assert_eq!(mutants.len(), 1);
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["1"]
+            mutants[0].to_string(),
+            r#"src/lib.rs:2: replace "hello" with "xyzzy" in f"#
         );
-
-        let reps = return_type_replacements(&parse_quote! { -> std::num::NonZeroU32 }, &[]);
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["1"]
+            mutants[0].mutated_code(),
+            "fn f() -> String {\n    format!(\"xyzzy\" /* ~ changed by cargo-mutants ~ */)\n}\n"
         );
     }
 
     #[test]
-    fn unit_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> () }, &[]);
+    fn only_plain_format_strings_are_mutated() {
+        let code = indoc::indoc! { r#"
+            fn f(w: &mut String, x: u32) -> std::fmt::Result {
+                write!(w, "plain")?;
+                writeln!(w, "line")?;
+                let _ = format!("{x}");
+                let _ = format!("{}", x);
+                let _ = format!("{{braces}}");
+                let _ = println!("not a format macro");
+                let _ = format!(concat!("a", "b"));
+                writeln!(w)
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["()"]
+            requested_mutants_of_genre(code, crate::Genre::FormatString)
+                .iter()
+                .map(|m| m.to_string())
+                .collect_vec(),
+            [
+                r#"src/lib.rs:2: replace "plain" with "xyzzy" in f"#,
+                r#"src/lib.rs:3: replace "line" with "xyzzy" in f"#,
+            ]
         );
     }
 
     #[test]
-    fn result_unit_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> Result<(), Error> }, &[]);
+    fn bool_then_mutants_are_opt_in() {
+        let code = indoc::indoc! { r#"
+            fn f(a: u32, b: std::cmp::Ordering) -> Option<u32> {
+                let _ = b.then(std::cmp::Ordering::Less);
+                let _ = (a > 1).then(|x| x);
+                let _ = (a > 2).then(|| a * 2);
+                (a > 3).then_some(a)
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["Ok(())"]
+            describe_mutants_of_genre(code, crate::Genre::BoolThen),
+            Vec::<String>::new()
         );
-
-        let reps = return_type_replacements(&parse_quote! { -> Result<()> }, &[]);
+        let mutants = requested_mutants_of_genre(code, crate::Genre::BoolThen);
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["Ok(())"]
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:4: replace (a > 2).then(|| a * 2) with Some(a * 2) in f",
+                "src/lib.rs:4: replace (a > 2).then(|| a * 2) with None in f",
+                "src/lib.rs:5: replace (a > 3).then_some(a) with Some(a) in f",
+                "src/lib.rs:5: replace (a > 3).then_some(a) with None in f",
+            ]
+        );
+        assert_eq!(
+            mutants[3].mutated_code(),
+            code.replace(
+                "(a > 3).then_some(a)",
+                "None /* ~ changed by cargo-mutants ~ */"
+            )
         );
     }
 
     #[test]
-    fn option_usize_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> Option<usize> }, &[]);
+    fn flip_early_error_returns() {
+        let code = indoc::indoc! { r#"
+            fn check(n: u32) -> Result<u32, String> {
+                if n == 0 {
+                    return Err("zero".into());
+                }
+                let f = |x: u32| -> Result<u32, String> { return Err(x.to_string()) };
+                match n {
+                    1 => return Err(format!("one")),
+                    _ => Ok(n),
+                }
+            }
+
+            fn not_result(n: u32) -> Option<u32> {
+                if n == 0 {
+                    return None;
+                }
+                Some(n)
+            }
+        "# };
+        let mutants = mutants_in_code(code, &Options::default())
+            .into_iter()
+            .filter(|m| m.genre == crate::Genre::ResultFlip)
+            .collect_vec();
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["None", "Some(0)", "Some(1)"]
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                r#"src/lib.rs:3: replace return Err("zero".into()) with return Ok(Default::default()) in check"#,
+                r#"src/lib.rs:7: replace return Err(format!("one")) with return Ok(Default::default()) in check"#,
+            ]
+        );
+        assert_eq!(
+            mutants[0].mutated_code(),
+            code.replace(
+                r#"return Err("zero".into());"#,
+                "return Ok(Default::default()) /* ~ changed by cargo-mutants ~ */;"
+            )
         );
     }
 
     #[test]
-    fn box_usize_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> Box<usize> }, &[]);
+    fn flip_final_ok_and_err() {
+        let code = indoc::indoc! { r#"
+            fn parse(s: &str) -> Result<u32, String> {
+                let n = s.len() as u32;
+                Ok(n)
+            }
+
+            fn fail() -> std::io::Result<()> {
+                log("failing");
+                Err(std::io::Error::other("no"))
+            }
+
+            fn not_result() -> Option<u32> {
+                Some(1)
+            }
+
+            fn not_tail() -> Result<(), String> {
+                return Ok(());
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["Box::new(0)", "Box::new(1)"]
+            describe_mutants_of_genre(code, crate::Genre::ResultFlip),
+            [
+                r#"src/lib.rs:8: replace Err(std::io::Error::other("no")) with Ok(Default::default()) in fail"#
+            ]
+        );
+        let flips = mutants_in_code_with_error_exprs(
+            code,
+            &Options::default(),
+            &[parse_quote! { "injected".into() }],
+        )
+        .into_iter()
+        .filter(|m| m.genre == crate::Genre::ResultFlip)
+        .collect_vec();
+        assert_eq!(
+            flips[0].to_string(),
+            r#"src/lib.rs:3: replace Ok(n) with Err("injected".into()) in parse"#
+        );
+        assert_eq!(
+            flips[0].mutated_code(),
+            code.replace(
+                "    Ok(n)\n",
+                "    Err(\"injected\".into()) /* ~ changed by cargo-mutants ~ */\n"
+            )
         );
+        assert_eq!(flips.len(), 2);
     }
 
     #[test]
-    fn box_unrecognized_type_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> Box<MyObject> }, &[]);
+    fn lazy_static_mutants_are_opt_in() {
+        let code = indoc::indoc! { r#"
+            lazy_static! {
+                static ref LIMIT: u32 = 10;
+                pub static ref NAMES: Vec<&'static str> = vec!["a"];
+            }
+
+            static VERBOSE: once_cell::sync::Lazy<bool> = Lazy::new(|| env_flag("V", false));
+
+            static PLAIN: u32 = 3;
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["Box::new(Default::default())"]
+            describe_mutants_of_genre(code, crate::Genre::LazyStatic),
+            Vec::<String>::new()
+        );
+        let mutants = requested_mutants_of_genre(code, crate::Genre::LazyStatic);
+        assert_eq!(
+            mutants.iter().map(|m| m.to_string()).collect_vec(),
+            [
+                "src/lib.rs:2: replace 10 with 9 in LIMIT",
+                "src/lib.rs:2: replace 10 with 11 in LIMIT",
+                "src/lib.rs:6: replace false with true in VERBOSE",
+            ]
+        );
+        assert_eq!(
+            mutants[1].mutated_code(),
+            code.replace("u32 = 10;", "u32 = 11 /* ~ changed by cargo-mutants ~ */;")
         );
     }
 
     #[test]
-    fn vec_string_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> std::vec::Vec<String> }, &[]);
+    fn replace_if_expression_with_each_branch() {
+        let code = indoc::indoc! { r#"
+            fn max(a: u32, b: u32) -> u32 {
+                if a > b {
+                    a
+                } else {
+                    b
+                }
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["vec![]", "vec![String::new()]", "vec![\"xyzzy\".into()]"]
+            describe_mutants_of_genre(code, crate::Genre::IfBranch),
+            [
+                "src/lib.rs:2: replace if a > b { a } else { b } with { a } in max",
+                "src/lib.rs:2: replace if a > b { a } else { b } with { b } in max",
+            ]
+        );
+        let mutants = mutants_in_code(code, &Options::default());
+        let mutant = mutants
+            .iter()
+            .find(|m| m.genre == crate::Genre::IfBranch)
+            .unwrap();
+        assert_eq!(
+            mutant.mutated_code(),
+            "fn max(a: u32, b: u32) -> u32 {\n    { a } /* ~ changed by cargo-mutants ~ */\n}\n"
         );
     }
 
     #[test]
-    fn float_replacement() {
-        let reps = return_type_replacements(&parse_quote! { -> f32 }, &[]);
+    fn no_default_genres_generates_only_requested_genres() {
+        let code = indoc::indoc! { r#"
+            fn max(a: u32, b: u32) -> u32 {
+                if a > b {
+                    a
+                } else {
+                    b
+                }
+            }
+        "# };
+        let options = Options {
+            no_default_genres: true,
+            opt_in_genres: vec![crate::Genre::IfBranch],
+            ..Default::default()
+        };
+        let mutants = mutants_in_code(code, &options);
+        assert_eq!(mutants.len(), 2);
+        assert!(mutants.iter().all(|m| m.genre == crate::Genre::IfBranch));
+
+        let options = Options {
+            no_default_genres: true,
+            ..Default::default()
+        };
+        assert_eq!(mutants_in_code(code, &options), []);
+    }
+
+    #[test]
+    fn else_if_chain_replaced_by_each_value() {
+        let code = indoc::indoc! { r#"
+            fn sign(a: i32) -> i32 {
+                if a < 0 { -1 } else if a > 0 { 1 } else { 0 }
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["0.0", "1.0", "-1.0"]
+            describe_mutants_of_genre(code, crate::Genre::IfBranch),
+            [
+                "src/lib.rs:2: replace if a < 0 { -1 } else if a > 0 { 1 } else { 0 } with { -1 } in sign",
+                "src/lib.rs:2: replace if a > 0 { 1 } else { 0 } with { 1 } in sign",
+                "src/lib.rs:2: replace if a > 0 { 1 } else { 0 } with { 0 } in sign",
+            ]
         );
     }
 
     #[test]
-    fn ref_replacement_recurses() {
-        let reps = return_type_replacements(&parse_quote! { -> &bool }, &[]);
+    fn if_without_single_value_branches_is_not_replaced() {
+        let code = indoc::indoc! { r#"
+            fn f(a: Option<u32>, b: bool) -> u32 {
+                if b {
+                    println!("b");
+                }
+                if b {
+                    println!("yes");
+                } else {
+                    println!("no");
+                }
+                let x = if b { 1 } else { let y = 2; y };
+                if let Some(a) = a { a } else { x }
+            }
+        "# };
         assert_eq!(
-            reps.iter().map(tokens_to_pretty_string).collect::<Vec<_>>(),
-            &["&true", "&false"]
+            describe_mutants_of_genre(code, crate::Genre::IfBranch),
+            Vec::<String>::new()
         );
     }
 
     #[test]
-    fn array_replacement() {
+    fn force_return_guard_conditions() {
+        let code = indoc::indoc! { r#"
+            fn parse(s: &str) -> Option<u32> {
+                if s.is_empty() {
+                    return None;
+                }
+                if s.len() > 10 { return Some(0) }
+                if let Some(rest) = s.strip_prefix('+') {
+                    return rest.parse().ok();
+                }
+                if s == "-" {
+                    println!("minus");
+                    return None;
+                }
+                if true {
+                    return None;
+                }
+                s.parse().ok()
+            }
+        "# };
         assert_eq!(
-            replace(&parse_quote! { -> [u8; 256] }, &[]),
-            &["[0; 256]", "[1; 256]"]
+            describe_mutants_of_genre(code, crate::Genre::IfBranch),
+            [
+                "src/lib.rs:2: replace s.is_empty() with true in parse",
+                "src/lib.rs:2: replace s.is_empty() with false in parse",
+                "src/lib.rs:5: replace s.len() > 10 with true in parse",
+                "src/lib.rs:5: replace s.len() > 10 with false in parse",
+                "src/lib.rs:13: replace true with false in parse",
+            ]
         );
     }
 
     #[test]
-    fn arc_replacement() {
-        // Also checks that it matches the path, even using an atypical path.
-        // TODO: Ideally this would be fully qualified like `alloc::sync::Arc::new(String::new())`.
+    fn force_field_conditions() {
+        let code = indoc::indoc! { r#"
+            impl Worker {
+                fn step(&mut self) {
+                    if self.enabled {
+                        self.count += 1;
+                    }
+                    if !(self.state.done) {
+                        self.finish();
+                    }
+                    while self.running {
+                        self.poll();
+                    }
+                    if self.enabled && self.verbose {
+                        self.log();
+                    }
+                    if self.paused {
+                        return;
+                    }
+                }
+            }
+        "# };
         assert_eq!(
-            replace(&parse_quote! { -> alloc::sync::Arc<String> }, &[]),
-            &["Arc::new(String::new())", "Arc::new(\"xyzzy\".into())"]
+            describe_mutants_of_genre(code, crate::Genre::IfBranch),
+            [
+                "src/lib.rs:3: replace self.enabled with true in Worker::step",
+                "src/lib.rs:3: replace self.enabled with false in Worker::step",
+                "src/lib.rs:6: replace self.state.done with true in Worker::step",
+                "src/lib.rs:6: replace self.state.done with false in Worker::step",
+                "src/lib.rs:9: replace self.running with true in Worker::step",
+                "src/lib.rs:9: replace self.running with false in Worker::step",
+                "src/lib.rs:15: replace self.paused with true in Worker::step",
+                "src/lib.rs:15: replace self.paused with false in Worker::step",
+            ]
         );
     }
 
-    // #[test]
-    // fn rc_replacement() {
-    //     // Also checks that it matches the path, even using an atypical path.
-    //     // TODO: Ideally this would be fully qualified like `alloc::sync::Rc::new(String::new())`.
-    //     assert_eq!(
-    //         replace(&parse_quote! { -> alloc::sync::Rc<String> }, &[]),
-    //         &["Rc::new(String::new())", "Rc::new(\"xyzzy\".into())"]
-    //     );
-    // }
+    #[test]
+    fn no_default_for_configured_types() {
+        let no_default_types = ["Handle".to_owned()];
+        let replace = |return_type: ReturnType| {
+            return_type_replacements(&return_type, &[], &no_default_types)
+                .into_iter()
+                .map(tokens_to_pretty_string)
+                .collect_vec()
+        };
+        assert_eq!(replace(parse_quote! { -> Handle }), Vec::<String>::new());
+        assert_eq!(
+            replace(parse_quote! { -> crate::io::Handle }),
+            Vec::<String>::new()
+        );
+        assert_eq!(replace(parse_quote! { -> Option<Handle> }), ["None"]);
+        assert_eq!(replace(parse_quote! { -> Vec<Handle> }), ["vec![]"]);
+        assert_eq!(
+            replace(parse_quote! { -> HashMap<String, Handle> }),
+            ["Default::default()"]
+        );
+        assert_eq!(replace(parse_quote! { -> Handler }), ["Default::default()"]);
+    }
 
     fn replace(return_type: &ReturnType, error_exprs: &[Expr]) -> Vec<String> {
-        return_type_replacements(return_type, error_exprs)
+        return_type_replacements(return_type, error_exprs, &[])
             .into_iter()
             .map(tokens_to_pretty_string)
             .collect::<Vec<_>>()
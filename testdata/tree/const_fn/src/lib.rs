@@ -0,0 +1,70 @@
+//! `const fn`s can only be replaced by values that can be evaluated in a
+//! const context, so for example `Default::default()` and `"xyzzy".into()`
+//! are not generated.
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub const fn is_even(n: u32) -> bool {
+    n % 2 == 0
+}
+
+pub const fn double(n: u32) -> u32 {
+    n * 2
+}
+
+pub const fn negate(n: i32) -> i32 {
+    -n
+}
+
+pub const fn name() -> &'static str {
+    "const"
+}
+
+pub const fn checked_half(n: u32) -> Option<u32> {
+    if n % 2 == 0 {
+        Some(n / 2)
+    } else {
+        None
+    }
+}
+
+pub const fn origin() -> Point {
+    Point { x: 0, y: 0 }
+}
+
+pub const LIMIT: u32 = double(21);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evens() {
+        assert!(is_even(2));
+        assert!(!is_even(3));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(LIMIT, 42);
+        assert_eq!(double(3), 6);
+        assert_eq!(negate(5), -5);
+    }
+
+    #[test]
+    fn halves() {
+        assert_eq!(checked_half(8), Some(4));
+        assert_eq!(checked_half(2), Some(1));
+        assert_eq!(checked_half(3), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(name(), "const");
+        assert_eq!(origin(), Point { x: 0, y: 0 });
+    }
+}
@@ -0,0 +1,70 @@
+//! Hand-written implementations of `Iterator::next`.
+//!
+//! Mutants that make `next` return `None` end the iteration immediately, and
+//! mutants that make it always return `Some` never end it, so the tests only
+//! take a limited number of items.
+
+/// Counts down from `n` to zero, not including `n`.
+pub struct Countdown(u32);
+
+impl Countdown {
+    pub fn from(n: u32) -> Countdown {
+        Countdown(n)
+    }
+}
+
+impl Iterator for Countdown {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            None
+        } else {
+            self.0 -= 1;
+            Some(self.0)
+        }
+    }
+}
+
+/// Iterates the space-separated words in a string.
+pub struct Words<'a> {
+    s: &'a str,
+}
+
+pub fn words(s: &str) -> Words<'_> {
+    Words { s }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let s = self.s.trim_start();
+        if s.is_empty() {
+            return None;
+        }
+        let end = s.find(' ').unwrap_or(s.len());
+        self.s = &s[end..];
+        Some(&s[..end])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn countdown() {
+        assert_eq!(Countdown::from(3).take(10).collect::<Vec<_>>(), [2, 1, 0]);
+        assert_eq!(Countdown::from(0).next(), None);
+    }
+
+    #[test]
+    fn split_words() {
+        assert_eq!(
+            words(" one two  three").take(10).collect::<Vec<_>>(),
+            ["one", "two", "three"]
+        );
+        assert_eq!(words("  ").next(), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! Example of operator overloads whose return type is the trait's `Output`.
+//!
+//! `Point` derives `Default`, so its operators can be replaced by a default value, but
+//! `Meters` doesn't, so operators returning it are not mutated.
+
+use std::ops::{Add, Div, Neg};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Meters(pub f64);
+
+impl Neg for Meters {
+    type Output = Meters;
+
+    fn neg(self) -> Self::Output {
+        Meters(-self.0)
+    }
+}
+
+impl Div for Meters {
+    type Output = f64;
+
+    fn div(self, other: Meters) -> <Self as Div>::Output {
+        self.0 / other.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn add_points() {
+        assert_eq!(
+            Point { x: 1, y: 2 } + Point { x: 3, y: 4 },
+            Point { x: 4, y: 6 }
+        );
+    }
+
+    #[test]
+    fn negate_meters() {
+        assert_eq!(-Meters(2.0), Meters(-2.0));
+    }
+
+    #[test]
+    fn divide_meters() {
+        assert_eq!(Meters(6.0) / Meters(4.0), 1.5);
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2023 Martin Pool
+
+//! Tests for running cargo-mutants through its library API.
+
+use std::fs;
+
+use camino::Utf8Path;
+use cargo_mutants::{run, Genre, MutantOutcome};
+use tempfile::tempdir;
+
+#[test]
+fn run_returns_outcome_of_each_mutant() {
+    let output = tempdir().unwrap();
+    let output_path = Utf8Path::from_path(output.path()).unwrap();
+    let report = run(
+        Utf8Path::new("testdata/tree/small_well_tested"),
+        ["--no-shuffle", "--output", output_path.as_str()],
+    )
+    .unwrap();
+    assert!(report.baseline_passed);
+    assert_eq!(report.total_mutants, 2);
+    assert_eq!(report.caught, 2);
+    assert_eq!(report.missed, 0);
+    assert_eq!(report.exit_code, 0);
+    assert_eq!(
+        report
+            .mutants
+            .iter()
+            .map(|m| (m.name.as_str(), m.line, m.genre, m.outcome))
+            .collect::<Vec<_>>(),
+        [
+            (
+                "src/lib.rs:4: replace factorial -> u32 with 0",
+                4,
+                Genre::FnValue,
+                MutantOutcome::Caught
+            ),
+            (
+                "src/lib.rs:4: replace factorial -> u32 with 1",
+                4,
+                Genre::FnValue,
+                MutantOutcome::Caught
+            ),
+        ]
+    );
+    assert!(output_path.join("mutants.out/outcomes.json").is_file());
+}
+
+#[test]
+fn run_reads_config_from_the_tree() {
+    let tmp_src_dir = tempdir().unwrap();
+    cp_r::CopyOptions::new()
+        .filter(|path, _stat| Ok(!path.starts_with("target") && !path.starts_with("mutants.out")))
+        .copy_tree("testdata/tree/small_well_tested", tmp_src_dir.path())
+        .unwrap();
+    fs::create_dir(tmp_src_dir.path().join(".cargo")).unwrap();
+    fs::write(
+        tmp_src_dir.path().join(".cargo/mutants.toml"),
+        "exclude_re = [\"with 0\"]\n",
+    )
+    .unwrap();
+    let report = run(
+        Utf8Path::from_path(tmp_src_dir.path()).unwrap(),
+        ["--no-shuffle"],
+    )
+    .unwrap();
+    assert_eq!(report.total_mutants, 1);
+    assert_eq!(
+        report.mutants[0].name,
+        "src/lib.rs:4: replace factorial -> u32 with 1"
+    );
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+    assert!(run(
+        Utf8Path::new("testdata/tree/small_well_tested"),
+        ["--no-such-option"]
+    )
+    .is_err());
+}
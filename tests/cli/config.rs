@@ -2,7 +2,7 @@
 
 //! Test handling of `mutants.toml` configuration.
 
-use std::fs::{create_dir, write};
+use std::fs::{create_dir, create_dir_all, write};
 
 use predicates::prelude::*;
 use tempfile::TempDir;
@@ -52,6 +52,39 @@ fn invalid_field_rejected() {
         );
 }
 
+#[test]
+fn config_profile_is_merged_over_base_config() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(
+        &testdata,
+        r#"exclude_globs = ["src/*_mod.rs"]
+
+        [profiles.quick]
+        examine_globs = ["src/simple_fns.rs"]
+        "#,
+    );
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("src/methods.rs"));
+    run()
+        .args(["mutants", "--list-files", "--config-profile", "quick", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .success()
+        .stdout("src/simple_fns.rs\n");
+    run()
+        .args(["mutants", "--list-files", "--config-profile", "slow", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            r#"config profile "slow" is not defined; the config has profiles "quick""#,
+        ));
+}
+
 #[test]
 fn list_with_config_file_exclusion() {
     let testdata = copy_of_testdata("well_tested");
@@ -120,6 +153,33 @@ fn list_with_config_file_regexps() {
         ));
 }
 
+#[test]
+fn list_with_config_file_skip_traits() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(
+        &testdata,
+        r#"
+        examine_globs = ["src/methods.rs"]
+        skip_traits = ["Debug"]
+        "#,
+    );
+    run()
+        .args(["mutants", "--list", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("<impl Display for Foo>::fmt"))
+        .stdout(predicates::str::contains("<impl Debug for &Foo>::fmt").not());
+    // The command line replaces the list from the config file.
+    run()
+        .args(["mutants", "--list", "--skip-trait", "Display", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("<impl Display for Foo>::fmt").not())
+        .stdout(predicates::str::contains("<impl Debug for &Foo>::fmt"));
+}
+
 #[test]
 fn tree_fails_without_needed_feature() {
     // The point of this tree is to check that Cargo features can be turned on,
@@ -172,3 +232,169 @@ fn additional_cargo_test_args() {
         .success()
         .stdout(predicates::str::contains("2 caught"));
 }
+
+#[test]
+fn config_option_reads_given_file() {
+    let testdata = copy_of_testdata("well_tested");
+    let config_dir = TempDir::new().unwrap();
+    let config_path = config_dir.path().join("custom.toml");
+    write(
+        &config_path,
+        r#"
+        examine_re = ["divisible"]
+        exclude_re = ["-> bool with true"]
+        "#
+        .as_bytes(),
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list", "-d"])
+        .arg(testdata.path())
+        .arg("--config")
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(
+            "src/simple_fns.rs:17: replace divisible_by_three -> bool with false\n",
+        ));
+}
+
+#[test]
+fn config_option_overrides_tree_config() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(
+        &testdata,
+        r#"exclude_globs = ["src/*_mod.rs"]
+        "#,
+    );
+    let config_dir = TempDir::new().unwrap();
+    let config_path = config_dir.path().join("empty.toml");
+    write(&config_path, b"").unwrap();
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .arg("--config")
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("src/inside_mod.rs"));
+}
+
+#[test]
+fn config_option_with_missing_file_fails() {
+    let testdata = copy_of_testdata("well_tested");
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .args(["--config", "/nonexistent/mutants.toml"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("does not exist"));
+}
+
+#[test]
+fn config_is_found_in_parent_of_dir_up_to_tree_root() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(&testdata, "exclude_globs = [\"src/*_mod.rs\"]\n");
+    // Run from a subdirectory of the tree, so the config is found in a parent.
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path().join("src"))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("_mod.rs").not())
+        .stdout(predicates::str::contains("src/simple_fns.rs"));
+}
+
+#[test]
+fn nearest_config_is_used() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(&testdata, "exclude_globs = [\"src/*_mod.rs\"]\n");
+    let src_config_dir = testdata.path().join("src/.cargo");
+    create_dir_all(&src_config_dir).unwrap();
+    write(
+        src_config_dir.join("mutants.toml"),
+        b"examine_globs = [\"src/*_mod.rs\"]\n",
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path().join("src"))
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(
+            "src/inside_mod.rs\nsrc/item_mod.rs\n",
+        ));
+    // From the tree root, the config in the subdirectory is not used.
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("_mod.rs").not());
+}
+
+#[test]
+fn config_dir_env_var_overrides_tree_config() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(&testdata, "exclude_globs = [\"src/*_mod.rs\"]\n");
+    let config_dir = TempDir::new().unwrap();
+    write(
+        config_dir.path().join("mutants.toml"),
+        b"examine_globs = [\"src/inside_mod.rs\"]\n",
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .env("CARGO_MUTANTS_CONFIG_DIR", config_dir.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::diff("src/inside_mod.rs\n"));
+}
+
+#[test]
+fn config_dir_env_var_without_config_file_fails() {
+    let testdata = copy_of_testdata("well_tested");
+    let config_dir = TempDir::new().unwrap();
+    run()
+        .args(["mutants", "--list-files", "-d"])
+        .arg(testdata.path())
+        .env("CARGO_MUTANTS_CONFIG_DIR", config_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "from CARGO_MUTANTS_CONFIG_DIR does not exist",
+        ));
+}
+
+#[test]
+fn print_config_shows_merged_options() {
+    let testdata = copy_of_testdata("well_tested");
+    write_config_file(
+        &testdata,
+        r#"exclude_globs = ["src/*_mod.rs"]
+        additional_cargo_args = ["--all-features"]
+        "#,
+    );
+    let template_path = testdata.path().join("template.txt");
+    write(&template_path, "{file}:{line} {outcome}\n").unwrap();
+    let output = run()
+        .args(["mutants", "--print-config", "--exclude", "src/lib.rs", "-d"])
+        .arg(testdata.path())
+        .arg("--report-template")
+        .arg(&template_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let config: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        config["exclude_globs"],
+        serde_json::json!(["src/lib.rs", "src/*_mod.rs"])
+    );
+    assert_eq!(
+        config["additional_cargo_args"],
+        serde_json::json!(["--all-features"])
+    );
+    assert_eq!(config["report_template"], "{file}:{line} {outcome}");
+}
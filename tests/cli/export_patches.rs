@@ -0,0 +1,51 @@
+// Copyright 2023 Martin Pool
+
+//! Tests for `--export-patches`.
+
+use std::fs::read_to_string;
+use std::process::Command;
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+use super::{copy_of_testdata, run};
+
+#[test]
+fn export_patches_writes_applicable_patches_and_manifest() {
+    let testdata = copy_of_testdata("factorial");
+    let export_dir = TempDir::new().unwrap();
+    run()
+        .args(["mutants", "--export-patches"])
+        .arg(export_dir.path())
+        .arg("-d")
+        .arg(testdata.path())
+        .assert()
+        .success();
+    assert!(
+        !testdata.path().join("mutants.out").exists(),
+        "no tests are run"
+    );
+
+    let manifest: Value =
+        serde_json::from_str(&read_to_string(export_dir.path().join("manifest.json")).unwrap())
+            .unwrap();
+    let entries = manifest.as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    for entry in entries {
+        let id = entry["id"].as_str().unwrap();
+        assert_eq!(entry["mutant"]["id"], id);
+        let patch_name = entry["patch"].as_str().unwrap();
+        assert_eq!(patch_name, format!("{id}.patch"));
+        let patch_path = export_dir.path().join(patch_name);
+        let patch = read_to_string(&patch_path).unwrap();
+        assert!(patch.starts_with("src/bin/factorial.rs:"), "{patch}");
+        assert!(patch.contains("--- a/src/bin/factorial.rs\n+++ b/src/bin/factorial.rs\n"));
+        let status = Command::new("git")
+            .args(["apply", "--check"])
+            .arg(&patch_path)
+            .current_dir(testdata.path())
+            .status()
+            .expect("run git apply");
+        assert!(status.success(), "patch {patch_name} doesn't apply");
+    }
+}
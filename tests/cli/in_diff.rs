@@ -0,0 +1,316 @@
+// Copyright 2023 Martin Pool
+
+//! Tests for selecting mutants from code changed in git.
+
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::Command;
+
+use predicates::prelude::*;
+
+use super::{copy_of_testdata, run};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn since_commits_selects_only_changed_functions() {
+    let testdata = copy_of_testdata("well_tested");
+    let dir = testdata.path();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Initial"]);
+
+    let path = dir.join("src/simple_fns.rs");
+    let code = read_to_string(&path).unwrap();
+    write(&path, code.replace("a % 3 == 0", "a % 3 == 0 && true")).unwrap();
+    git(
+        dir,
+        &["commit", "-q", "-a", "-m", "Change divisible_by_three"],
+    );
+
+    run()
+        .args(["mutants", "--list", "--since-commits", "1", "-d"])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "src/simple_fns.rs:17: replace divisible_by_three -> bool with true\n\
+             src/simple_fns.rs:17: replace divisible_by_three -> bool with false\n",
+        ));
+
+    // With more commits than the history, everything is selected.
+    run()
+        .args(["mutants", "--list", "--since-commits", "5", "-d"])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("returns_42u32"));
+}
+
+#[test]
+fn dry_run_diff_only_shows_changed_lines_and_selected_mutants() {
+    let testdata = copy_of_testdata("well_tested");
+    let dir = testdata.path();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Initial"]);
+
+    let path = dir.join("src/simple_fns.rs");
+    let code = read_to_string(&path).unwrap();
+    write(&path, code.replace("a % 3 == 0", "a % 3 == 0 && true")).unwrap();
+    git(
+        dir,
+        &["commit", "-q", "-a", "-m", "Change divisible_by_three"],
+    );
+
+    run()
+        .args([
+            "mutants",
+            "--dry-run-diff-only",
+            "--since-commits",
+            "1",
+            "-d",
+        ])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "Changed lines:\n\
+             src/simple_fns.rs: 18\n\
+             Selected mutants:\n\
+             src/simple_fns.rs:17: replace divisible_by_three -> bool with true\n\
+             src/simple_fns.rs:17: replace divisible_by_three -> bool with false\n",
+        ));
+}
+
+#[test]
+fn dry_run_diff_only_requires_since_commits() {
+    run()
+        .args(["mutants", "--dry-run-diff-only"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("--since-commits"));
+}
+
+#[test]
+fn since_commits_outside_git_fails() {
+    let testdata = copy_of_testdata("factorial");
+    run()
+        .args(["mutants", "--list", "--since-commits", "1", "-d"])
+        .arg(testdata.path())
+        .env("GIT_CEILING_DIRECTORIES", testdata.path().parent().unwrap())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("git"));
+}
+
+#[test]
+fn since_commits_zero_is_rejected() {
+    run()
+        .args(["mutants", "--list", "--since-commits", "0"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("--since-commits"));
+}
+
+#[test]
+fn only_package_with_changes_selects_whole_packages() {
+    let testdata = copy_of_testdata("workspace");
+    let dir = testdata.path();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Initial"]);
+
+    // Change only a test in the utils package, so that no mutated function changed.
+    let path = dir.join("utils/src/lib.rs");
+    let code = read_to_string(&path).unwrap();
+    write(
+        &path,
+        code.replace(
+            "assert_eq!(triple(3), 9);",
+            "assert_eq!(triple(3), 9);\n    assert_eq!(triple(0), 0);",
+        ),
+    )
+    .unwrap();
+    git(dir, &["commit", "-q", "-a", "-m", "Test triple of zero"]);
+
+    run()
+        .args(["mutants", "--list", "--since-commits", "1", "-d"])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout("");
+
+    run()
+        .args([
+            "mutants",
+            "--list",
+            "--since-commits",
+            "1",
+            "--only-package-with-changes",
+            "-d",
+        ])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "utils/src/lib.rs:1: replace triple -> i32 with 0\n\
+             utils/src/lib.rs:1: replace triple -> i32 with 1\n\
+             utils/src/lib.rs:1: replace triple -> i32 with -1\n",
+        ));
+
+    // Both binaries depend on utils.
+    run()
+        .args([
+            "mutants",
+            "--list",
+            "--since-commits",
+            "1",
+            "--only-package-with-changes",
+            "--include-dependents",
+            "-d",
+        ])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("main/src/main.rs:11: replace factorial")
+                .and(predicate::str::contains(
+                    "main2/src/main.rs:9: replace triple_3",
+                ))
+                .and(predicate::str::contains(
+                    "utils/src/lib.rs:1: replace triple",
+                )),
+        );
+}
+
+#[test]
+fn only_package_with_changes_requires_since_commits() {
+    run()
+        .args(["mutants", "--list", "--only-package-with-changes", "-d"])
+        .arg("testdata/tree/workspace")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--since-commits"));
+}
+
+#[test]
+fn since_tag_selects_functions_changed_since_the_tag() {
+    let testdata = copy_of_testdata("well_tested");
+    let dir = testdata.path();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Initial"]);
+    git(dir, &["tag", "v1.0.0"]);
+
+    let path = dir.join("src/simple_fns.rs");
+    let code = read_to_string(&path).unwrap();
+    write(&path, code.replace("a % 3 == 0", "a % 3 == 0 && true")).unwrap();
+    git(
+        dir,
+        &["commit", "-q", "-a", "-m", "Change divisible_by_three"],
+    );
+    git(dir, &["commit", "-q", "--allow-empty", "-m", "Another"]);
+
+    let expected = "src/simple_fns.rs:17: replace divisible_by_three -> bool with true\n\
+         src/simple_fns.rs:17: replace divisible_by_three -> bool with false\n";
+    for tag in ["v1.0.0", "latest"] {
+        run()
+            .args(["mutants", "--list", "--since-tag", tag, "-d"])
+            .arg(dir)
+            .assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+
+    run()
+        .args(["mutants", "--list", "--since-tag", "v0.9.0", "-d"])
+        .arg(dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("git tag \"v0.9.0\" not found"));
+    run()
+        .args([
+            "mutants",
+            "--list",
+            "--since-tag",
+            "v1.0.0",
+            "--since-commits",
+            "1",
+            "-d",
+        ])
+        .arg(dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn only_new_files_selects_added_and_untracked_files() {
+    let testdata = copy_of_testdata("well_tested");
+    let dir = testdata.path();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Initial"]);
+
+    let lib_path = dir.join("src/lib.rs");
+    let lib = read_to_string(&lib_path).unwrap();
+    write(&lib_path, format!("{lib}\nmod added;\nmod untracked;\n")).unwrap();
+    write(
+        dir.join("src/added.rs"),
+        "pub fn added() -> u32 {\n    1\n}\n",
+    )
+    .unwrap();
+    let path = dir.join("src/simple_fns.rs");
+    let code = read_to_string(&path).unwrap();
+    write(&path, code.replace("a % 3 == 0", "a % 3 == 0 && true")).unwrap();
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Add a file"]);
+    write(
+        dir.join("src/untracked.rs"),
+        "pub fn untracked() -> bool {\n    true\n}\n",
+    )
+    .unwrap();
+
+    run()
+        .args([
+            "mutants",
+            "--list",
+            "--since-commits",
+            "1",
+            "--only-new-files",
+            "-d",
+        ])
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "src/added.rs:1: replace added -> u32 with 0\n\
+             src/untracked.rs:1: replace untracked -> bool with false\n",
+        ));
+}
+
+#[test]
+fn only_new_files_requires_since_commits() {
+    run()
+        .args(["mutants", "--list", "--only-new-files", "-d"])
+        .arg("testdata/tree/well_tested")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--since-commits"));
+}
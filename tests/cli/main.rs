@@ -23,6 +23,8 @@ use tempfile::{tempdir, TempDir};
 
 mod config;
 mod error_value;
+mod export_patches;
+mod in_diff;
 mod jobs;
 mod trace;
 #[cfg(windows)]
@@ -59,6 +61,8 @@ fn run() -> assert_cmd::Command {
         .for_each(|k| {
             cmd.env_remove(k);
         });
+    // Don't print GitHub annotations when the tests themselves run in GitHub Actions.
+    cmd.env_remove("GITHUB_ACTIONS");
     cmd
 }
 
@@ -415,6 +419,50 @@ fn list_mutants_json_well_tested() {
         .assert_insta("list_mutants_json_well_tested");
 }
 
+#[test]
+fn list_mutants_markdown_well_tested() {
+    run()
+        .arg("mutants")
+        .arg("--list")
+        .arg("--markdown")
+        .current_dir("testdata/tree/well_tested")
+        .assert_insta("list_mutants_markdown_well_tested");
+}
+
+#[test]
+fn list_markdown_conflicts_with_json() {
+    run()
+        .args(["mutants", "--list", "--markdown", "--json"])
+        .current_dir("testdata/tree/well_tested")
+        .assert()
+        .code(1)
+        .stderr(contains("cannot be used with"));
+}
+
+#[test]
+fn list_mutants_grouped_by_genre() {
+    run()
+        .args(["mutants", "--list", "--group-by-genre"])
+        .current_dir("testdata/tree/well_tested")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(indoc! { r#"
+            fn-value: 25 mutants
+                src/arc.rs:3: replace return_arc -> Arc<String> with Arc::new(String::new())
+                src/arc.rs:3: replace return_arc -> Arc<String> with Arc::new("xyzzy".into())
+                src/inside_mod.rs:3: replace outer::inner::name -> &'static str with ""
+                ... and 22 more
+            if-branch: 2 mutants
+        "# }))
+        .stdout(contains("result-flip: 1 mutant\n"));
+    run()
+        .args(["mutants", "--list", "--group-by-genre", "--json"])
+        .current_dir("testdata/tree/well_tested")
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+}
+
 #[test]
 fn list_files_text_well_tested() {
     run()
@@ -438,6 +486,37 @@ fn list_files_respects_file_filters() {
         .stdout(predicate::str::contains("lib.rs").not());
 }
 
+#[test]
+fn list_files_respects_file_lists() {
+    let list_dir = tempdir().unwrap();
+    let examine_list = list_dir.path().join("examine.txt");
+    fs::write(
+        &examine_list,
+        "# Changed files\nsrc/methods.rs\n./src/simple_fns.rs\n\nsrc/nested_function.rs\n",
+    )
+    .unwrap();
+    let exclude_list = list_dir.path().join("exclude.txt");
+    fs::write(&exclude_list, "src/nested_function.rs\n").unwrap();
+    run()
+        .arg("mutants")
+        .arg("--list-files")
+        .arg("--examine-file-list")
+        .arg(&examine_list)
+        .arg("--exclude-file-list")
+        .arg(&exclude_list)
+        .current_dir("testdata/tree/well_tested")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("src/methods.rs\nsrc/simple_fns.rs\n"));
+    run()
+        .args(["mutants", "--list-files", "--examine-file-list"])
+        .arg(list_dir.path().join("missing.txt"))
+        .current_dir("testdata/tree/well_tested")
+        .assert()
+        .failure()
+        .stderr(contains("read file list"));
+}
+
 #[test]
 fn list_files_json_well_tested() {
     run()
@@ -448,6 +527,35 @@ fn list_files_json_well_tested() {
         .assert_insta("list_files_json_well_tested");
 }
 
+#[test]
+fn list_genres_text() {
+    // Doesn't need to be run in a source tree.
+    run()
+        .args(["mutants", "--list-genres"])
+        .current_dir(env::temp_dir())
+        .assert()
+        .success()
+        .stdout(is_match(r"(?m)^fn-value +FnValue +Replace the body of a function").unwrap())
+        .stdout(contains("default-value"));
+}
+
+#[test]
+fn list_genres_json() {
+    let output = run()
+        .args(["mutants", "--list-genres", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let genres: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let genres = genres.as_array().unwrap();
+    assert!(genres.len() >= 2);
+    assert_eq!(genres[0]["genre"], "FnValue");
+    assert_eq!(genres[0]["name"], "fn-value");
+    assert!(genres
+        .iter()
+        .all(|g| !g["description"].as_str().unwrap().is_empty()));
+}
+
 #[test]
 fn list_files_json_workspace() {
     // Demonstrates that we get package names in the json listing.
@@ -543,6 +651,287 @@ fn copy_testdata_doesnt_include_build_artifacts() {
     assert!(tmp_src_dir.path().join("Cargo.toml").exists());
 }
 
+/// `--only-missing-from` selects only mutants whose ids weren't in an earlier list.
+#[test]
+fn only_missing_from_selects_new_mutants() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let report_path = tmp_src_dir.path().join("before.json");
+    let output = run()
+        .args(["mutants", "--list", "--json"])
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    fs::write(&report_path, output.stdout).unwrap();
+
+    // Move the existing function down, and add a new one.
+    let lib_path = tmp_src_dir.path().join("src/lib.rs");
+    let lib = fs::read_to_string(&lib_path).unwrap();
+    fs::write(
+        &lib_path,
+        lib.replace(
+            "pub fn factorial",
+            "pub fn double(n: u32) -> u32 {\n    n * 2\n}\n\npub fn factorial",
+        ),
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list", "--only-missing-from"])
+        .arg(&report_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::diff(indoc! { "
+            src/lib.rs:4: replace double -> u32 with 0
+            src/lib.rs:4: replace double -> u32 with 1
+        " }));
+    run()
+        .args([
+            "mutants",
+            "--list",
+            "--only-missing-from",
+            "/nonexistent.json",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .failure()
+        .stderr(contains("read mutants report"));
+}
+
+/// `--compare` shows how the outcomes differ from an earlier run.
+#[test]
+fn compare_with_earlier_outcomes() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .success();
+
+    // Pretend that both mutants were missed before, and that there was another
+    // mutant that no longer exists.
+    let outcomes_json =
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap();
+    let mut outcomes: serde_json::Value = serde_json::from_str(&outcomes_json).unwrap();
+    let outcomes_list = outcomes["outcomes"].as_array_mut().unwrap();
+    let mut gone = outcomes_list[1].clone();
+    gone["scenario"]["Mutant"]["id"] = "000000000000".into();
+    gone["scenario"]["Mutant"]["function"] = "gone".into();
+    outcomes_list.push(gone);
+    for outcome in outcomes_list.iter_mut().skip(1) {
+        outcome["summary"] = "MissedMutant".into();
+    }
+    let before_path = tmp_src_dir.path().join("before.json");
+    fs::write(&before_path, outcomes.to_string()).unwrap();
+
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle", "--compare"])
+        .arg(&before_path)
+        .current_dir(tmp_src_dir.path())
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .success()
+        .stdout(contains(indoc! { "
+            0 newly missed
+            2 newly caught
+                src/lib.rs:4: replace factorial -> u32 with 0
+                src/lib.rs:4: replace factorial -> u32 with 1
+            1 no longer exist
+                src/lib.rs:4: gone (id 000000000000)
+        " }));
+    let comparison: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(tmp_src_dir.path().join("mutants.out/compare.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(comparison["newly_missed"].as_array().unwrap().len(), 0);
+    assert_eq!(comparison["newly_caught"].as_array().unwrap().len(), 2);
+    assert_eq!(comparison["removed"][0]["id"], "000000000000");
+}
+
+/// `--list-affected-tests` shows how many tests cover each mutant, from an lcov tracefile.
+#[test]
+fn list_affected_tests_from_lcov() {
+    let tmp_dir = tempdir().unwrap();
+    let lcov_path = tmp_dir.path().join("lcov.info");
+    fs::write(
+        &lcov_path,
+        indoc! { "
+            TN:test_factorial
+            SF:src/bin/factorial.rs
+            DA:7,1
+            DA:8,1
+            end_of_record
+            SF:/home/user/.cargo/registry/src/index/some-dep-1.0/src/bin/factorial.rs
+            DA:1,1
+            end_of_record
+        " },
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list-affected-tests"])
+        .arg(&lcov_path)
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success()
+        .stdout(indoc! { "
+            src/bin/factorial.rs:1: replace main with () ... not covered
+            src/bin/factorial.rs:7: replace factorial -> u32 with 0 ... covered by 1 test
+            src/bin/factorial.rs:7: replace factorial -> u32 with 1 ... covered by 1 test
+        " });
+    let output = run()
+        .args(["mutants", "--json", "--list-affected-tests"])
+        .arg(&lcov_path)
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert_eq!(json[0]["tests"], serde_json::json!([]));
+    assert_eq!(json[1]["tests"], serde_json::json!(["test_factorial"]));
+}
+
+/// `--coverage-prefix` matches a tracefile collected in another checkout of the tree.
+#[test]
+fn list_affected_tests_with_coverage_prefix() {
+    let tmp_dir = tempdir().unwrap();
+    let lcov_path = tmp_dir.path().join("lcov.info");
+    fs::write(
+        &lcov_path,
+        indoc! { "
+            TN:test_factorial
+            SF:/home/ci/checkout/src/bin/factorial.rs
+            DA:1,1
+            end_of_record
+        " },
+    )
+    .unwrap();
+    run()
+        .args(["mutants", "--list-affected-tests"])
+        .arg(&lcov_path)
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success()
+        .stdout(contains("replace main with () ... not covered"));
+    run()
+        .args(["mutants", "--list-affected-tests"])
+        .arg(&lcov_path)
+        .args(["--coverage-prefix", "/home/ci/checkout"])
+        .current_dir("testdata/tree/factorial")
+        .assert()
+        .success()
+        .stdout(contains("replace main with () ... covered by 1 test"));
+}
+
+/// `--list-survivors` shows the mutants missed in an earlier run, with their diffs,
+/// without running the tests.
+#[test]
+fn list_survivors_from_earlier_outcomes() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let mutant = |id: &str, function: &str, summary: &str| {
+        serde_json::json!({
+            "scenario": { "Mutant": {
+                "id": id, "file": "src/lib.rs", "line": 4, "function": function,
+            } },
+            "summary": summary,
+        })
+    };
+    let outcomes = serde_json::json!({
+        "outcomes": [
+            { "scenario": "Baseline", "summary": "Success" },
+            mutant("225ffeb1a215", "factorial", "CaughtMutant"),
+            mutant("8a8d6bb93582", "factorial", "MissedMutant"),
+            mutant("000000000000", "gone", "MissedMutant"),
+        ]
+    });
+    let report_path = tmp_src_dir.path().join("outcomes.json");
+    fs::write(&report_path, outcomes.to_string()).unwrap();
+
+    run()
+        .args(["mutants", "--list-survivors", "outcomes.json"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(indoc! { "
+            2 survivors in outcomes.json
+
+            src/lib.rs: 1 survivor
+
+            src/lib.rs:4: replace factorial -> u32 with 1
+            --- src/lib.rs
+            +++ replace factorial -> u32 with 1
+        " }))
+        .stdout(contains("+1 /* ~ changed by cargo-mutants ~ */"))
+        .stdout(contains(indoc! { "
+            Not found in the tree now: 1
+                src/lib.rs:4: gone (id 000000000000)
+        " }))
+        .stdout(contains("with 0").not());
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+}
+
+/// `--dump-ast` describes the items in one file, and why any were skipped.
+#[test]
+fn dump_ast_shows_skip_reasons() {
+    run()
+        .args(["mutants", "--dump-ast", "src/methods.rs", "-d"])
+        .arg("testdata/tree/well_tested")
+        .assert()
+        .success()
+        .stdout(indoc! { "
+            src/methods.rs:11: impl Foo: 1 mutant
+            src/methods.rs:12: fn Foo::new -> Foo: skipped: constructor
+            src/methods.rs:16: fn Foo::double: 1 mutant
+            src/methods.rs:21: impl <impl Display for Foo>: skipped: impl of a skipped trait
+            src/methods.rs:27: impl <impl Debug for &Foo>: skipped: impl of a skipped trait
+            src/methods.rs:33: impl <impl Default for Foo>: skipped: impl Default
+            src/methods.rs:40: fn double #[test]: skipped: test code
+            src/methods.rs:50: fn default #[test]: skipped: test code
+            src/methods.rs:56: fn new_foo #[test]: skipped: test code
+            src/methods.rs:62: fn display_foo #[test]: skipped: test code
+            src/methods.rs:67: fn debug_ref_foo #[test]: skipped: test code
+        " });
+}
+
+/// `const fn`s are only replaced by values that can be evaluated in a const context,
+/// so none of their mutants are unviable.
+#[test]
+fn const_fn_mutants_are_all_viable() {
+    let tmp_src_dir = copy_of_testdata("const_fn");
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle"])
+        .current_dir(tmp_src_dir.path())
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .success()
+        .stdout(contains("14 mutants tested: 14 caught"));
+}
+
+/// Hand-written `Iterator::next` methods get mutants that end the iteration
+/// immediately, and that never end it.
+#[test]
+fn custom_iterator_next_mutants_are_caught() {
+    let tmp_src_dir = copy_of_testdata("custom_iterator");
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle", "-v"])
+        .current_dir(tmp_src_dir.path())
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .success()
+        .stdout(contains(
+            "replace <impl Iterator for Countdown>::next -> Option<Self::Item> with None ... caught",
+        ))
+        .stdout(contains(
+            "replace <impl Iterator for Countdown>::next -> Option<Self::Item> with Some(0) ... caught",
+        ))
+        .stdout(contains(
+            "replace <impl Iterator for Words<'a>>::next -> Option<&'a str> with None ... caught",
+        ))
+        .stdout(contains(
+            "replace <impl Iterator for Words<'a>>::next -> Option<&'a str> with Some(\"\") ... caught",
+        ))
+        .stdout(contains("8 caught, 2 unviable"));
+}
+
 #[test]
 fn small_well_tested_tree_is_clean() {
     let tmp_src_dir = copy_of_testdata("small_well_tested");
@@ -622,8 +1011,8 @@ fn well_tested_tree_quiet() {
         fs::read_to_string(tmp_src_dir.path().join("mutants.out/outcomes.json")).unwrap();
     println!("outcomes.json:\n{outcomes_json}");
     let outcomes: serde_json::Value = outcomes_json.parse().unwrap();
-    assert_eq!(outcomes["total_mutants"], 27);
-    assert_eq!(outcomes["caught"], 27);
+    assert_eq!(outcomes["total_mutants"], 28);
+    assert_eq!(outcomes["caught"], 28);
     assert_eq!(outcomes["unviable"], 0);
     assert_eq!(outcomes["missed"], 0);
 }
@@ -696,6 +1085,41 @@ fn unviable_mutation_of_struct_with_no_default() {
     );
 }
 
+#[test]
+fn unviable_errors_lists_compiler_errors() {
+    let tmp_src_dir = copy_of_testdata("struct_with_no_default");
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle", "--unviable-errors"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Unviable mutants:\n\
+             src/lib.rs:11: replace make_an_s -> S with Default::default()\n    \
+             error[E0277]: the trait bound `S: Default` is not satisfied\n",
+        ));
+}
+
+/// Operators returning `Self::Output` are mutated with values of the resolved output type,
+/// except when it's a type that can't be made by `Default::default()`.
+#[test]
+fn operator_outputs_are_mutated_and_caught() {
+    let tmp_src_dir = copy_of_testdata("operator_output");
+    run()
+        .args(["mutants", "--no-times", "--no-shuffle", "--caught"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(indoc! { "
+            src/lib.rs:17: replace <impl Add for Point>::add -> Self::Output with Default::default() ... caught
+            src/lib.rs:39: replace <impl Div for Meters>::div -> <Self as Div>::Output with 0.0 ... caught
+            src/lib.rs:39: replace <impl Div for Meters>::div -> <Self as Div>::Output with 1.0 ... caught
+            src/lib.rs:39: replace <impl Div for Meters>::div -> <Self as Div>::Output with -1.0 ... caught
+            4 mutants tested: 4 caught
+        " }));
+}
+
 #[test]
 fn integration_test_source_is_not_mutated() {
     let tmp_src_dir = copy_of_testdata("integration_tests");
@@ -762,6 +1186,41 @@ fn uncaught_mutant_in_factorial() {
     check_text_list_output(tmp_src_dir.path(), "uncaught_mutant_in_factorial");
 }
 
+#[test]
+fn annotate_github_prints_workflow_commands_for_missed_mutants() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run()
+        .args(["mutants", "--no-shuffle", "--annotate", "github", "-d"])
+        .arg(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(
+            "::warning file=src/bin/factorial.rs,line=1,endLine=5,title=Missed mutant::\
+             replace main with ()\n",
+        ))
+        // Caught mutants aren't annotated.
+        .stdout(contains("::warning file=src/bin/factorial.rs,line=7").not());
+}
+
+#[test]
+fn annotations_are_automatic_in_github_actions() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    run()
+        .args(["mutants", "--no-shuffle", "-d"])
+        .arg(tmp_src_dir.path())
+        .env("GITHUB_ACTIONS", "true")
+        .assert()
+        .code(2)
+        .stdout(contains("::warning file=src/bin/factorial.rs,line=1,"));
+    run()
+        .args(["mutants", "--no-shuffle", "--annotate", "none", "-d"])
+        .arg(tmp_src_dir.path())
+        .env("GITHUB_ACTIONS", "true")
+        .assert()
+        .code(2)
+        .stdout(contains("::warning").not());
+}
+
 #[test]
 fn factorial_mutants_with_all_logs() {
     // The log contains a lot of build output, which is hard to deal with, but let's check that
@@ -852,6 +1311,28 @@ fn small_well_tested_mutants_with_cargo_arg_release() {
         .unwrap();
 }
 
+#[test]
+fn small_well_tested_mutants_with_release_profile() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .arg("mutants")
+        .args(["--no-times", "--profile", "release"])
+        .arg("-d")
+        .arg(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("caught"));
+    // The profile is used for the baseline and for the mutants.
+    let build_re = regex::Regex::new(r"cargo.* build --tests .*--profile=release").unwrap();
+    let test_re = regex::Regex::new(r"cargo.* test .*--profile=release").unwrap();
+    let log_dir = tmp_src_dir.path().join("mutants.out/log");
+    for entry in fs::read_dir(log_dir).unwrap() {
+        let log_content = fs::read_to_string(entry.unwrap().path()).unwrap();
+        assert!(build_re.is_match(&log_content), "{log_content}");
+        assert!(test_re.is_match(&log_content), "{log_content}");
+    }
+}
+
 #[test]
 /// The `--output` directory creates the named directory if necessary, and then
 /// creates `mutants.out` within it. `mutants.out` is not created in the
@@ -952,6 +1433,62 @@ fn already_failing_tests_are_detected_before_running_mutants() {
         .stdout(predicate::str::contains("test result: FAILED. 0 passed; 1 failed;").normalize());
 }
 
+#[test]
+fn failing_baseline_command_stops_before_testing() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let summary_path = tmp_src_dir.path().join("summary.json");
+    run()
+        .args([
+            "mutants",
+            "--baseline-command",
+            "echo setup failed && exit 3",
+            "--summary-json",
+        ])
+        .arg(&summary_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(4) // exit_code::CLEAN_TESTS_FAILED
+        .stdout(contains(
+            "baseline command \"echo setup failed && exit 3\" failed, so no mutants were tested",
+        ));
+    let log = fs::read_to_string(
+        tmp_src_dir
+            .path()
+            .join("mutants.out/log/baseline-command.log"),
+    )
+    .unwrap();
+    assert!(log.contains("setup failed"), "log: {log}");
+    assert!(!tmp_src_dir
+        .path()
+        .join("mutants.out/log/baseline.log")
+        .exists());
+    // The run finishes normally, rather than exiting from inside the lab.
+    let summary: serde_json::Value = fs::read_to_string(&summary_path).unwrap().parse().unwrap();
+    assert_eq!(summary["total_mutants"], 0);
+}
+
+#[test]
+fn baseline_command_runs_before_baseline() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .args([
+            "mutants",
+            "--check",
+            "--baseline-command",
+            "echo generating code",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    let log = fs::read_to_string(
+        tmp_src_dir
+            .path()
+            .join("mutants.out/log/baseline-command.log"),
+    )
+    .unwrap();
+    assert!(log.contains("generating code"), "log: {log}");
+}
+
 #[test]
 fn already_failing_doctests_are_detected() {
     let tmp_src_dir = copy_of_testdata("already_failing_doctests");
@@ -1045,7 +1582,7 @@ fn timeout_when_unmutated_tree_test_hangs() {
     let tmp_src_dir = copy_of_testdata("already_hangs");
     run()
         .arg("mutants")
-        .args(["--timeout", "2.9"])
+        .args(["--baseline-timeout", "2.9"])
         .current_dir(tmp_src_dir.path())
         .env_remove("RUST_BACKTRACE")
         .timeout(OUTER_TIMEOUT)
@@ -1058,6 +1595,20 @@ fn timeout_when_unmutated_tree_test_hangs() {
         ));
 }
 
+#[test]
+fn invalid_baseline_timeout_is_rejected() {
+    for value in ["-1", "0", "NaN", "inf"] {
+        run()
+            .args(["mutants", "--list"])
+            .arg(format!("--baseline-timeout={value}"))
+            .assert()
+            .code(1)
+            .stderr(contains(format!(
+                "invalid value '{value}' for '--baseline-timeout <SECS>': must be a positive number of seconds"
+            )));
+    }
+}
+
 /// If the test hangs and the user (in this case the test suite) interrupts it, then
 /// the `cargo test` child should be killed.
 ///
@@ -1179,6 +1730,372 @@ fn mutants_causing_tests_to_hang_are_stopped_by_manual_timeout() {
     // TODO: Inspect outcomes.json.
 }
 
+/// `explain` doesn't test the baseline, so without `--timeout` a hanging mutant is
+/// stopped after the minimum test timeout.
+#[test]
+fn explain_stops_hanging_mutant_after_minimum_timeout() {
+    let tmp_src_dir = copy_of_testdata("hang_when_mutated");
+    let output = run()
+        .args(["mutants", "--list", "--json"])
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap();
+    let mutants: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let hanging = mutants
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| {
+            m["function"] == "should_stop" && m["replacement"] == "false" && m["genre"] == "FnValue"
+        })
+        .unwrap();
+    run()
+        .args(["mutants", "--minimum-test-timeout", "5", "explain"])
+        .arg(hanging["id"].as_str().unwrap())
+        .current_dir(tmp_src_dir.path())
+        .env_remove("RUST_BACKTRACE")
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .code(3) // exit_code::TIMEOUT
+        .stdout(contains(
+            "Timeout: the tests didn't finish within the timeout",
+        ));
+}
+
+/// With `--timeout-is`, mutants that time out can be counted as caught or missed,
+/// which changes the exit code.
+#[test]
+fn timeout_is_option_controls_classification_of_timeouts() {
+    let tmp_src_dir = copy_of_testdata("hang_when_mutated");
+    for (policy, exit_code, summary) in [
+        ("caught", 0, "1 timeouts counted as caught"), // exit_code::SUCCESS
+        ("survived", 2, "1 timeouts counted as missed"), // exit_code::FOUND_PROBLEMS
+    ] {
+        run()
+            .arg("mutants")
+            .args(["-t", "8.1", "--timeout-is", policy])
+            .args(["-F", "should_stop -> bool with false"])
+            .current_dir(tmp_src_dir.path())
+            .env_remove("RUST_BACKTRACE")
+            .timeout(OUTER_TIMEOUT)
+            .assert()
+            .code(exit_code)
+            .stdout(contains(
+                "replace should_stop -> bool with false ... TIMEOUT",
+            ))
+            .stdout(contains(summary));
+    }
+}
+
+/// Generous resource limits don't stop the mutants being tested, and are set in a shell
+/// that runs the mutant's tests, but not the baseline.
+#[cfg(unix)]
+#[test]
+fn resource_limits_are_applied_to_mutant_tests() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .arg("mutants")
+        .args(["--memory-limit", "16G", "--cpu-limit", "60"])
+        .current_dir(tmp_src_dir.path())
+        .timeout(OUTER_TIMEOUT)
+        .assert()
+        .success();
+    let log_dir = tmp_src_dir.path().join("mutants.out/log");
+    let baseline_log = fs::read_to_string(log_dir.join("baseline.log")).unwrap();
+    assert!(!baseline_log.contains("ulimit"));
+    let mutant_log = fs::read_to_string(log_dir.join("src__lib.rs_line_4.log")).unwrap();
+    assert!(
+        mutant_log.contains("*** run sh -c ulimit -S -v 16777216 && ulimit -S -t 60 && exec"),
+        "mutant log should show limits: {mutant_log}"
+    );
+}
+
+#[test]
+fn output_format_jsonl_streams_mutant_outcomes() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let output = run()
+        .args(["mutants", "--output-format", "jsonl"])
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let outcomes = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(outcomes.len(), 2);
+    for outcome in outcomes {
+        assert_eq!(outcome["summary"], "CaughtMutant");
+        assert_eq!(outcome["scenario"]["Mutant"]["function"], "factorial");
+    }
+}
+
+#[test]
+fn output_format_jsonl_sends_requested_reports_to_stderr() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let template_path = tmp_src_dir.path().join("template.txt");
+    fs::write(&template_path, "MUTANT {outcome}\n").unwrap();
+    let output = run()
+        .args(["mutants", "--output-format", "jsonl", "--report-template"])
+        .arg(&template_path)
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(!stdout.contains("MUTANT"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("MUTANT caught").count(), 2, "{stderr}");
+}
+
+#[test]
+fn explain_tests_one_mutant_by_id() {
+    let tmp_src_dir = copy_of_testdata("error_value");
+    let output = run()
+        .args(["mutants", "--list", "--json"])
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap();
+    let mutants: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let missed = mutants
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["replacement"] == "Err(\"injected\")")
+        .unwrap();
+    run()
+        .args(["mutants", "explain", missed["id"].as_str().unwrap()])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(
+            "Missed: the tests passed with this mutant applied",
+        ))
+        .stdout(contains("*** mutation diff"))
+        .stdout(contains("test result: ok"));
+    // The results of earlier runs in mutants.out are kept.
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+}
+
+#[test]
+fn explain_unknown_id_fails() {
+    let tmp_src_dir = copy_of_testdata("error_value");
+    run()
+        .args(["mutants", "explain", "000000000000"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(contains("No mutant has id \"000000000000\""));
+}
+
+/// `--confirm` can't ask anything when not run from a terminal, so it tests all the mutants.
+#[test]
+fn confirm_without_terminal_tests_all_mutants() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .args(["mutants", "--confirm", "--no-times"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "--confirm needs an interactive terminal; testing all mutants without asking",
+        ))
+        .stdout(contains("2 mutants tested: 2 caught"));
+}
+
+#[test]
+fn invalid_memory_limit_is_rejected() {
+    run()
+        .args(["mutants", "--memory-limit", "lots", "--list"])
+        .assert()
+        .code(1)
+        .stderr(contains(
+            "invalid value 'lots' for '--memory-limit <SIZE>': not a number",
+        ));
+}
+
+#[test]
+fn invalid_cpu_limit_is_rejected() {
+    for value in ["-1", "0", "NaN", "inf"] {
+        run()
+            .args(["mutants", "--list"])
+            .arg(format!("--cpu-limit={value}"))
+            .assert()
+            .code(1)
+            .stderr(contains(format!(
+                "invalid value '{value}' for '--cpu-limit <SECS>': must be a positive number of seconds"
+            )));
+    }
+    run()
+        .args(["mutants", "--cpu-limit", "soon", "--list"])
+        .assert()
+        .code(1)
+        .stderr(contains("not a number"));
+}
+
+/// `--fail-under` makes the exit code depend on the mutation score rather than on whether
+/// any mutants were missed.
+#[test]
+fn fail_under_gates_on_mutation_score() {
+    // This tree has 3 caught and 2 missed mutants.
+    let tmp_src_dir = copy_of_testdata("error_value");
+    for (threshold, exit_code, summary) in [
+        ("50", 0, "; mutation score 60.0%\n"), // exit_code::SUCCESS
+        (
+            "70",
+            2,
+            "; mutation score 60.0% is below the required 70%\n",
+        ), // exit_code::FOUND_PROBLEMS
+    ] {
+        run()
+            .args(["mutants", "--no-times", "--fail-under", threshold])
+            .current_dir(tmp_src_dir.path())
+            .assert()
+            .code(exit_code)
+            .stdout(contains(summary));
+    }
+    run()
+        .args(["mutants", "--fail-under", "101"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1)
+        .stderr(contains("must be between 0 and 100"));
+}
+
+/// `--stop-function-after-caught` skips the rest of a function's mutants once enough in a
+/// row were caught.
+#[test]
+fn stop_function_after_caught_assumes_the_rest_are_caught() {
+    // factorial has two mutants, and both are caught.
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    run()
+        .args([
+            "mutants",
+            "--no-times",
+            "--no-shuffle",
+            "--caught",
+            "--stop-function-after-caught",
+            "1",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(contains(indoc! { "
+            src/lib.rs:4: replace factorial -> u32 with 0 ... caught
+            src/lib.rs:4: replace factorial -> u32 with 1 ... assumed caught
+        " }))
+        .stdout(contains("1 mutant tested: 1 caught, 1 assumed caught\n"));
+    assert_eq!(
+        fs::read_to_string(tmp_src_dir.path().join("mutants.out/assumed_caught.txt")).unwrap(),
+        "src/lib.rs:4: replace factorial -> u32 with 1\n"
+    );
+    run()
+        .args(["mutants", "--stop-function-after-caught", "0"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn badge_json_shows_mutation_score() {
+    // This tree has 3 caught and 2 missed mutants.
+    let tmp_src_dir = copy_of_testdata("error_value");
+    let badge_path = tmp_src_dir.path().join("badge.json");
+    run()
+        .args(["mutants", "--badge-json"])
+        .arg(&badge_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    let badge: serde_json::Value = fs::read_to_string(&badge_path).unwrap().parse().unwrap();
+    assert_eq!(
+        badge,
+        serde_json::json!({
+            "schemaVersion": 1,
+            "label": "mutation score",
+            "message": "60%",
+            "color": "yellow",
+        })
+    );
+}
+
+#[test]
+fn summary_json_has_counts_and_score() {
+    // This tree has 3 caught and 2 missed mutants.
+    let tmp_src_dir = copy_of_testdata("error_value");
+    let summary_path = tmp_src_dir.path().join("summary.json");
+    run()
+        .args(["mutants", "--summary-json"])
+        .arg(&summary_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    let mut summary: serde_json::Value =
+        fs::read_to_string(&summary_path).unwrap().parse().unwrap();
+    assert!(summary["elapsed_secs"].as_f64().unwrap() > 0.0);
+    summary.as_object_mut().unwrap().remove("elapsed_secs");
+    assert_eq!(
+        summary,
+        serde_json::json!({
+            "total_mutants": 5,
+            "missed": 2,
+            "caught": 3,
+            "timeout": 0,
+            "unviable": 0,
+            "success": 0,
+            "failure": 0,
+            "assumed_caught": 0,
+            "mutation_score": 60.0,
+        })
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn on_finish_command_gets_outcome_in_environment() {
+    // This tree has 3 caught and 2 missed mutants.
+    let tmp_src_dir = copy_of_testdata("error_value");
+    run()
+        .args([
+            "mutants",
+            "--on-finish",
+            "echo $CARGO_MUTANTS_EXIT_CODE $CARGO_MUTANTS_MUTATION_SCORE >hook.txt; \
+             cp $CARGO_MUTANTS_SUMMARY hook.json",
+        ])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2);
+    assert_eq!(
+        fs::read_to_string(tmp_src_dir.path().join("hook.txt")).unwrap(),
+        "2 60.0\n"
+    );
+    let summary: serde_json::Value = fs::read_to_string(tmp_src_dir.path().join("hook.json"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(summary["missed"], 2);
+    assert_eq!(summary["caught"], 3);
+}
+
+#[test]
+fn group_output_by_file_lists_missed_mutants_under_each_file() {
+    let tmp_src_dir = copy_of_testdata("error_value");
+    run()
+        .args(["mutants", "--no-times", "--group-output-by", "file"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .stdout(contains(indoc! { r#"
+            Missed mutants by file:
+
+            src/lib.rs
+                   3: replace even_is_ok -> Result<u32, &'static str> with Err("injected")
+                   4: replace if n % 2 == 0 { Ok(n) } else { Err("number is odd") } with { Err("number is odd") } in even_is_ok
+        "# }));
+}
+
 #[test]
 fn log_file_names_are_short_and_dont_collide() {
     // The "well_tested" tree can generate multiple mutants from single lines. They get distinct file names.
@@ -1376,3 +2293,161 @@ fn completions_option_generates_something() {
             .stdout(predicate::str::is_empty().not());
     }
 }
+
+#[test]
+fn clean_removes_output_and_exits() {
+    let tmp_src_dir = copy_of_testdata("factorial");
+    fs::create_dir_all(tmp_src_dir.path().join("mutants.out/log")).unwrap();
+    fs::create_dir_all(tmp_src_dir.path().join("mutants.out.old/log")).unwrap();
+    run()
+        .args(["mutants", "--clean"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Removed ").and(predicate::str::contains("mutants.out.old")),
+        );
+    assert!(!tmp_src_dir.path().join("mutants.out").exists());
+    assert!(!tmp_src_dir.path().join("mutants.out.old").exists());
+    assert!(tmp_src_dir.path().join("Cargo.toml").exists());
+}
+
+/// With `--reuse-build-dir`, build directories are kept in `mutants.build`, and their
+/// source is refreshed from the tree on the next run, keeping the target directory.
+#[test]
+fn reuse_build_dir_keeps_build_dirs_between_runs() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let build_dirs = tmp_src_dir.path().join("mutants.build");
+    run()
+        .args(["mutants", "--reuse-build-dir", "-j", "2"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    assert!(build_dirs.join("0/target").is_dir());
+    assert!(build_dirs.join("1/target").is_dir());
+
+    // Leftovers from an earlier run, like an interrupted mutation, are replaced.
+    fs::write(build_dirs.join("0/src/lib.rs"), "syntax error").unwrap();
+    fs::write(build_dirs.join("0/junk.txt"), "junk").unwrap();
+    run()
+        .args(["mutants", "--reuse-build-dir", "-j", "2"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(build_dirs.join("0/src/lib.rs")).unwrap(),
+        fs::read_to_string(tmp_src_dir.path().join("src/lib.rs")).unwrap()
+    );
+    assert!(!build_dirs.join("0/junk.txt").exists());
+    assert!(build_dirs.join("0/target").is_dir());
+
+    run()
+        .args(["mutants", "--clean"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success();
+    assert!(!build_dirs.exists());
+}
+
+#[test]
+fn verbose_streams_cargo_output_prefixed_by_mutant_id() {
+    let tmp_src_dir = copy_of_testdata("small_well_tested");
+    let mutants_json = run()
+        .args(["mutants", "--list", "--json"])
+        .current_dir(tmp_src_dir.path())
+        .output()
+        .unwrap()
+        .stdout;
+    let mutants: serde_json::Value = serde_json::from_slice(&mutants_json).unwrap();
+    let first_id = mutants[0]["id"].as_str().unwrap().to_owned();
+    run()
+        .args(["mutants", "--verbose", "--no-shuffle", "-j", "2"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("[baseline] ")
+                .and(predicate::str::is_match(r"\[baseline\] .*test result: ok").unwrap())
+                .and(predicate::str::contains(format!("[{first_id}] "))),
+        );
+}
+
+#[test]
+fn max_mutants_per_function_caps_each_function() {
+    let tmp_src_dir = copy_of_testdata("well_tested");
+    run()
+        .args(["mutants", "--list", "--max-mutants-per-function", "1"])
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/simple_fns.rs:17: replace divisible_by_three -> bool with true\n",
+        ))
+        .stdout(predicate::str::contains("divisible_by_three -> bool with false").not())
+        .stdout(predicate::str::contains(
+            "Only testing 1 of 2 mutants in src/simple_fns.rs: divisible_by_three",
+        ));
+}
+
+#[test]
+fn report_template_prints_a_line_per_mutant() {
+    let tmp_src_dir = copy_of_testdata("error_value");
+    let template_path = tmp_src_dir.path().join("template.txt");
+    fs::write(
+        &template_path,
+        "MUTANT {outcome} {file}:{line} {function}\n",
+    )
+    .unwrap();
+    let output = run()
+        .args(["mutants", "--no-shuffle", "--report-template"])
+        .arg(&template_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .code(2)
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    let report_lines = stdout
+        .lines()
+        .filter(|line| line.starts_with("MUTANT "))
+        .collect::<Vec<_>>();
+    assert_eq!(report_lines.len(), 5);
+    assert_eq!(
+        report_lines
+            .iter()
+            .filter(|line| line.starts_with("MUTANT missed src/lib.rs:"))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn report_template_with_unknown_placeholder_is_an_error() {
+    let tmp_src_dir = copy_of_testdata("error_value");
+    let template_path = tmp_src_dir.path().join("template.txt");
+    fs::write(&template_path, "{file} {mutant}\n").unwrap();
+    run()
+        .args(["mutants", "--report-template"])
+        .arg(&template_path)
+        .current_dir(tmp_src_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown placeholder {mutant}"));
+}
+
+#[test]
+fn list_mutants_with_original_values() {
+    run()
+        .args(["mutants", "--list", "--show-original", "-d"])
+        .arg("testdata/tree/well_tested")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(indoc! { r#"
+            src/nested_function.rs:1: replace has_nested -> u32 with 0
+                original: inner() * inner()
+            "# }))
+        .stdout(predicate::str::contains(indoc! { r#"
+            src/methods.rs:16: replace Foo::double with ()
+            src/nested_function.rs"# }));
+}